@@ -1,4 +1,7 @@
-use clap::Parser;
+use crate::config::ConfigOverride;
+use crate::refresh::RefreshTask;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(name = "ccline")]
@@ -9,16 +12,219 @@ pub struct Cli {
     pub config: bool,
 
     /// Set theme
-    #[arg(short = 't', long = "theme")]
+    #[arg(short = 't', long = "theme", global = true)]
     pub theme: Option<String>,
 
+    /// Override a config value, e.g. `segments.git.options.show_sha=true` (repeatable)
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = ConfigOverride::from_arg, global = true)]
+    pub set: Vec<ConfigOverride>,
+
+    /// Turn a segment off, e.g. `cost` or `custom:name` (repeatable)
+    #[arg(long = "disable", value_name = "SEGMENT", global = true)]
+    pub disable: Vec<String>,
+
+    /// Override the style mode: plain, nerd_font or powerline
+    #[arg(long = "style", value_name = "MODE", global = true)]
+    pub style: Option<String>,
+
+    /// Maximum statusline width; lower-priority segments are dropped to fit
+    #[arg(long = "max-width", global = true)]
+    pub max_width: Option<u16>,
+
+    /// Directory for config, caches and state (default: $CLAUDE_CONFIG_DIR/ccline or ~/.claude/ccline)
+    #[arg(long = "config-dir", global = true)]
+    pub config_dir: Option<PathBuf>,
+
+    /// Same as `ccline print`
+    #[arg(long = "print-config")]
+    pub print_config: bool,
+
+    /// Print segment timings, empty segments and model resolution to stderr
+    #[arg(long = "debug", global = true)]
+    pub debug: bool,
+
+    /// Write the debug report as JSON to this file
+    #[arg(long = "debug-report", value_name = "FILE", global = true)]
+    pub debug_report: Option<PathBuf>,
+
     /// Patch Claude Code cli.js to disable context warnings
     #[arg(long = "patch")]
     pub patch: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Create config.toml, models.toml and the built-in theme files
+    Init,
+    /// Validate config.toml and project .ccline.toml files, with line and column
+    /// numbers for each problem
+    Check,
+    /// Print the effective config for the current directory
+    Print,
+    /// Same as `ccline check` and `ccline print`
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Let project .ccline.toml files run commands and set credential options;
+    /// editing a trusted file revokes its trust
+    Trust {
+        /// Project file (default: every .ccline.toml from the current directory up)
+        path: Option<PathBuf>,
+        /// Remove the trust instead
+        #[arg(long)]
+        revoke: bool,
+    },
+    /// List, apply, export and import themes
+    Theme {
+        #[command(subcommand)]
+        command: ThemeCommand,
+    },
+    /// Turn segments on and off and reorder them in config.toml
+    Segment {
+        #[command(subcommand)]
+        command: SegmentCommand,
+    },
+    /// Inspect model names, context limits and pricing
+    Models {
+        #[command(subcommand)]
+        command: ModelsCommand,
+    },
+    /// Render the statusline from a recorded JSON fixture, without recording spend
+    ///
+    /// With --dry-run nothing else changes either: custom commands and credential
+    /// helpers aren't run, caches aren't written and no background refresh is
+    /// started, so segments that depend on them show what's already cached.
+    Render {
+        /// Claude Code's statusline JSON (default: stdin)
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+        /// Render from the fixture and existing caches only, without side effects
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Save the JSON Claude Code pipes in to a file, then render as usual
+    Capture {
+        /// Fixture file (default: capture.json in the cache directory)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Print spend recorded across sessions by day, week and project
+    Report {
+        /// Number of days to list
+        #[arg(long, default_value_t = 7)]
+        days: u32,
+    },
+    /// Refresh a cached value in the background (started by the statusline)
+    #[command(hide = true)]
+    Refresh {
+        #[arg(value_enum)]
+        task: RefreshTask,
+        /// Directory or session the refresh is limited to
+        #[arg(long)]
+        scope: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Validate config.toml and project .ccline.toml files (same as `ccline check`)
+    Check,
+    /// Print the effective config for the current directory (same as `ccline print`)
+    Print,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ThemeCommand {
+    /// List built-in and custom themes, marking the current one
+    List,
+    /// Replace the config's styling with a theme, keeping budgets
+    Apply { name: String },
+    /// Write a theme, or the current config if no name is given, as TOML
+    Export {
+        name: Option<String>,
+        /// File to write instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Validate a theme file and add it to the themes directory
+    Import {
+        file: PathBuf,
+        /// Theme name (default: the file name without .toml)
+        #[arg(long)]
+        name: Option<String>,
+        /// Also apply the imported theme
+        #[arg(long)]
+        apply: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SegmentCommand {
+    /// Turn a segment on, adding it from the theme if the config doesn't have it
+    Enable {
+        /// Segment id, or `custom:<name>` for a named custom segment
+        segment: String,
+    },
+    /// Turn a segment off
+    Disable {
+        /// Segment id, or `custom:<name>` for a named custom segment
+        segment: String,
+    },
+    /// Change a segment's position or line
+    #[command(group(
+        clap::ArgGroup::new("target")
+            .required(true)
+            .multiple(true)
+            .args(["before", "after", "to", "line"])
+    ))]
+    Move {
+        /// Segment id, or `custom:<name>` for a named custom segment
+        segment: String,
+        /// Put it right before this segment
+        #[arg(long, conflicts_with_all = ["after", "to"])]
+        before: Option<String>,
+        /// Put it right after this segment
+        #[arg(long, conflicts_with = "to")]
+        after: Option<String>,
+        /// Put it at this position in the segment list (1-based)
+        #[arg(long)]
+        to: Option<usize>,
+        /// Render it on this statusline row (1-based)
+        #[arg(long)]
+        line: Option<u8>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ModelsCommand {
+    /// List model entries, context modifiers and built-in model families
+    List,
+    /// Show how a model id is displayed, its context limit and pricing
+    Test { id: String },
 }
 
 impl Cli {
     pub fn parse_args() -> Self {
         Self::parse()
     }
+
+    /// Config overrides from `CCLINE_*` variables, then from the command line,
+    /// so flags win over the environment
+    pub fn overrides(&self) -> Vec<ConfigOverride> {
+        let mut overrides = ConfigOverride::from_env();
+        if let Some(style) = &self.style {
+            overrides.push(ConfigOverride::style(style, "--style"));
+        }
+        overrides.extend(
+            self.disable
+                .iter()
+                .map(|segment| ConfigOverride::disable(segment, "--disable")),
+        );
+        overrides.extend(self.set.iter().cloned());
+        overrides
+    }
 }
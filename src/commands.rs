@@ -0,0 +1,437 @@
+//! Subcommands: non-interactive config management for scripts and
+//! provisioning, and rendering from recorded fixtures.
+//!
+//! Commands that change the configuration edit the global `config.toml`;
+//! project `.ccline.toml` files are left alone.
+
+use crate::cli::{Cli, Command, ConfigCommand, ModelsCommand, SegmentCommand, ThemeCommand};
+use crate::config::{
+    project, schema, Config, InitResult, InputData, ModelConfig, SegmentConfig, SegmentId,
+};
+use crate::core::{collect_all_segments, DebugReport, StatusLineGenerator};
+use crate::ledger::Ledger;
+use crate::ui::themes::ThemePresets;
+use std::io::Read;
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Instant;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Run a configuration subcommand
+pub fn run(command: &Command, cli: &Cli) -> Result<ExitCode> {
+    let result = match command {
+        Command::Init => init(),
+        Command::Check
+        | Command::Config {
+            command: ConfigCommand::Check,
+        } => return check(),
+        Command::Print
+        | Command::Config {
+            command: ConfigCommand::Print,
+        } => print(cli),
+        Command::Trust { path, revoke } => trust(path.as_deref(), *revoke),
+        Command::Theme { command } => theme(command),
+        Command::Segment { command } => segment(command),
+        Command::Models { command } => {
+            let models = ModelConfig::load();
+            match command {
+                ModelsCommand::List => models.print_list(),
+                ModelsCommand::Test { id } => models.print_resolution(id),
+            }
+            Ok(())
+        }
+        Command::Render { input, dry_run } => {
+            if *dry_run {
+                crate::utils::dry_run::enable();
+            }
+            let input: InputData = match input {
+                Some(path) => {
+                    let content = std::fs::read_to_string(path)
+                        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                    serde_json::from_str(&content)
+                        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?
+                }
+                None => serde_json::from_reader(std::io::stdin().lock())?,
+            };
+            render(cli, &input, false)
+        }
+        Command::Capture { output } => {
+            let mut raw = Vec::new();
+            std::io::stdin().read_to_end(&mut raw)?;
+
+            let path = output
+                .clone()
+                .unwrap_or_else(|| crate::utils::paths::cache_dir().join("capture.json"));
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, &raw)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            eprintln!("Captured input to {}", path.display());
+
+            let input: InputData = serde_json::from_slice(&raw)?;
+            render(cli, &input, true)
+        }
+        Command::Report { days } => {
+            Ledger::load().print_report(*days);
+            Ok(())
+        }
+        Command::Refresh { task, scope } => {
+            crate::refresh::run(*task, scope.as_deref());
+            Ok(())
+        }
+    };
+    result.map(|()| ExitCode::SUCCESS)
+}
+
+/// Render and print the statusline for `input`, with `--debug` output if asked.
+/// With `record_spend`, the session's cost goes into the spend ledger when the
+/// config uses it.
+pub fn render(cli: &Cli, input: &InputData, record_spend: bool) -> Result<()> {
+    // Load configuration, with project .ccline.toml files merged over the global one.
+    // A broken file falls back to the global config or the defaults, and the
+    // error is shown below the statusline.
+    let mut config_errors = Vec::new();
+    let mut config = Config::load_for_dir(&input.workspace.current_dir).unwrap_or_else(|e| {
+        config_errors.push(e.to_string());
+        Config::load().unwrap_or_else(|_| Config::default())
+    });
+
+    // Apply theme override if provided
+    if let Some(theme) = &cli.theme {
+        config.switch_theme(theme);
+    }
+
+    // Apply --set/--disable/--style and CCLINE_* overrides. They aren't in
+    // any file `ccline check` reads, so a bad one gets its own hint.
+    let mut override_error = None;
+    if let Err(e) = config.apply_overrides(&cli.overrides()) {
+        override_error = Some(e.to_string());
+    }
+
+    // Record this session's cost for daily/weekly rollups
+    if record_spend && Ledger::is_used_by(&config) {
+        Ledger::record(input);
+    }
+
+    // Collect segment data, tracing each segment when debugging
+    let mut report = (cli.debug || cli.debug_report.is_some()).then(|| DebugReport::new(input));
+    let segments_data = match &mut report {
+        Some(report) => report.collect(&config, input),
+        None => collect_all_segments(&config, input),
+    };
+
+    // Width limit: CLI flag, then config, then the terminal's COLUMNS
+    let max_width = cli.max_width.or(config.style.max_width).or_else(|| {
+        std::env::var("COLUMNS")
+            .ok()
+            .and_then(|columns| columns.parse().ok())
+    });
+
+    // Render statusline
+    let render_started = Instant::now();
+    let generator = StatusLineGenerator::new(config).with_max_width(max_width);
+    let statusline = generator.generate(segments_data);
+
+    println!("{}", statusline);
+    for error in &config_errors {
+        println!("⚠ {} (see `ccline check`)", error);
+    }
+    if let Some(error) = &override_error {
+        println!("⚠ {} (overrides not applied)", error);
+    }
+    config_errors.extend(override_error);
+
+    if let Some(mut report) = report {
+        report.finish(&statusline, render_started.elapsed());
+        report.config_error = (!config_errors.is_empty()).then(|| config_errors.join("; "));
+        report.max_width = max_width;
+        if cli.debug {
+            report.print();
+        }
+        if let Some(path) = &cli.debug_report {
+            report.write(path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Fails through the exit code rather than an error, since the problems
+/// have already been printed
+fn check() -> Result<ExitCode> {
+    let current_dir = std::env::current_dir()?;
+    if schema::print_check(&current_dir.to_string_lossy()) {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+fn print(cli: &Cli) -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+    Config::print_layered(&current_dir.to_string_lossy(), &cli.overrides())
+}
+
+fn init() -> Result<()> {
+    match Config::init()? {
+        InitResult::Created(path) => println!("Created config at {}", path.display()),
+        InitResult::AlreadyExists(path) => println!("Config already exists at {}", path.display()),
+    }
+    // `ModelConfig::load` writes the commented template when there's none
+    ModelConfig::load();
+    Ok(())
+}
+
+fn trust(path: Option<&Path>, revoke: bool) -> Result<()> {
+    let paths = match path {
+        Some(path) if path.is_dir() => vec![path.join(project::PROJECT_CONFIG_FILE)],
+        Some(path) => vec![path.to_path_buf()],
+        None => project::find_project_configs(&std::env::current_dir()?),
+    };
+    if paths.is_empty() {
+        return Err(format!(
+            "No {} found from the current directory",
+            project::PROJECT_CONFIG_FILE
+        )
+        .into());
+    }
+
+    let mut config = Config::load()?;
+    for path in &paths {
+        if revoke {
+            let trusted = project::trust_path(path);
+            config
+                .trusted_projects
+                .retain(|entry| entry.path != trusted);
+            println!("Revoked trust in {}", path.display());
+            continue;
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let entry = project::trust_entry(path, &content);
+        config
+            .trusted_projects
+            .retain(|trusted| trusted.path != entry.path);
+        config.trusted_projects.push(entry);
+
+        println!("Trusted {}", path.display());
+        // Show what the file is now allowed to do
+        let mut table: toml::Table = toml::from_str(&content)?;
+        for key in project::remove_untrusted_options(&mut table) {
+            println!("  allows {}", key);
+        }
+    }
+    config.check()?;
+    config.save()
+}
+
+fn theme(command: &ThemeCommand) -> Result<()> {
+    match command {
+        ThemeCommand::List => {
+            let current = Config::load()?.theme;
+            let builtin: Vec<&str> = ThemePresets::get_available_themes()
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect();
+            for name in ThemePresets::list_available_themes() {
+                let marker = if name == current { "*" } else { " " };
+                let kind = if builtin.contains(&name.as_str()) {
+                    "built-in"
+                } else {
+                    "custom"
+                };
+                println!("{} {:<24} {}", marker, name, kind);
+            }
+            Ok(())
+        }
+        ThemeCommand::Apply { name } => {
+            ensure_theme_exists(name)?;
+            apply_theme(name)
+        }
+        ThemeCommand::Export { name, output } => {
+            let mut config = match name {
+                Some(name) => {
+                    ensure_theme_exists(name)?;
+                    ThemePresets::get_theme(name)
+                }
+                None => Config::load()?,
+            };
+            // Trusted project files are specific to this machine
+            config.trusted_projects.clear();
+            let content = toml::to_string_pretty(&config)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(path, content)?;
+                    println!("Wrote {} to {}", config.theme, path.display());
+                }
+                None => print!("{}", content),
+            }
+            Ok(())
+        }
+        ThemeCommand::Import { file, name, apply } => {
+            let name = match name {
+                Some(name) => name.clone(),
+                None => theme_name_from_path(file)?,
+            };
+            let theme = schema::read_file(file)?.config;
+            theme.check()?;
+            ThemePresets::save_theme(&name, &theme)?;
+            println!("Imported theme {}", name);
+
+            if *apply {
+                apply_theme(&name)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn ensure_theme_exists(name: &str) -> Result<()> {
+    if ThemePresets::list_available_themes()
+        .iter()
+        .any(|theme| theme == name)
+    {
+        Ok(())
+    } else {
+        Err(format!("Unknown theme: {} (see `ccline theme list`)", name).into())
+    }
+}
+
+fn apply_theme(name: &str) -> Result<()> {
+    let mut config = Config::load()?;
+    config.switch_theme(name);
+    config.check()?;
+    config.save()?;
+    println!("Applied theme {}", name);
+    Ok(())
+}
+
+fn theme_name_from_path(path: &Path) -> Result<String> {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| !stem.is_empty())
+        .map(|stem| stem.to_string())
+        .ok_or_else(|| format!("Can't name a theme after {}; pass --name", path.display()).into())
+}
+
+fn segment(command: &SegmentCommand) -> Result<()> {
+    let mut config = Config::load()?;
+
+    let message = match command {
+        SegmentCommand::Enable { segment } => {
+            let selector = Selector::parse(segment)?;
+            match selector.position(&config) {
+                Some(index) => config.segments[index].enabled = true,
+                None => {
+                    let added = selector.template(&config.theme)?;
+                    config.segments.push(added);
+                }
+            }
+            format!("Enabled {}", segment)
+        }
+        SegmentCommand::Disable { segment } => {
+            let index = Selector::parse(segment)?.require(&config)?;
+            config.segments[index].enabled = false;
+            format!("Disabled {}", segment)
+        }
+        SegmentCommand::Move {
+            segment,
+            before,
+            after,
+            to,
+            line,
+        } => {
+            let index = Selector::parse(segment)?.require(&config)?;
+            if let Some(line) = line {
+                config.segments[index].line = *line;
+            }
+
+            let moved = config.segments.remove(index);
+            let target = if let Some(before) = before {
+                Some(Selector::parse(before)?.require(&config)?)
+            } else if let Some(after) = after {
+                Some(Selector::parse(after)?.require(&config)? + 1)
+            } else {
+                to.map(|to| to.saturating_sub(1).min(config.segments.len()))
+            };
+            config.segments.insert(target.unwrap_or(index), moved);
+
+            let order: Vec<String> = config.segments.iter().map(Selector::label).collect();
+            format!("Segments: {}", order.join(", "))
+        }
+    };
+
+    config.check()?;
+    config.save()?;
+    println!("{}", message);
+    Ok(())
+}
+
+/// Segment named on the command line: an id, or `id:name` for a named instance
+struct Selector {
+    id: SegmentId,
+    name: Option<String>,
+}
+
+impl Selector {
+    fn parse(arg: &str) -> Result<Self> {
+        let (id, name) = match arg.split_once(':') {
+            Some((id, name)) => (id, Some(name.to_string())),
+            None => (arg, None),
+        };
+        let id = toml::Value::String(id.trim().to_string())
+            .try_into::<SegmentId>()
+            .map_err(|_| format!("Unknown segment: {}", id))?;
+        Ok(Self { id, name })
+    }
+
+    fn matches(&self, segment: &SegmentConfig) -> bool {
+        segment.id == self.id
+            && self
+                .name
+                .as_deref()
+                .is_none_or(|name| segment.instance_name() == Some(name))
+    }
+
+    fn position(&self, config: &Config) -> Option<usize> {
+        config
+            .segments
+            .iter()
+            .position(|segment| self.matches(segment))
+    }
+
+    fn require(&self, config: &Config) -> Result<usize> {
+        self.position(config)
+            .ok_or_else(|| format!("No {} segment in the config", self.describe()).into())
+    }
+
+    fn describe(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{:?} ({})", self.id, name),
+            None => format!("{:?}", self.id),
+        }
+    }
+
+    /// A new segment styled by the theme. Custom segments need a command,
+    /// which the command line can't supply.
+    fn template(&self, theme: &str) -> Result<SegmentConfig> {
+        if self.id == SegmentId::Custom {
+            return Err("Custom segments need a command; add them in config.toml".into());
+        }
+        Ok(crate::config::project::segment_template(self.id, theme))
+    }
+
+    fn label(segment: &SegmentConfig) -> String {
+        let id = toml::Value::try_from(segment.id)
+            .ok()
+            .and_then(|id| id.as_str().map(|id| id.to_string()))
+            .unwrap_or_default();
+        match segment.instance_name() {
+            Some(name) => format!("{}:{}", id, name),
+            None => id,
+        }
+    }
+}
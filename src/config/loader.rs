@@ -1,4 +1,5 @@
-use super::types::Config;
+use super::types::{Config, SegmentId};
+use crate::utils::paths;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -19,9 +20,7 @@ impl ConfigLoader {
     }
 
     pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<Config, Box<dyn std::error::Error>> {
-        let content = fs::read_to_string(path)?;
-        let config: Config = toml::from_str(&content)?;
-        Ok(config)
+        Ok(super::schema::read_file(path.as_ref())?.config)
     }
 
     /// Initialize themes directory and create built-in theme files
@@ -63,19 +62,17 @@ impl ConfigLoader {
         Ok(())
     }
 
-    /// Get the themes directory path (~/.claude/ccline/themes/)
+    /// Get the themes directory path (~/.claude/ccline/themes/ by default)
     pub fn get_themes_path() -> PathBuf {
-        if let Some(home) = dirs::home_dir() {
-            home.join(".claude").join("ccline").join("themes")
-        } else {
-            PathBuf::from(".claude/ccline/themes")
-        }
+        paths::themes_dir()
     }
 
     /// Ensure themes directory exists and has built-in themes (silent mode)
     pub fn ensure_themes_exist() {
         // Silently ensure themes exist without printing output
-        let _ = Self::init_themes_silent();
+        if !crate::utils::dry_run::is_enabled() {
+            let _ = Self::init_themes_silent();
+        }
     }
 
     /// Initialize themes directory and create built-in theme files (silent mode)
@@ -123,9 +120,7 @@ impl Config {
             return Ok(Config::default());
         }
 
-        let content = fs::read_to_string(config_path)?;
-        let config: Config = toml::from_str(&content)?;
-        Ok(config)
+        super::schema::load_and_migrate(&config_path)
     }
 
     /// Save configuration to default location
@@ -142,13 +137,18 @@ impl Config {
         Ok(())
     }
 
-    /// Get the default config file path (~/.claude/ccline/config.toml)
+    /// Replace the segments and style with a theme's, keeping what a theme
+    /// doesn't carry: budgets and trusted project files
+    pub fn switch_theme(&mut self, name: &str) {
+        let mut themed = crate::ui::themes::ThemePresets::get_theme(name);
+        themed.budget = std::mem::take(&mut self.budget);
+        themed.trusted_projects = std::mem::take(&mut self.trusted_projects);
+        *self = themed;
+    }
+
+    /// Get the config file path (~/.claude/ccline/config.toml by default)
     fn get_config_path() -> PathBuf {
-        if let Some(home) = dirs::home_dir() {
-            home.join(".claude").join("ccline").join("config.toml")
-        } else {
-            PathBuf::from(".claude/ccline/config.toml")
-        }
+        paths::config_file()
     }
 
     /// Initialize config directory and create default config
@@ -180,11 +180,87 @@ impl Config {
             return Err("No segments configured".into());
         }
 
-        // Validate segment IDs are unique
+        // Validate segment IDs are unique; only custom segments can repeat,
+        // distinguished by name
         let mut seen_ids = std::collections::HashSet::new();
         for segment in &self.segments {
-            if !seen_ids.insert(segment.id) {
-                return Err(format!("Duplicate segment ID: {:?}", segment.id).into());
+            let name = match segment.id {
+                SegmentId::Custom => segment.instance_name(),
+                _ => None,
+            };
+            if !seen_ids.insert((segment.id, name)) {
+                return match (segment.id, name) {
+                    (SegmentId::Custom, Some(name)) => {
+                        Err(format!("Duplicate custom segment name: {}", name).into())
+                    }
+                    (SegmentId::Custom, None) => Err(
+                        "Duplicate segment ID: Custom; add `options.name` to distinguish custom segments"
+                            .into(),
+                    ),
+                    _ => Err(format!("Duplicate segment ID: {:?}", segment.id).into()),
+                };
+            }
+        }
+
+        // Validate custom segments have a command
+        for segment in &self.segments {
+            if segment.id == SegmentId::Custom
+                && segment
+                    .options
+                    .get("command")
+                    .and_then(|v| v.as_str())
+                    .is_none_or(|c| c.trim().is_empty())
+            {
+                return Err(format!(
+                    "Custom segment {} has no command",
+                    segment.instance_name().unwrap_or("(unnamed)")
+                )
+                .into());
+            }
+        }
+
+        // Validate line numbers (1-based)
+        if let Some(segment) = self.segments.iter().find(|s| s.line == 0) {
+            return Err(format!("Segment {:?}: line must be 1 or greater", segment.id).into());
+        }
+
+        // Validate threshold rules
+        for segment in &self.segments {
+            for rule in &segment.thresholds {
+                if rule.metric.trim().is_empty() {
+                    return Err(
+                        format!("Segment {:?}: threshold rule has no metric", segment.id).into(),
+                    );
+                }
+                if !rule.value.is_finite() {
+                    return Err(format!(
+                        "Segment {:?}: threshold value for {} must be a number",
+                        segment.id, rule.metric
+                    )
+                    .into());
+                }
+            }
+        }
+
+        // Validate budgets
+        for (name, limit) in [
+            ("session", self.budget.session),
+            ("daily", self.budget.daily),
+            ("monthly", self.budget.monthly),
+        ] {
+            if limit.is_some_and(|limit| !limit.is_finite() || limit <= 0.0) {
+                return Err(format!("Budget {} must be a positive amount", name).into());
+            }
+        }
+
+        // Validate format templates
+        for segment in &self.segments {
+            if let Some(format) = segment.options.get("format") {
+                let source = format
+                    .as_str()
+                    .ok_or_else(|| format!("Segment {:?}: format must be a string", segment.id))?;
+                crate::core::Template::parse(source)
+                    .map_err(|e| format!("Segment {:?}: invalid format: {}", segment.id, e))?;
             }
         }
 
@@ -1,8 +1,13 @@
 pub mod defaults;
 pub mod loader;
 pub mod models;
+pub mod overrides;
+pub mod project;
+pub mod schema;
 pub mod types;
 
 pub use loader::{ConfigLoader, InitResult};
 pub use models::*;
+pub use overrides::ConfigOverride;
+pub use project::LayeredConfig;
 pub use types::*;
@@ -1,3 +1,4 @@
+use crate::utils::paths;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -17,6 +18,105 @@ pub struct ModelEntry {
     pub pattern: String,
     pub display_name: String,
     pub context_limit: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<ModelPricing>,
+}
+
+/// Token prices in USD per million tokens, used to estimate session cost
+/// when Claude Code doesn't report one.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+    /// Defaults to 1.25x the input price (Anthropic's 5-minute cache write rate)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write: Option<f64>,
+    /// Defaults to 0.1x the input price (Anthropic's cache read rate)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read: Option<f64>,
+    /// Rates for requests whose prompt exceeds [`LONG_CONTEXT_THRESHOLD`] tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub long_context: Option<LongContextPricing>,
+}
+
+/// Prompt size in tokens above which long context pricing applies to the whole request
+pub const LONG_CONTEXT_THRESHOLD: u64 = 200_000;
+
+/// Whether a request with this many prompt tokens, counting cache writes and
+/// reads, is billed at long context rates
+pub fn is_long_context(prompt_tokens: u64) -> bool {
+    prompt_tokens > LONG_CONTEXT_THRESHOLD
+}
+
+/// Input and output prices in USD per million tokens for long context requests.
+/// Cache prices scale with the input price.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LongContextPricing {
+    pub input: f64,
+    pub output: f64,
+}
+
+impl ModelPricing {
+    fn new(input: f64, output: f64, cache_write: f64, cache_read: f64) -> Self {
+        Self {
+            input,
+            output,
+            cache_write: Some(cache_write),
+            cache_read: Some(cache_read),
+            long_context: None,
+        }
+    }
+
+    fn with_long_context(mut self, input: f64, output: f64) -> Self {
+        self.long_context = Some(LongContextPricing { input, output });
+        self
+    }
+
+    /// The rates for long context requests, or these rates if the model has none
+    pub fn long_context_rates(&self) -> ModelPricing {
+        let Some(long) = self.long_context else {
+            return *self;
+        };
+        let scale = long.input / self.input;
+        ModelPricing {
+            input: long.input,
+            output: long.output,
+            cache_write: Some(self.cache_write.unwrap_or(self.input * 1.25) * scale),
+            cache_read: Some(self.cache_read.unwrap_or(self.input * 0.1) * scale),
+            long_context: None,
+        }
+    }
+
+    /// e.g. `$3 in / $15 out / $3.75 cache write / $0.3 cache read per MTok`
+    pub fn describe(&self) -> String {
+        let mut description = format!(
+            "${} in / ${} out / ${} cache write / ${} cache read per MTok",
+            self.input,
+            self.output,
+            self.cache_write.unwrap_or(self.input * 1.25),
+            self.cache_read.unwrap_or(self.input * 0.1)
+        );
+        if let Some(long) = self.long_context {
+            description.push_str(&format!(
+                " (${} in / ${} out above {}k)",
+                long.input,
+                long.output,
+                LONG_CONTEXT_THRESHOLD / 1000
+            ));
+        }
+        description
+    }
+
+    /// Cost in USD of the given token counts
+    pub fn cost_usd(&self, input: u64, output: u64, cache_write: u64, cache_read: u64) -> f64 {
+        let cache_write_price = self.cache_write.unwrap_or(self.input * 1.25);
+        let cache_read_price = self.cache_read.unwrap_or(self.input * 0.1);
+        (input as f64 * self.input
+            + output as f64 * self.output
+            + cache_write as f64 * cache_write_price
+            + cache_read as f64 * cache_read_price)
+            / 1_000_000.0
+    }
 }
 
 /// Context modifier that overrides context limits and appends a suffix to display names.
@@ -28,6 +128,21 @@ pub struct ContextModifier {
     pub context_limit: u32,
 }
 
+/// Result of [`ModelConfig::explain`]
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelResolution {
+    pub model_id: String,
+    /// `None` when nothing matched and Claude Code's display name is used
+    pub display_name: Option<String>,
+    /// Which rule set the display name, e.g. "built-in Opus family"
+    pub matched: String,
+    pub modifier: Option<ContextModifier>,
+    pub context_limit: u32,
+    /// Nothing matched, so the context limit is the 200k default
+    pub default_context_limit: bool,
+    pub pricing: Option<ModelPricing>,
+}
+
 /// Built-in Claude model family definition (internal, not serialized).
 /// Uses regex with named capture groups to auto-extract version numbers from model IDs.
 ///
@@ -38,6 +153,8 @@ struct BuiltinModelFamily {
     regex: Regex,
     display_prefix: String,
     context_limit: u32,
+    /// `(minimum version, pricing)`, newest first
+    price_tiers: Vec<((u32, u32), ModelPricing)>,
 }
 
 impl BuiltinModelFamily {
@@ -62,9 +179,37 @@ impl BuiltinModelFamily {
             regex: Regex::new(&pattern).expect("built-in family regex should compile"),
             display_prefix: display_prefix.to_string(),
             context_limit,
+            price_tiers: Vec::new(),
         }
     }
 
+    /// Add a price tier for versions at or above `min_version`.
+    /// Tiers must be added newest first.
+    fn with_pricing(mut self, min_version: (u32, u32), pricing: ModelPricing) -> Self {
+        self.price_tiers.push((min_version, pricing));
+        self
+    }
+
+    /// Pricing for the matched model version, if any tier covers it
+    fn match_pricing(&self, model_id_lower: &str) -> Option<ModelPricing> {
+        let caps = self.regex.captures(model_id_lower)?;
+
+        let number = |post: &str, pre: &str| {
+            caps.name(post)
+                .or_else(|| caps.name(pre))
+                .and_then(|m| m.as_str().parse::<u32>().ok())
+        };
+        let version = (
+            number("post_major", "pre_major")?,
+            number("post_minor", "pre_minor").unwrap_or(0),
+        );
+
+        self.price_tiers
+            .iter()
+            .find(|(min_version, _)| version >= *min_version)
+            .map(|(_, pricing)| *pricing)
+    }
+
     /// Try to match a model ID (already lowercased) and extract a formatted display name.
     /// Returns `None` if the model ID doesn't match this family.
     fn match_model(&self, model_id_lower: &str) -> Option<String> {
@@ -99,9 +244,19 @@ impl ModelConfig {
     fn builtin_families() -> &'static [BuiltinModelFamily] {
         BUILTIN_FAMILIES.get_or_init(|| {
             vec![
-                BuiltinModelFamily::new("sonnet", "Sonnet", 200_000),
-                BuiltinModelFamily::new("opus", "Opus", 200_000),
-                BuiltinModelFamily::new("haiku", "Haiku", 200_000),
+                BuiltinModelFamily::new("sonnet", "Sonnet", 200_000)
+                    .with_pricing(
+                        (4, 0),
+                        ModelPricing::new(3.0, 15.0, 3.75, 0.3).with_long_context(6.0, 22.5),
+                    )
+                    .with_pricing((0, 0), ModelPricing::new(3.0, 15.0, 3.75, 0.3)),
+                BuiltinModelFamily::new("opus", "Opus", 200_000)
+                    .with_pricing((4, 5), ModelPricing::new(5.0, 25.0, 6.25, 0.5))
+                    .with_pricing((0, 0), ModelPricing::new(15.0, 75.0, 18.75, 1.5)),
+                BuiltinModelFamily::new("haiku", "Haiku", 200_000)
+                    .with_pricing((4, 5), ModelPricing::new(1.0, 5.0, 1.25, 0.1))
+                    .with_pricing((3, 5), ModelPricing::new(0.8, 4.0, 1.0, 0.08))
+                    .with_pricing((0, 0), ModelPricing::new(0.25, 1.25, 0.3, 0.03)),
             ]
         })
     }
@@ -130,20 +285,15 @@ impl ModelConfig {
         let mut model_config = Self::default();
 
         // First, try to create default models.toml if it doesn't exist
-        if let Some(home_dir) = dirs::home_dir() {
-            let user_models_path = home_dir.join(".claude").join("ccline").join("models.toml");
-            if !user_models_path.exists() {
-                let _ = Self::create_default_file(&user_models_path);
-            }
+        let user_models_path = paths::models_file();
+        if !user_models_path.exists() && !crate::utils::dry_run::is_enabled() {
+            let _ = Self::create_default_file(&user_models_path);
         }
 
         // Try loading from user config directory first, then local
-        let config_paths = [
-            dirs::home_dir().map(|d| d.join(".claude").join("ccline").join("models.toml")),
-            Some(Path::new("models.toml").to_path_buf()),
-        ];
+        let config_paths = [user_models_path, Path::new("models.toml").to_path_buf()];
 
-        for path in config_paths.iter().flatten() {
+        for path in config_paths.iter() {
             if path.exists() {
                 if let Ok(config) = Self::load_from_file(path) {
                     // Prepend external models to built-in ones for priority
@@ -250,6 +400,149 @@ impl ModelConfig {
         suffix
     }
 
+    /// Get per-million token pricing for a model.
+    /// Priority: model entries with `pricing` > built-in Claude families > None.
+    pub fn get_pricing(&self, model_id: &str) -> Option<ModelPricing> {
+        let model_lower = model_id.to_lowercase();
+
+        self.model_entries
+            .iter()
+            .filter(|e| model_lower.contains(&e.pattern.to_lowercase()))
+            .find_map(|e| e.pricing)
+            .or_else(|| {
+                Self::builtin_families()
+                    .iter()
+                    .find_map(|family| family.match_pricing(&model_lower))
+            })
+    }
+
+    /// Print model entries, context modifiers and built-in families, in the
+    /// order they're matched
+    pub fn print_list(&self) {
+        println!("Model entries (substring match, first match wins):");
+        if self.model_entries.is_empty() {
+            println!("  (none)");
+        }
+        for entry in &self.model_entries {
+            println!(
+                "  {:<20} {:<18} {:>9}  {}",
+                entry.pattern,
+                entry.display_name,
+                entry.context_limit,
+                entry.pricing.map(|p| p.describe()).unwrap_or_default()
+            );
+        }
+
+        println!();
+        println!("Context modifiers:");
+        if self.context_modifiers.is_empty() {
+            println!("  (none)");
+        }
+        for modifier in &self.context_modifiers {
+            println!(
+                "  {:<20} {:<18} {:>9}",
+                modifier.pattern,
+                format!("\"{}\"", modifier.display_suffix),
+                modifier.context_limit
+            );
+        }
+
+        println!();
+        println!("Built-in families:");
+        for family in Self::builtin_families() {
+            let pricing = family
+                .price_tiers
+                .iter()
+                .map(|((major, minor), pricing)| match (major, minor) {
+                    (0, 0) if family.price_tiers.len() == 1 => pricing.describe(),
+                    (0, 0) => format!("older: {}", pricing.describe()),
+                    _ => format!("{}.{}+: {}", major, minor, pricing.describe()),
+                })
+                .collect::<Vec<_>>()
+                .join("; ");
+            println!(
+                "  {:<20} {:<18} {:>9}  {}",
+                family.display_prefix.to_lowercase(),
+                format!("{} <version>", family.display_prefix),
+                family.context_limit,
+                pricing
+            );
+        }
+    }
+
+    /// How a model id resolves, and which rule decided it
+    pub fn explain(&self, model_id: &str) -> ModelResolution {
+        let model_lower = model_id.to_lowercase();
+        let (display_name, context_limit, _) = self.resolve(model_id);
+
+        let matched = match self
+            .model_entries
+            .iter()
+            .find(|e| model_lower.contains(&e.pattern.to_lowercase()))
+        {
+            Some(entry) => format!("model entry `{}`", entry.pattern),
+            None => match Self::builtin_families()
+                .iter()
+                .find(|family| family.match_model(&model_lower).is_some())
+            {
+                Some(family) => format!("built-in {} family", family.display_prefix),
+                None => "nothing; Claude Code's display name is used".to_string(),
+            },
+        };
+
+        ModelResolution {
+            model_id: model_id.to_string(),
+            display_name,
+            matched,
+            modifier: self
+                .context_modifiers
+                .iter()
+                .find(|m| model_lower.contains(&m.pattern.to_lowercase()))
+                .cloned(),
+            context_limit,
+            default_context_limit: self.try_get_context_limit(model_id).is_none(),
+            pricing: self.get_pricing(model_id),
+        }
+    }
+
+    /// Print how a model id resolves: display name, which rule matched,
+    /// context limit and pricing
+    pub fn print_resolution(&self, model_id: &str) {
+        let resolution = self.explain(model_id);
+
+        println!("Model:          {}", resolution.model_id);
+        println!(
+            "Display name:   {}",
+            resolution
+                .display_name
+                .as_deref()
+                .unwrap_or("(not recognized)")
+        );
+        println!("Matched:        {}", resolution.matched);
+        if let Some(modifier) = &resolution.modifier {
+            println!(
+                "Modifier:       `{}` (suffix \"{}\")",
+                modifier.pattern, modifier.display_suffix
+            );
+        }
+        println!(
+            "Context limit:  {}{}",
+            resolution.context_limit,
+            if resolution.default_context_limit {
+                " (default)"
+            } else {
+                ""
+            }
+        );
+        println!(
+            "Pricing:        {}",
+            resolution
+                .pricing
+                .map(|pricing| pricing.describe())
+                .unwrap_or_else(|| "unknown; costs aren't estimated".to_string())
+        );
+    }
+
     /// Create default model configuration file with minimal template
     pub fn create_default_file<P: AsRef<Path>>(path: P) -> Result<(), Box<dyn std::error::Error>> {
         // Add comments and examples to the template
@@ -270,6 +563,10 @@ impl ModelConfig {
              # pattern = \"my-model\"\n\
              # display_name = \"My Model\"\n\
              # context_limit = 128000\n\
+             # # Optional USD per million tokens, for cost estimates\n\
+             # pricing = { input = 0.6, output = 2.2, cache_read = 0.11 }\n\
+             # # Add long_context = { input = 1.2, output = 4.4 } to pricing for\n\
+             # # higher rates on prompts above 200k tokens\n\
              \n\
              # Context modifiers override context limits and append suffix to display names\n\
              # They are matched independently, enabling composition:\n\
@@ -302,21 +599,25 @@ impl Default for ModelConfig {
                     pattern: "glm-4.5".to_string(),
                     display_name: "GLM-4.5".to_string(),
                     context_limit: 128_000,
+                    pricing: None,
                 },
                 ModelEntry {
                     pattern: "kimi-k2-turbo".to_string(),
                     display_name: "Kimi K2 Turbo".to_string(),
                     context_limit: 128_000,
+                    pricing: None,
                 },
                 ModelEntry {
                     pattern: "kimi-k2".to_string(),
                     display_name: "Kimi K2".to_string(),
                     context_limit: 128_000,
+                    pricing: None,
                 },
                 ModelEntry {
                     pattern: "qwen3-coder".to_string(),
                     display_name: "Qwen Coder".to_string(),
                     context_limit: 256_000,
+                    pricing: None,
                 },
             ],
             context_modifiers: vec![ContextModifier {
@@ -327,3 +628,84 @@ impl Default for ModelConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builtin_pricing(model_id: &str) -> Option<ModelPricing> {
+        ModelConfig {
+            model_entries: Vec::new(),
+            context_modifiers: Vec::new(),
+        }
+        .get_pricing(model_id)
+    }
+
+    #[test]
+    fn dated_and_qualified_ids_match_their_family_tier() {
+        for (model_id, input) in [
+            ("claude-sonnet-4-5-20250929", 3.0),
+            ("claude-3-5-sonnet-20241022", 3.0),
+            ("claude-opus-4-5-20251101", 5.0),
+            ("claude-opus-4-1-20250805", 15.0),
+            ("claude-4-opus-20250514", 15.0),
+            ("claude-haiku-4-5-20251001", 1.0),
+            ("claude-3-5-haiku-20241022", 0.8),
+            ("claude-3-haiku-20240307", 0.25),
+            ("claude-opus-4-6[1m]", 5.0),
+            ("claude-sonnet-4-5-thinking", 3.0),
+        ] {
+            assert_eq!(
+                builtin_pricing(model_id).map(|pricing| pricing.input),
+                Some(input),
+                "{}",
+                model_id
+            );
+        }
+        assert_eq!(builtin_pricing("gpt-4o"), None);
+    }
+
+    #[test]
+    fn long_context_applies_above_200k_prompt_tokens() {
+        assert!(!is_long_context(199_999));
+        assert!(!is_long_context(200_000));
+        assert!(is_long_context(200_001));
+
+        let sonnet_4 = builtin_pricing("claude-sonnet-4-20250514").unwrap();
+        let long = sonnet_4.long_context_rates();
+        assert_eq!((long.input, long.output), (6.0, 22.5));
+        assert_eq!((long.cache_write, long.cache_read), (Some(7.5), Some(0.6)));
+
+        // Older models have a single rate
+        let sonnet_3_7 = builtin_pricing("claude-3-7-sonnet-20250219").unwrap();
+        assert_eq!(sonnet_3_7.long_context_rates(), sonnet_3_7);
+    }
+
+    #[test]
+    fn cost_prices_each_token_kind() {
+        let pricing = ModelPricing::new(3.0, 15.0, 3.75, 0.3);
+        assert_eq!(pricing.cost_usd(1_000_000, 0, 0, 0), 3.0);
+        assert_eq!(pricing.cost_usd(0, 1_000_000, 0, 0), 15.0);
+        assert_eq!(pricing.cost_usd(0, 0, 1_000_000, 0), 3.75);
+        assert!((pricing.cost_usd(0, 0, 0, 1_000_000) - 0.3).abs() < 1e-9);
+        assert!((pricing.cost_usd(100_000, 10_000, 20_000, 500_000) - 0.675).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cache_prices_default_from_the_input_price() {
+        let entry: ModelEntry = toml::from_str(
+            r#"
+            pattern = "my-model"
+            display_name = "My Model"
+            context_limit = 128000
+            pricing = { input = 2.0, output = 8.0, long_context = { input = 4.0, output = 12.0 } }
+            "#,
+        )
+        .unwrap();
+        let pricing = entry.pricing.unwrap();
+        assert_eq!(pricing.cost_usd(0, 0, 1_000_000, 1_000_000), 2.5 + 0.2);
+
+        let long = pricing.long_context_rates();
+        assert_eq!((long.cache_write, long.cache_read), (Some(5.0), Some(0.4)));
+    }
+}
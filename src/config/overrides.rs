@@ -0,0 +1,412 @@
+use super::types::Config;
+use toml::{Table, Value};
+
+/// Prefix of environment variables holding overrides
+const ENV_PREFIX: &str = "CCLINE_";
+/// Separates key parts in environment variable names, e.g.
+/// `CCLINE_SEGMENTS__GIT__OPTIONS__SHOW_SHA`
+const ENV_KEY_SEPARATOR: &str = "__";
+
+/// A single `key=value` change applied on top of the loaded configuration,
+/// from the command line or the environment
+#[derive(Debug, Clone)]
+pub struct ConfigOverride {
+    /// Dotted key, e.g. `style.separator` or `segments.git.options.show_sha`.
+    /// Segments are selected by id, or `id:name` for a named custom segment.
+    pub key: String,
+    /// Raw value; parsed as a TOML value, or taken as a string if it isn't one
+    pub value: String,
+    /// Where the override came from, e.g. `--set` or `CCLINE_STYLE`
+    pub source: String,
+}
+
+impl ConfigOverride {
+    pub fn new(
+        key: impl Into<String>,
+        value: impl Into<String>,
+        source: impl Into<String>,
+    ) -> Self {
+        Self {
+            key: key.into(),
+            value: value.into(),
+            source: source.into(),
+        }
+    }
+
+    /// Parse a `--set key=value` argument
+    pub fn from_arg(arg: &str) -> Result<Self, String> {
+        let (key, value) = arg
+            .split_once('=')
+            .ok_or_else(|| format!("expected key=value, got `{}`", arg))?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(format!("missing key in `{}`", arg));
+        }
+        Ok(Self::new(key, value, "--set"))
+    }
+
+    /// `--disable <segment>`: turn a segment off
+    pub fn disable(segment: &str, source: &str) -> Self {
+        Self::new(
+            format!("segments.{}.enabled", segment.trim()),
+            "false",
+            source,
+        )
+    }
+
+    /// `--style <mode>`: `plain`, `nerd_font` or `powerline`
+    pub fn style(mode: &str, source: &str) -> Self {
+        Self::new("style.mode", mode.trim().replace('-', "_"), source)
+    }
+
+    /// Overrides from `CCLINE_*` environment variables:
+    ///
+    /// - `CCLINE_STYLE=plain`, like `--style`
+    /// - `CCLINE_DISABLE=cost,git`, like `--disable`
+    /// - `CCLINE_<KEY>` with `__` between key parts, like `--set`, e.g.
+    ///   `CCLINE_SEGMENTS__GIT__OPTIONS__SHOW_SHA=true`
+    ///
+    /// Other `CCLINE_*` variables, like the ones custom segment commands are
+    /// run with, are ignored.
+    pub fn from_env() -> Vec<Self> {
+        Self::from_vars(std::env::vars())
+    }
+
+    fn from_vars(vars: impl IntoIterator<Item = (String, String)>) -> Vec<Self> {
+        let mut vars: Vec<(String, String)> = vars
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+            .collect();
+        // Apply in a stable order
+        vars.sort();
+
+        let mut overrides = Vec::new();
+        for (name, value) in vars {
+            let key = &name[ENV_PREFIX.len()..];
+            match key {
+                "STYLE" => overrides.push(Self::style(&value, &name)),
+                "DISABLE" => overrides.extend(
+                    value
+                        .split(',')
+                        .filter(|segment| !segment.trim().is_empty())
+                        .map(|segment| Self::disable(segment, &name)),
+                ),
+                _ if key.contains(ENV_KEY_SEPARATOR) => {
+                    let key = key
+                        .split(ENV_KEY_SEPARATOR)
+                        .map(|part| part.to_lowercase())
+                        .collect::<Vec<_>>()
+                        .join(".");
+                    overrides.push(Self::new(key, value, name.as_str()));
+                }
+                _ => {}
+            }
+        }
+        overrides
+    }
+
+    /// The value as TOML: `true`, `3` or `[1, 2]` keep their types, anything
+    /// else is a string
+    fn typed_value(&self) -> Option<Value> {
+        format!("value = {}", self.value)
+            .parse::<Table>()
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+    }
+}
+
+impl Config {
+    /// Apply overrides in order, then validate the result with [`Config::check`]
+    pub fn apply_overrides(
+        &mut self,
+        overrides: &[ConfigOverride],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if overrides.is_empty() {
+            return Ok(());
+        }
+
+        let mut root = Value::try_from(&*self)?;
+        for config_override in overrides {
+            root = apply(&root, config_override).map_err(|e| {
+                format!("{} {}: {}", config_override.source, config_override.key, e)
+            })?;
+        }
+
+        // Leave the config as it was if the result isn't valid
+        let config = root
+            .try_into::<Config>()
+            .map_err(|e| e.to_string())
+            .and_then(|config| config.check().map(|_| config).map_err(|e| e.to_string()))
+            .map_err(|e| {
+                let mut sources: Vec<&str> = overrides.iter().map(|o| o.source.as_str()).collect();
+                sources.dedup();
+                format!("{}: {}", sources.join(", "), e)
+            })?;
+        *self = config;
+        Ok(())
+    }
+}
+
+/// Set one override on a copy of `root`, trying the typed value first and the
+/// raw string second, e.g. for `style.separator=1`
+fn apply(root: &Value, config_override: &ConfigOverride) -> Result<Value, String> {
+    let string = Value::String(config_override.value.clone());
+    let candidates = match config_override.typed_value() {
+        Some(typed) if typed != string => vec![typed, string],
+        _ => vec![string],
+    };
+
+    let mut error = String::new();
+    for value in candidates {
+        let mut next = root.clone();
+        set(&mut next, &config_override.key, value)?;
+        match next.try_into::<Config>() {
+            Ok(config) => {
+                // Serde drops fields it doesn't know, so an unknown key is
+                // one that doesn't survive the round trip
+                let canonical = Value::try_from(&config).map_err(|e| e.to_string())?;
+                if !contains(&canonical, &config_override.key) {
+                    return Err("unknown config key".to_string());
+                }
+                return Ok(canonical);
+            }
+            Err(e) => error = e.message().to_string(),
+        }
+    }
+    Err(error)
+}
+
+/// Split `segments.<id>[:<name>].rest` into the segment selector and the rest
+fn split_segment_key(key: &str) -> Option<(&str, Option<&str>, Vec<&str>)> {
+    let mut parts = key.split('.');
+    if parts.next() != Some("segments") {
+        return None;
+    }
+    let selector = parts.next().unwrap_or_default();
+    let (id, name) = match selector.split_once(':') {
+        Some((id, name)) => (id, Some(name)),
+        None => (selector, None),
+    };
+    Some((id, name, parts.collect()))
+}
+
+fn is_selected(segment: &Value, id: &str, name: Option<&str>) -> bool {
+    segment.get("id").and_then(|v| v.as_str()) == Some(id)
+        && name.is_none_or(|name| {
+            segment
+                .get("options")
+                .and_then(|options| options.get("name"))
+                .and_then(|v| v.as_str())
+                == Some(name)
+        })
+}
+
+/// Set `key` in the config table, creating intermediate tables as needed
+fn set(root: &mut Value, key: &str, value: Value) -> Result<(), String> {
+    let Some((id, name, rest)) = split_segment_key(key) else {
+        let parts: Vec<&str> = key.split('.').collect();
+        return set_in(root, &parts, value);
+    };
+
+    if id.is_empty() || rest.is_empty() {
+        return Err("expected segments.<id>.<key>".to_string());
+    }
+    let segments = root
+        .get_mut("segments")
+        .and_then(|segments| segments.as_array_mut())
+        .ok_or("no segments configured")?;
+
+    let mut found = false;
+    for segment in segments
+        .iter_mut()
+        .filter(|segment| is_selected(segment, id, name))
+    {
+        set_in(segment, &rest, value.clone())?;
+        found = true;
+    }
+    if !found {
+        return Err(match name {
+            Some(name) => format!("no {} segment named {} in the config", id, name),
+            None => format!("no {} segment in the config", id),
+        });
+    }
+    Ok(())
+}
+
+fn set_in(table: &mut Value, parts: &[&str], value: Value) -> Result<(), String> {
+    let (last, path) = parts.split_last().ok_or("empty key")?;
+    let mut current = table;
+    for part in path {
+        let Value::Table(table) = current else {
+            return Err(format!("{} is not a table", part));
+        };
+        current = table
+            .entry(part.to_string())
+            .or_insert_with(|| Value::Table(Table::new()));
+    }
+    match current {
+        Value::Table(table) if !last.is_empty() => {
+            table.insert(last.to_string(), value);
+            Ok(())
+        }
+        Value::Table(_) => Err("empty key".to_string()),
+        _ => Err(format!(
+            "cannot set {} on a value that is not a table",
+            last
+        )),
+    }
+}
+
+fn contains(root: &Value, key: &str) -> bool {
+    let lookup = |value: &Value, parts: &[&str]| {
+        parts
+            .iter()
+            .try_fold(value, |current, part| current.get(*part))
+            .is_some()
+    };
+
+    match split_segment_key(key) {
+        Some((id, name, rest)) => root
+            .get("segments")
+            .and_then(|segments| segments.as_array())
+            .is_some_and(|segments| {
+                segments
+                    .iter()
+                    .filter(|segment| is_selected(segment, id, name))
+                    .all(|segment| lookup(segment, &rest))
+            }),
+        None => lookup(root, &key.split('.').collect::<Vec<_>>()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::project::segment_template;
+    use crate::config::SegmentId;
+
+    fn set(config: &mut Config, arg: &str) -> Result<(), String> {
+        let config_override = ConfigOverride::from_arg(arg)?;
+        config
+            .apply_overrides(&[config_override])
+            .map_err(|e| e.to_string())
+    }
+
+    /// Default config plus two named custom segments
+    fn config_with_customs() -> Config {
+        let mut config = Config::default();
+        for name in ["build", "deploy"] {
+            let mut custom = segment_template(SegmentId::Custom, "default");
+            custom.options.insert("name".to_string(), name.into());
+            custom
+                .options
+                .insert("command".to_string(), format!("echo {}", name).into());
+            config.segments.push(custom);
+        }
+        config
+    }
+
+    fn custom_option(config: &Config, name: &str, key: &str) -> Option<serde_json::Value> {
+        config
+            .segments
+            .iter()
+            .find(|segment| segment.instance_name() == Some(name))
+            .and_then(|segment| segment.options.get(key).cloned())
+    }
+
+    #[test]
+    fn values_keep_their_type_or_fall_back_to_a_string() {
+        let mut config = Config::default();
+        // `1` parses as an integer, which a separator can't be
+        set(&mut config, "style.separator=1").unwrap();
+        assert_eq!(config.style.separator, "1");
+        set(&mut config, "style.separator= | ").unwrap();
+        assert_eq!(config.style.separator, " | ");
+
+        set(&mut config, "segments.git.options.show_sha=true").unwrap();
+        let git = config
+            .segments
+            .iter()
+            .find(|segment| segment.id == SegmentId::Git)
+            .unwrap();
+        assert_eq!(git.options["show_sha"], true);
+    }
+
+    #[test]
+    fn named_selectors_pick_one_custom_segment() {
+        let mut config = config_with_customs();
+        set(&mut config, "segments.custom:deploy.options.timeout_ms=100").unwrap();
+        assert_eq!(
+            custom_option(&config, "deploy", "timeout_ms"),
+            Some(100.into())
+        );
+        assert_eq!(custom_option(&config, "build", "timeout_ms"), None);
+
+        // Without a name, every custom segment is changed
+        set(&mut config, "segments.custom.options.cache_duration=5").unwrap();
+        assert_eq!(
+            custom_option(&config, "build", "cache_duration"),
+            Some(5.into())
+        );
+        assert_eq!(
+            custom_option(&config, "deploy", "cache_duration"),
+            Some(5.into())
+        );
+
+        let error = set(&mut config, "segments.custom:nope.enabled=false").unwrap_err();
+        assert!(error.contains("no custom segment named nope"), "{}", error);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected_and_nothing_changes() {
+        let mut config = Config::default();
+        let separator = config.style.separator.clone();
+        for arg in ["style.sepparator=x", "nonsense=1", "segments.git.colour=1"] {
+            let error = set(&mut config, arg).unwrap_err();
+            assert!(error.starts_with("--set "), "{}", error);
+        }
+
+        let overrides = [
+            ConfigOverride::new("style.separator", "/", "--set"),
+            ConfigOverride::new("style.bogus", "1", "CCLINE_STYLE__BOGUS"),
+        ];
+        let error = config.apply_overrides(&overrides).unwrap_err().to_string();
+        assert!(
+            error.starts_with("CCLINE_STYLE__BOGUS style.bogus"),
+            "{}",
+            error
+        );
+        assert_eq!(config.style.separator, separator);
+    }
+
+    #[test]
+    fn environment_variables_map_to_dotted_keys() {
+        let vars = [
+            ("CCLINE_STYLE__SEPARATOR", " / "),
+            ("CCLINE_SEGMENTS__GIT__OPTIONS__SHOW_SHA", "true"),
+            ("CCLINE_STYLE", "powerline"),
+            ("CCLINE_DISABLE", "cost, git,"),
+            ("CCLINE_MODEL_ID", "ignored"),
+            ("PATH", "/usr/bin"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+
+        let overrides: Vec<(String, String, String)> = ConfigOverride::from_vars(vars)
+            .into_iter()
+            .map(|o| (o.key, o.value, o.source))
+            .collect();
+        let expected = [
+            ("segments.cost.enabled", "false", "CCLINE_DISABLE"),
+            ("segments.git.enabled", "false", "CCLINE_DISABLE"),
+            (
+                "segments.git.options.show_sha",
+                "true",
+                "CCLINE_SEGMENTS__GIT__OPTIONS__SHOW_SHA",
+            ),
+            ("style.mode", "powerline", "CCLINE_STYLE"),
+            ("style.separator", " / ", "CCLINE_STYLE__SEPARATOR"),
+        ]
+        .map(|(key, value, source)| (key.to_string(), value.to_string(), source.to_string()));
+        assert_eq!(overrides, expected);
+    }
+}
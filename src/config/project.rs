@@ -0,0 +1,562 @@
+use super::loader::ConfigLoader;
+use super::overrides::ConfigOverride;
+use super::schema::Diagnostic;
+use super::types::{
+    ColorConfig, Config, IconConfig, SegmentConfig, SegmentId, TextStyleConfig, TrustedProject,
+};
+use crate::utils::paths;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// Project-local config file, looked up from the working directory upwards
+pub const PROJECT_CONFIG_FILE: &str = ".ccline.toml";
+
+/// Segment options that run commands or receive the OAuth token. A project
+/// file only sets them once it's trusted with `ccline trust`, so checking out
+/// a repository doesn't run its commands.
+pub const UNTRUSTED_OPTIONS: &[&str] = &["command", "credential_helper", "api_base_url"];
+
+/// Effective configuration plus where its values came from
+pub struct LayeredConfig {
+    pub config: Config,
+    /// Global config file, or `None` when running on built-in defaults
+    pub base: Option<PathBuf>,
+    /// Project files that were merged, outermost first
+    pub layers: Vec<PathBuf>,
+    /// Keys set by a project file, e.g. `segments[cost].enabled`, with that file
+    pub sources: Vec<(String, PathBuf)>,
+    /// Options ignored because their project file isn't trusted, with that file
+    pub untrusted: Vec<(String, PathBuf)>,
+}
+
+impl Config {
+    /// Load the global configuration with any `.ccline.toml` files between
+    /// `dir` and the filesystem root merged over it
+    pub fn load_for_dir(dir: &str) -> Result<Config, Box<dyn std::error::Error>> {
+        Ok(Self::load_layered(dir)?.config)
+    }
+
+    /// Like [`Config::load_for_dir`], keeping track of which file set each value
+    pub fn load_layered(dir: &str) -> Result<LayeredConfig, Box<dyn std::error::Error>> {
+        ConfigLoader::ensure_themes_exist();
+
+        let config_path = paths::config_file();
+        let (global, base) = if config_path.exists() {
+            let config = super::schema::load_and_migrate(&config_path)?;
+            (config, Some(config_path))
+        } else {
+            (Config::default(), None)
+        };
+        Self::layer_projects(global, base, Path::new(dir))
+    }
+
+    /// Merge the project files between `dir` and the filesystem root over `global`
+    fn layer_projects(
+        global: Config,
+        base: Option<PathBuf>,
+        dir: &Path,
+    ) -> Result<LayeredConfig, Box<dyn std::error::Error>> {
+        let mut layers = Vec::new();
+        let mut untrusted = Vec::new();
+        for path in find_project_configs(dir) {
+            let content = fs::read_to_string(&path)?;
+            let mut table: Table = toml::from_str(&content)
+                .map_err(|e| Diagnostic::from_toml(&content, &e).in_file(&path))?;
+            if !global.is_trusted(&path, &content) {
+                for key in remove_untrusted_options(&mut table) {
+                    untrusted.push((key, path.clone()));
+                }
+            }
+            layers.push((path, table));
+        }
+
+        let mut merger = Merger::default();
+
+        // A project theme replaces the global segment styling, like `--theme`,
+        // before any project overrides are applied
+        let theme = layers
+            .iter()
+            .rev()
+            .find_map(|(_, table)| table.get("theme").and_then(|v| v.as_str()));
+        let mut merged = match theme {
+            Some(theme) if theme != global.theme => {
+                let mut themed = global.clone();
+                themed.switch_theme(theme);
+                Value::try_from(themed)?
+            }
+            _ => Value::try_from(&global)?,
+        };
+
+        for (path, table) in &layers {
+            merger.source = path.clone();
+            merger.merge_root(&mut merged, table);
+        }
+
+        let mut config: Config = merged.try_into()?;
+        // A custom segment added without its command has nothing to run
+        config.segments.retain(|segment| {
+            segment.id != SegmentId::Custom
+                || segment.options.contains_key("command")
+                || !untrusted.iter().any(|(key, _)| {
+                    let label = match segment.instance_name() {
+                        Some(name) => format!("segments[custom:{}]", name),
+                        None => "segments[custom]".to_string(),
+                    };
+                    *key == format!("{}.options.command", label)
+                })
+        });
+        Ok(LayeredConfig {
+            config,
+            base,
+            layers: layers.into_iter().map(|(path, _)| path).collect(),
+            sources: merger.sources,
+            untrusted,
+        })
+    }
+
+    /// Whether `path` was trusted with `ccline trust` and hasn't changed since
+    pub fn is_trusted(&self, path: &Path, content: &str) -> bool {
+        self.trusted_projects.contains(&trust_entry(path, content))
+    }
+
+    /// Print the effective configuration for `dir` as TOML, with comments
+    /// naming the files and overrides it was built from
+    pub fn print_layered(
+        dir: &str,
+        overrides: &[ConfigOverride],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut layered = Self::load_layered(dir)?;
+        layered.config.apply_overrides(overrides)?;
+
+        match &layered.base {
+            Some(path) => println!("# Base: {}", path.display()),
+            None => println!("# Base: built-in defaults"),
+        }
+        if layered.layers.is_empty() {
+            println!("# No {} found from {}", PROJECT_CONFIG_FILE, dir);
+        }
+        for path in &layered.layers {
+            println!("# Merged: {}", path.display());
+        }
+        if !layered.sources.is_empty() {
+            println!("#");
+            println!("# Overridden by project files:");
+            let width = layered
+                .sources
+                .iter()
+                .map(|(key, _)| key.len())
+                .max()
+                .unwrap_or(0);
+            for (key, path) in &layered.sources {
+                println!("#   {:<width$}  {}", key, path.display(), width = width);
+            }
+        }
+        if !overrides.is_empty() {
+            println!("#");
+            println!("# Overrides:");
+            for config_override in overrides {
+                println!(
+                    "#   {}={}  {}",
+                    config_override.key, config_override.value, config_override.source
+                );
+            }
+        }
+        if !layered.untrusted.is_empty() {
+            println!("#");
+            println!("# Ignored until trusted (see `ccline trust`):");
+            for (key, path) in &layered.untrusted {
+                println!("#   {}  {}", key, path.display());
+            }
+        }
+        println!();
+
+        layered.config.print()
+    }
+}
+
+/// `.ccline.toml` files from the filesystem root down to `dir`
+pub fn find_project_configs(dir: &Path) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = dir
+        .ancestors()
+        .map(|ancestor| ancestor.join(PROJECT_CONFIG_FILE))
+        .filter(|path| path.is_file())
+        .collect();
+    found.reverse();
+    found
+}
+
+/// Allowlist entry for a project file with its current content
+pub fn trust_entry(path: &Path, content: &str) -> TrustedProject {
+    TrustedProject {
+        path: trust_path(path),
+        sha256: format!("{:x}", Sha256::digest(content.as_bytes())),
+    }
+}
+
+/// Absolute path a project file is trusted under
+pub fn trust_path(path: &Path) -> String {
+    fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .display()
+        .to_string()
+}
+
+/// Drop [`UNTRUSTED_OPTIONS`] from a project table, returning the keys that
+/// were set, e.g. `segments[custom:build].options.command`
+pub fn remove_untrusted_options(table: &mut Table) -> Vec<String> {
+    let mut removed = Vec::new();
+    let Some(Value::Array(segments)) = table.get_mut("segments") else {
+        return removed;
+    };
+    for segment in segments.iter_mut().filter_map(|s| s.as_table_mut()) {
+        let label = segment_label(segment);
+        let Some(Value::Table(options)) = segment.get_mut("options") else {
+            continue;
+        };
+        for key in UNTRUSTED_OPTIONS {
+            if options.remove(*key).is_some() {
+                removed.push(format!("{}.options.{}", label, key));
+            }
+        }
+    }
+    removed
+}
+
+/// Deep-merges project tables into the config, recording each key it sets
+#[derive(Default)]
+struct Merger {
+    source: PathBuf,
+    sources: Vec<(String, PathBuf)>,
+}
+
+impl Merger {
+    fn record(&mut self, key: String) {
+        self.sources.retain(|(existing, _)| *existing != key);
+        self.sources.push((key, self.source.clone()));
+    }
+
+    fn merge_root(&mut self, base: &mut Value, overlay: &Table) {
+        let Some(base) = base.as_table_mut() else {
+            return;
+        };
+        for (key, value) in overlay {
+            match (key.as_str(), value) {
+                ("segments", Value::Array(segments)) => {
+                    let theme = base
+                        .get("theme")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("default")
+                        .to_string();
+                    let base_segments = base
+                        .entry("segments")
+                        .or_insert_with(|| Value::Array(Vec::new()));
+                    if let Value::Array(base_segments) = base_segments {
+                        for segment in segments.iter().filter_map(|s| s.as_table()) {
+                            self.merge_segment(base_segments, segment, &theme);
+                        }
+                    }
+                }
+                // Only the global config decides what's trusted
+                ("trusted_projects", _) => {}
+                _ => self.merge_value(base, key, value, key.clone()),
+            }
+        }
+    }
+
+    /// Merge a segment into the one with the same id (and `name` option for
+    /// custom segments), or append it if there's none
+    fn merge_segment(&mut self, segments: &mut Vec<Value>, overlay: &Table, theme: &str) {
+        let Some(id) = overlay.get("id").and_then(|v| v.as_str()) else {
+            return;
+        };
+        let name = segment_name(overlay);
+        let label = segment_label(overlay);
+
+        let existing = segments.iter_mut().find(|segment| {
+            segment.as_table().is_some_and(|segment| {
+                segment.get("id").and_then(|v| v.as_str()) == Some(id)
+                    && segment_name(segment) == name
+            })
+        });
+
+        match existing {
+            Some(Value::Table(segment)) => {
+                for (key, value) in overlay {
+                    if key != "id" {
+                        self.merge_value(segment, key, value, format!("{}.{}", label, key));
+                    }
+                }
+            }
+            _ => {
+                let Some(mut segment) = segment_template_table(id, theme) else {
+                    return;
+                };
+                for (key, value) in overlay {
+                    if key != "id" {
+                        self.merge_value(&mut segment, key, value, String::new());
+                    }
+                }
+                segments.push(Value::Table(segment));
+                self.record(label);
+            }
+        }
+    }
+
+    /// Tables merge key by key; anything else, including arrays and colors,
+    /// replaces the base value. An empty `path` means don't record.
+    fn merge_value(&mut self, base: &mut Table, key: &str, value: &Value, path: String) {
+        match (base.get_mut(key), value) {
+            (Some(Value::Table(base_table)), Value::Table(overlay)) if !is_color(overlay) => {
+                for (child_key, child_value) in overlay {
+                    let child_path = if path.is_empty() {
+                        String::new()
+                    } else {
+                        format!("{}.{}", path, child_key)
+                    };
+                    self.merge_value(base_table, child_key, child_value, child_path);
+                }
+            }
+            _ => {
+                base.insert(key.to_string(), value.clone());
+                if !path.is_empty() {
+                    self.record(path);
+                }
+            }
+        }
+    }
+}
+
+/// How a segment is named in `ccline print`, e.g. `segments[custom:build]`
+fn segment_label(segment: &Table) -> String {
+    let id = segment.get("id").and_then(|v| v.as_str()).unwrap_or("?");
+    match segment_name(segment) {
+        Some(name) => format!("segments[{}:{}]", id, name),
+        None => format!("segments[{}]", id),
+    }
+}
+
+fn segment_name(segment: &Table) -> Option<&str> {
+    segment
+        .get("options")
+        .and_then(|options| options.get("name"))
+        .and_then(|name| name.as_str())
+}
+
+/// Colors are one of several shapes (`c16`, `c256`, `r`/`g`/`b`), so a new
+/// color replaces the old one instead of merging into it
+fn is_color(table: &Table) -> bool {
+    ["c16", "c256", "r", "g", "b"]
+        .iter()
+        .any(|key| table.contains_key(*key))
+}
+
+/// Full segment settings for a segment that's added to a config: the theme's
+/// version of it if the theme has one, otherwise a blank segment. Added
+/// segments are enabled.
+pub(crate) fn segment_template(id: SegmentId, theme: &str) -> SegmentConfig {
+    let mut segment = crate::ui::themes::ThemePresets::get_theme(theme)
+        .segments
+        .into_iter()
+        .find(|segment| segment.id == id)
+        .unwrap_or(SegmentConfig {
+            id,
+            enabled: true,
+            line: 1,
+            icon: IconConfig {
+                plain: String::new(),
+                nerd_font: String::new(),
+            },
+            colors: ColorConfig::default(),
+            styles: TextStyleConfig::default(),
+            options: HashMap::new(),
+            thresholds: Vec::new(),
+        });
+    segment.enabled = true;
+    // Don't inherit e.g. a themed custom segment's command
+    if id == SegmentId::Custom {
+        segment.options.clear();
+    }
+    segment
+}
+
+fn segment_template_table(id: &str, theme: &str) -> Option<Table> {
+    let id: SegmentId = Value::String(id.to_string()).try_into().ok()?;
+    match Value::try_from(segment_template(id, theme)).ok()? {
+        Value::Table(table) => Some(table),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory tree with the given project files, keyed by
+    /// subdirectory ("" for the root)
+    fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("ccline-project-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (dir, content) in files {
+            let dir = root.join(dir);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(PROJECT_CONFIG_FILE), content).unwrap();
+        }
+        root
+    }
+
+    fn segment<'a>(
+        config: &'a Config,
+        id: SegmentId,
+        name: Option<&str>,
+    ) -> Option<&'a SegmentConfig> {
+        config
+            .segments
+            .iter()
+            .find(|segment| segment.id == id && segment.instance_name() == name)
+    }
+
+    const RISKY: &str = r#"
+        [[segments]]
+        id = "custom"
+        options = { name = "build", command = "make status" }
+
+        [[segments]]
+        id = "usage"
+        options = { credential_helper = "cat token", api_base_url = "https://evil.example", cache_duration = 60 }
+    "#;
+
+    #[test]
+    fn untrusted_files_cannot_set_risky_options() {
+        let root = project("untrusted", &[("", RISKY)]);
+        let layered = Config::layer_projects(Config::default(), None, &root).unwrap();
+
+        let mut ignored: Vec<&str> = layered
+            .untrusted
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .collect();
+        ignored.sort();
+        assert_eq!(
+            ignored,
+            [
+                "segments[custom:build].options.command",
+                "segments[usage].options.api_base_url",
+                "segments[usage].options.credential_helper",
+            ]
+        );
+
+        // The custom segment has nothing left to run, so it isn't added
+        assert!(segment(&layered.config, SegmentId::Custom, Some("build")).is_none());
+        // The usage segment keeps the global values for the risky options
+        let default_usage = segment(&Config::default(), SegmentId::Usage, None)
+            .unwrap()
+            .clone();
+        let usage = segment(&layered.config, SegmentId::Usage, None).unwrap();
+        for key in ["credential_helper", "api_base_url"] {
+            assert_eq!(usage.options.get(key), default_usage.options.get(key));
+        }
+        assert_eq!(usage.options["cache_duration"], 60);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn trust_covers_the_exact_content() {
+        let root = project("trusted", &[("", RISKY)]);
+        let path = root.join(PROJECT_CONFIG_FILE);
+        let mut global = Config::default();
+        global.trusted_projects.push(trust_entry(&path, RISKY));
+
+        let layered = Config::layer_projects(global.clone(), None, &root).unwrap();
+        assert!(layered.untrusted.is_empty());
+        let custom = segment(&layered.config, SegmentId::Custom, Some("build")).unwrap();
+        assert_eq!(custom.options["command"], "make status");
+
+        // Changing a single byte revokes the trust
+        fs::write(&path, RISKY.replace("make status", "make statuz")).unwrap();
+        let layered = Config::layer_projects(global, None, &root).unwrap();
+        assert_eq!(layered.untrusted.len(), 3);
+        assert!(segment(&layered.config, SegmentId::Custom, Some("build")).is_none());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn custom_segments_without_a_command_are_kept_unless_it_was_removed() {
+        let root = project(
+            "retain",
+            &[(
+                "",
+                r#"
+                [[segments]]
+                id = "custom"
+                options = { name = "plain", timeout_ms = 100 }
+                "#,
+            )],
+        );
+        let layered = Config::layer_projects(Config::default(), None, &root).unwrap();
+        assert!(segment(&layered.config, SegmentId::Custom, Some("plain")).is_some());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn segments_merge_by_id_and_name_inner_files_last() {
+        let outer = r#"
+            [[segments]]
+            id = "git"
+            enabled = false
+
+            [[segments]]
+            id = "custom"
+            options = { name = "a", command = "echo a" }
+
+            [[segments]]
+            id = "custom"
+            options = { name = "b", command = "echo b" }
+        "#;
+        let inner = r#"
+            [[segments]]
+            id = "git"
+            enabled = true
+            options = { show_sha = true }
+
+            [[segments]]
+            id = "custom"
+            options = { name = "b", timeout_ms = 100 }
+        "#;
+        let root = project("merge", &[("", outer), ("inner", inner)]);
+        let mut global = Config::default();
+        global
+            .trusted_projects
+            .push(trust_entry(&root.join(PROJECT_CONFIG_FILE), outer));
+        let global_segments = global.segments.len();
+
+        let layered = Config::layer_projects(global, None, &root.join("inner")).unwrap();
+        assert_eq!(layered.layers.len(), 2);
+        let config = &layered.config;
+        assert_eq!(config.segments.len(), global_segments + 2);
+
+        let git = segment(config, SegmentId::Git, None).unwrap();
+        assert!(git.enabled);
+        assert_eq!(git.options["show_sha"], true);
+
+        let a = segment(config, SegmentId::Custom, Some("a")).unwrap();
+        assert_eq!(a.options["command"], "echo a");
+        assert!(!a.options.contains_key("timeout_ms"));
+        let b = segment(config, SegmentId::Custom, Some("b")).unwrap();
+        assert_eq!(b.options["command"], "echo b");
+        assert_eq!(b.options["timeout_ms"], 100);
+
+        let inner_path = root.join("inner").join(PROJECT_CONFIG_FILE);
+        assert!(layered
+            .sources
+            .contains(&("segments[git].enabled".to_string(), inner_path.clone())));
+        assert!(layered.sources.contains(&(
+            "segments[custom:b].options.timeout_ms".to_string(),
+            inner_path
+        )));
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,573 @@
+//! Config file schema: layout versions, migration of older layouts, and
+//! problems reported with line and column numbers.
+
+use super::project::{find_project_configs, remove_untrusted_options};
+use super::types::{BudgetConfig, Config, SegmentId, StyleConfig, COMMON_OPTIONS};
+use crate::utils::{lock, paths};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::de::{DeTable, DeValue};
+use toml::{Table, Value};
+
+/// Current config layout. Files without a `version` key are version 1, unless
+/// they use the legacy `[segments]` on/off flags, which are version 0.
+pub const CONFIG_VERSION: u32 = 1;
+
+pub fn current_version() -> u32 {
+    CONFIG_VERSION
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in a config file
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 1-based line and column, when the problem can be located
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(message: impl Into<String>, position: Option<(usize, usize)>) -> Self {
+        Self {
+            severity: Severity::Error,
+            position,
+            message: message.into(),
+        }
+    }
+
+    fn warning(message: impl Into<String>, position: Option<(usize, usize)>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            position,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn from_toml(content: &str, error: &toml::de::Error) -> Self {
+        Self::error(
+            error.message().trim(),
+            error.span().map(|span| position(content, span.start)),
+        )
+    }
+
+    /// `path:line:column: message`, like compiler output
+    pub fn in_file(&self, path: &Path) -> String {
+        match self.position {
+            Some((line, column)) => {
+                format!("{}:{}:{}: {}", path.display(), line, column, self.message)
+            }
+            None => format!("{}: {}", path.display(), self.message),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(f, "line {}, column {}: {}", line, column, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// 1-based line and column of a byte offset
+fn position(content: &str, offset: usize) -> (usize, usize) {
+    let before = content.get(..offset).unwrap_or(content);
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |line| line.chars().count())
+        + 1;
+    (line, column)
+}
+
+/// A config file brought up to the current layout
+pub struct ParsedConfig {
+    pub config: Config,
+    /// Layout the file was written in; older than [`CONFIG_VERSION`] means
+    /// `config` was migrated
+    pub version: u32,
+}
+
+/// Parse config file contents, migrating an older layout in memory
+pub fn parse(content: &str) -> Result<ParsedConfig, Diagnostic> {
+    let table: Table = toml::from_str(content).map_err(|e| Diagnostic::from_toml(content, &e))?;
+    let version = detect_version(&table)?;
+
+    let config = if version == CONFIG_VERSION {
+        // Deserialize from the text rather than the table to keep error positions
+        toml::from_str(content).map_err(|e| Diagnostic::from_toml(content, &e))?
+    } else {
+        migrate(table, version).map_err(|message| Diagnostic::error(message, None))?
+    };
+    Ok(ParsedConfig { config, version })
+}
+
+fn detect_version(table: &Table) -> Result<u32, Diagnostic> {
+    let version = match table.get("version") {
+        Some(Value::Integer(version)) => u32::try_from(*version)
+            .map_err(|_| Diagnostic::error("version must be a positive number", None))?,
+        Some(_) => return Err(Diagnostic::error("version must be a number", None)),
+        None if table.get("segments").is_some_and(Value::is_table) => 0,
+        None => 1,
+    };
+    if version > CONFIG_VERSION {
+        return Err(Diagnostic::error(
+            format!(
+                "config version {} is newer than this ccline supports ({}); update ccline",
+                version, CONFIG_VERSION
+            ),
+            None,
+        ));
+    }
+    Ok(version)
+}
+
+/// Bring an older layout up to date, one version at a time
+fn migrate(mut table: Table, from: u32) -> Result<Config, String> {
+    for version in from..CONFIG_VERSION {
+        table = match version {
+            0 => migrate_v0(&table)?,
+            _ => table,
+        };
+    }
+    Value::Table(table)
+        .try_into()
+        .map_err(|e: toml::de::Error| e.message().to_string())
+}
+
+/// Version 0 had `[segments]` as on/off flags for a fixed set of segments
+/// (`SegmentsConfig`) and took everything else from the theme
+fn migrate_v0(table: &Table) -> Result<Table, String> {
+    let theme = table
+        .get("theme")
+        .and_then(|v| v.as_str())
+        .unwrap_or("default");
+    // Built-in presets only: a theme file could itself need migrating
+    let mut config = crate::ui::themes::ThemePresets::get_builtin_theme(theme);
+    config.theme = theme.to_string();
+
+    if let Some(style) = table
+        .get("style")
+        .and_then(|style| style.clone().try_into::<StyleConfig>().ok())
+    {
+        config.style = style;
+    }
+    if let Some(budget) = table
+        .get("budget")
+        .and_then(|budget| budget.clone().try_into::<BudgetConfig>().ok())
+    {
+        config.budget = budget;
+    }
+
+    if let Some(Value::Table(flags)) = table.get("segments") {
+        for (name, enabled) in flags {
+            let (Some(enabled), Ok(id)) = (
+                enabled.as_bool(),
+                Value::String(name.clone()).try_into::<SegmentId>(),
+            ) else {
+                continue;
+            };
+            for segment in config.segments.iter_mut().filter(|s| s.id == id) {
+                segment.enabled = enabled;
+            }
+        }
+    }
+
+    config.version = 1;
+    match Value::try_from(config).map_err(|e| e.to_string())? {
+        Value::Table(table) => Ok(table),
+        _ => Err("migrated config is not a table".to_string()),
+    }
+}
+
+/// Read and parse a config file, with errors as `path:line:column: message`
+pub fn read_file(path: &Path) -> Result<ParsedConfig, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    parse(&content).map_err(|diagnostic| diagnostic.in_file(path).into())
+}
+
+/// Read a config file, rewriting it in the current layout if it was older.
+/// The original is kept next to it as `<name>.v<version>.bak`.
+pub fn load_and_migrate(path: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+    let parsed = read_file(path)?;
+    if parsed.version < CONFIG_VERSION && !crate::utils::dry_run::is_enabled() {
+        let backup = backup_path(path, parsed.version);
+        // Keep using the migrated config in memory if the file can't be rewritten
+        if fs::copy(path, &backup).is_ok() {
+            let content = toml::to_string_pretty(&parsed.config)?;
+            lock::write_atomic(path, content.as_bytes())?;
+        }
+    }
+    Ok(parsed.config)
+}
+
+fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}.v{}.bak", name, version))
+}
+
+const ROOT_KEYS: &[&str] = &[
+    "version",
+    "style",
+    "segments",
+    "theme",
+    "budget",
+    "trusted_projects",
+];
+const STYLE_KEYS: &[&str] = &["mode", "separator", "max_width", "deadline_ms"];
+const BUDGET_KEYS: &[&str] = &["session", "daily", "monthly"];
+const SEGMENT_KEYS: &[&str] = &[
+    "id",
+    "enabled",
+    "line",
+    "icon",
+    "colors",
+    "styles",
+    "options",
+    "thresholds",
+];
+const ICON_KEYS: &[&str] = &["plain", "nerd_font"];
+const COLOR_KEYS: &[&str] = &["icon", "text", "background"];
+const TEXT_STYLE_KEYS: &[&str] = &["text_bold"];
+const THRESHOLD_KEYS: &[&str] = &["metric", "operator", "value", "colors", "styles"];
+
+/// Warnings for keys ccline doesn't know, which would otherwise be ignored
+/// without a word. Works on partial configs like project files too.
+pub fn lint(content: &str) -> Vec<Diagnostic> {
+    let Ok(root) = DeTable::parse(content) else {
+        return Vec::new();
+    };
+    let mut linter = Linter {
+        content,
+        diagnostics: Vec::new(),
+    };
+    linter.check_root(root.get_ref());
+    linter.diagnostics
+}
+
+struct Linter<'a> {
+    content: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter<'_> {
+    fn check_root(&mut self, root: &DeTable) {
+        self.unknown_keys(root, ROOT_KEYS, "the config");
+        if let Some(style) = table(root, "style") {
+            self.unknown_keys(style, STYLE_KEYS, "[style]");
+        }
+        if let Some(budget) = table(root, "budget") {
+            self.unknown_keys(budget, BUDGET_KEYS, "[budget]");
+        }
+        if let Some(DeValue::Array(segments)) = root.get("segments").map(|v| v.get_ref()) {
+            for segment in segments {
+                if let DeValue::Table(segment) = segment.get_ref() {
+                    self.check_segment(segment);
+                }
+            }
+        }
+    }
+
+    fn check_segment(&mut self, segment: &DeTable) {
+        let id = segment
+            .get("id")
+            .and_then(|id| id.get_ref().as_str())
+            .unwrap_or("?");
+        let context = format!("the {} segment", id);
+
+        self.unknown_keys(segment, SEGMENT_KEYS, &context);
+        if let Some(icon) = table(segment, "icon") {
+            self.unknown_keys(icon, ICON_KEYS, &format!("{} icon", context));
+        }
+        if let Some(colors) = table(segment, "colors") {
+            self.unknown_keys(colors, COLOR_KEYS, &format!("{} colors", context));
+        }
+        if let Some(styles) = table(segment, "styles") {
+            self.unknown_keys(styles, TEXT_STYLE_KEYS, &format!("{} styles", context));
+        }
+        if let Some(DeValue::Array(thresholds)) = segment.get("thresholds").map(|v| v.get_ref()) {
+            let context = format!("{} thresholds", context);
+            for rule in thresholds {
+                if let DeValue::Table(rule) = rule.get_ref() {
+                    self.unknown_keys(rule, THRESHOLD_KEYS, &context);
+                    if let Some(colors) = table(rule, "colors") {
+                        self.unknown_keys(colors, COLOR_KEYS, &context);
+                    }
+                    if let Some(styles) = table(rule, "styles") {
+                        self.unknown_keys(styles, TEXT_STYLE_KEYS, &context);
+                    }
+                }
+            }
+        }
+
+        // Only known ids have a list of options; an unknown id fails to load anyway
+        let Ok(id) = Value::String(id.to_string()).try_into::<SegmentId>() else {
+            return;
+        };
+        if let Some(options) = table(segment, "options") {
+            for (key, _) in options.iter() {
+                let name: &str = key.get_ref();
+                if !COMMON_OPTIONS.contains(&name) && !id.known_options().contains(&name) {
+                    self.warn(
+                        key.span().start,
+                        format!("unknown option `{}` for {}", name, context),
+                    );
+                }
+            }
+        }
+    }
+
+    fn unknown_keys(&mut self, table: &DeTable, known: &[&str], context: &str) {
+        for (key, _) in table.iter() {
+            let name: &str = key.get_ref();
+            if !known.contains(&name) {
+                self.warn(
+                    key.span().start,
+                    format!("unknown key `{}` in {}", name, context),
+                );
+            }
+        }
+    }
+
+    fn warn(&mut self, offset: usize, message: String) {
+        self.diagnostics.push(Diagnostic::warning(
+            message,
+            Some(position(self.content, offset)),
+        ));
+    }
+}
+
+fn table<'a, 'i>(parent: &'a DeTable<'i>, key: &str) -> Option<&'a DeTable<'i>> {
+    parent.get(key).and_then(|value| value.get_ref().as_table())
+}
+
+/// Check the global config and any project files for `dir`, printing what's
+/// wrong with each. Returns whether the configuration is usable.
+pub fn print_check(dir: &str) -> bool {
+    let mut report = CheckReport::default();
+
+    // Checking shouldn't rewrite an old config, which loading it would
+    let mut migration_pending = false;
+    let mut global = Config::default();
+    let config_path = paths::config_file();
+    if config_path.exists() {
+        let diagnostics = match fs::read_to_string(&config_path) {
+            Ok(content) => {
+                if let Ok(parsed) = parse(&content) {
+                    migration_pending = parsed.version < CONFIG_VERSION;
+                    global = parsed.config;
+                }
+                check_content(&content, true)
+            }
+            Err(e) => vec![Diagnostic::error(e.to_string(), None)],
+        };
+        report.print(&config_path.display().to_string(), &diagnostics);
+    } else {
+        println!(
+            "{}\n  not found, using built-in defaults",
+            config_path.display()
+        );
+    }
+
+    for path in find_project_configs(Path::new(dir)) {
+        let diagnostics = match fs::read_to_string(&path) {
+            Ok(content) => {
+                let mut diagnostics = check_content(&content, false);
+                diagnostics.extend(check_trust(&global, &path, &content));
+                diagnostics
+            }
+            Err(e) => vec![Diagnostic::error(e.to_string(), None)],
+        };
+        report.print(&path.display().to_string(), &diagnostics);
+    }
+
+    // Rules that span the merged result, like duplicate segments
+    if report.errors == 0 && !migration_pending {
+        if let Err(e) = Config::load_layered(dir).and_then(|layered| layered.config.check()) {
+            report.print(
+                "effective config",
+                &[Diagnostic::error(e.to_string(), None)],
+            );
+        }
+    }
+
+    println!();
+    match (report.errors, report.warnings) {
+        (0, 0) => println!("✓ Configuration is valid"),
+        (0, warnings) => println!("✓ Configuration is valid ({} warning(s))", warnings),
+        (errors, warnings) => println!("✗ {} error(s), {} warning(s)", errors, warnings),
+    }
+    report.errors == 0
+}
+
+/// Warnings for what a project file sets that only the global config or a
+/// trusted file may
+fn check_trust(global: &Config, path: &Path, content: &str) -> Vec<Diagnostic> {
+    let Ok(mut table) = toml::from_str::<Table>(content) else {
+        return Vec::new();
+    };
+    let mut diagnostics = Vec::new();
+    if table.contains_key("trusted_projects") {
+        diagnostics.push(Diagnostic::warning(
+            "`trusted_projects` is ignored outside config.toml",
+            None,
+        ));
+    }
+    if !global.is_trusted(path, content) {
+        for key in remove_untrusted_options(&mut table) {
+            diagnostics.push(Diagnostic::warning(
+                format!(
+                    "`{}` is ignored until this file is trusted (run `ccline trust`)",
+                    key
+                ),
+                None,
+            ));
+        }
+    }
+    diagnostics
+}
+
+#[derive(Default)]
+struct CheckReport {
+    errors: usize,
+    warnings: usize,
+}
+
+impl CheckReport {
+    fn print(&mut self, title: &str, diagnostics: &[Diagnostic]) {
+        println!("{}", title);
+        if diagnostics.is_empty() {
+            println!("  ok");
+        }
+        for diagnostic in diagnostics {
+            let label = match diagnostic.severity {
+                Severity::Error => {
+                    self.errors += 1;
+                    "error"
+                }
+                Severity::Warning => {
+                    self.warnings += 1;
+                    "warning"
+                }
+            };
+            println!("  {}: {}", label, diagnostic);
+        }
+    }
+}
+
+/// Problems in one file. `full` files must be complete configs; project
+/// files only need to be valid TOML since they're merged over one.
+fn check_content(content: &str, full: bool) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    if full {
+        match parse(content) {
+            Ok(parsed) if parsed.version < CONFIG_VERSION => {
+                diagnostics.push(Diagnostic::warning(
+                    format!(
+                        "version {} layout, migrated to version {} on the next load (the original is kept as a .v{}.bak file)",
+                        parsed.version, CONFIG_VERSION, parsed.version
+                    ),
+                    None,
+                ));
+                if let Err(e) = parsed.config.check() {
+                    diagnostics.push(Diagnostic::error(e.to_string(), None));
+                }
+                // Unknown keys refer to the old layout, which is going away
+                return diagnostics;
+            }
+            Ok(_) => {}
+            Err(diagnostic) => diagnostics.push(diagnostic),
+        }
+    } else if let Err(e) = toml::from_str::<Table>(content) {
+        diagnostics.push(Diagnostic::from_toml(content, &e));
+    }
+
+    diagnostics.extend(lint(content));
+    diagnostics.sort_by_key(|diagnostic| diagnostic.position);
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V0_CONFIG: &str = r#"theme = "default"
+
+[segments]
+git = false
+cost = true
+"#;
+
+    fn segment_enabled(config: &Config, id: SegmentId) -> bool {
+        config.segments.iter().any(|s| s.id == id && s.enabled)
+    }
+
+    #[test]
+    fn migrates_v0_segment_flags() {
+        let parsed = parse(V0_CONFIG).unwrap();
+        assert_eq!(parsed.version, 0);
+        assert_eq!(parsed.config.version, CONFIG_VERSION);
+        assert_eq!(parsed.config.theme, "default");
+        assert!(!segment_enabled(&parsed.config, SegmentId::Git));
+        assert!(segment_enabled(&parsed.config, SegmentId::Cost));
+        assert!(segment_enabled(&parsed.config, SegmentId::Model));
+    }
+
+    #[test]
+    fn reports_toml_errors_with_positions() {
+        let diagnostic = parse("theme = \"default\"\nseparator = = 1\n")
+            .err()
+            .unwrap();
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.position, Some((2, 13)));
+
+        let diagnostic = parse("[style]\nmode = \"nerdfont\"\n").err().unwrap();
+        assert_eq!(diagnostic.position, Some((2, 8)));
+        assert!(diagnostic.message.contains("nerdfont"));
+    }
+
+    #[test]
+    fn warns_about_unknown_options() {
+        let content =
+            "[[segments]]\nid = \"git\"\noptions = { show_shaa = true, show_sha = true }\n";
+        let diagnostics = lint(content);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].position, Some((3, 13)));
+        assert_eq!(
+            diagnostics[0].message,
+            "unknown option `show_shaa` for the git segment"
+        );
+    }
+
+    #[test]
+    fn backs_up_v0_config_before_rewriting() {
+        let dir = std::env::temp_dir().join(format!("ccline-schema-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, V0_CONFIG).unwrap();
+
+        let config = load_and_migrate(&path).unwrap();
+        assert!(!segment_enabled(&config, SegmentId::Git));
+
+        let backup = dir.join("config.toml.v0.bak");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), V0_CONFIG);
+        let rewritten = parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(rewritten.version, CONFIG_VERSION);
+        assert!(!segment_enabled(&rewritten.config, SegmentId::Git));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
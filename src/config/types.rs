@@ -4,9 +4,43 @@ use std::collections::HashMap;
 // Main config structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// Schema version, bumped when the layout changes; see [`super::schema`]
+    #[serde(default = "super::schema::current_version")]
+    pub version: u32,
     pub style: StyleConfig,
     pub segments: Vec<SegmentConfig>,
     pub theme: String,
+    #[serde(default, skip_serializing_if = "BudgetConfig::is_empty")]
+    pub budget: BudgetConfig,
+    /// Project files allowed to set commands and credential settings; see
+    /// [`super::project`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_projects: Vec<TrustedProject>,
+}
+
+/// A `.ccline.toml` trusted with `ccline trust`, as it was when trusted.
+/// Editing the file revokes the trust.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrustedProject {
+    pub path: String,
+    pub sha256: String,
+}
+
+/// Spending limits in USD. Unset limits are not tracked.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly: Option<f64>,
+}
+
+impl BudgetConfig {
+    pub fn is_empty(&self) -> bool {
+        self.session.is_none() && self.daily.is_none() && self.monthly.is_none()
+    }
 }
 
 // Default implementation moved to ui/themes/presets.rs
@@ -15,6 +49,14 @@ pub struct Config {
 pub struct StyleConfig {
     pub mode: StyleMode,
     pub separator: String,
+    /// Maximum statusline width; segments are dropped or ellipsized to fit.
+    /// Falls back to `COLUMNS` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_width: Option<u16>,
+    /// Milliseconds to wait for segments before rendering without the late
+    /// ones; 100 when unset, 0 waits for every segment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -29,10 +71,20 @@ pub enum StyleMode {
 pub struct SegmentConfig {
     pub id: SegmentId,
     pub enabled: bool,
+    /// Statusline row this segment is rendered on (1-based)
+    #[serde(default = "default_line")]
+    pub line: u8,
     pub icon: IconConfig,
     pub colors: ColorConfig,
     pub styles: TextStyleConfig,
     pub options: HashMap<String, serde_json::Value>,
+    /// Ordered color/style overrides chosen from metadata values; first match wins
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub thresholds: Vec<ThresholdRule>,
+}
+
+fn default_line() -> u8 {
+    1
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,18 +93,45 @@ pub struct IconConfig {
     pub nerd_font: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct ColorConfig {
     pub icon: Option<AnsiColor>,
     pub text: Option<AnsiColor>,
     pub background: Option<AnsiColor>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct TextStyleConfig {
     pub text_bold: bool,
 }
 
+/// Rule that restyles a segment when one of its metadata values crosses a threshold,
+/// e.g. `percentage >= 80`. Colors left unset keep the segment's base colors.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThresholdRule {
+    pub metric: String,
+    #[serde(default)]
+    pub operator: ThresholdOperator,
+    pub value: f64,
+    #[serde(default)]
+    pub colors: ColorConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub styles: Option<TextStyleConfig>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum ThresholdOperator {
+    #[default]
+    #[serde(rename = ">=")]
+    GreaterOrEqual,
+    #[serde(rename = ">")]
+    Greater,
+    #[serde(rename = "<=")]
+    LessOrEqual,
+    #[serde(rename = "<")]
+    Less,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum AnsiColor {
@@ -71,8 +150,12 @@ pub enum SegmentId {
     Usage,
     Cost,
     Session,
+    Tokens,
+    Spend,
+    Budget,
     OutputStyle,
     Update,
+    Custom,
 }
 
 // Legacy compatibility structure
@@ -85,18 +168,18 @@ pub struct SegmentsConfig {
 }
 
 // Data structures compatible with existing main.rs
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Model {
     pub id: String,
     pub display_name: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Workspace {
     pub current_dir: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Cost {
     pub total_cost_usd: Option<f64>,
     pub total_duration_ms: Option<u64>,
@@ -105,12 +188,12 @@ pub struct Cost {
     pub total_lines_removed: Option<u32>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct OutputStyle {
     pub name: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct InputData {
     pub model: Model,
     pub workspace: Workspace,
@@ -239,6 +322,153 @@ impl NormalizedUsage {
     }
 }
 
+impl ThresholdOperator {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            ThresholdOperator::GreaterOrEqual => ">=",
+            ThresholdOperator::Greater => ">",
+            ThresholdOperator::LessOrEqual => "<=",
+            ThresholdOperator::Less => "<",
+        }
+    }
+
+    fn compare(&self, actual: f64, threshold: f64) -> bool {
+        match self {
+            ThresholdOperator::GreaterOrEqual => actual >= threshold,
+            ThresholdOperator::Greater => actual > threshold,
+            ThresholdOperator::LessOrEqual => actual <= threshold,
+            ThresholdOperator::Less => actual < threshold,
+        }
+    }
+}
+
+impl ThresholdRule {
+    /// Check the rule against a segment's metadata; non-numeric or missing values never match
+    pub fn matches(&self, metadata: &HashMap<String, String>) -> bool {
+        metadata
+            .get(&self.metric)
+            .and_then(|v| v.parse::<f64>().ok())
+            .is_some_and(|actual| self.operator.compare(actual, self.value))
+    }
+}
+
+/// `options` keys every segment takes
+pub const COMMON_OPTIONS: &[&str] = &["format", "name", "priority", "min_width", "max_width"];
+
+impl SegmentId {
+    /// Default priority when the statusline is too wide; lower priorities are dropped first
+    pub fn default_priority(&self) -> u64 {
+        match self {
+            SegmentId::Model => 90,
+            SegmentId::ContextWindow => 85,
+            SegmentId::Directory => 80,
+            SegmentId::Git => 70,
+            SegmentId::Budget => 60,
+            SegmentId::Usage => 50,
+            SegmentId::Custom => 50,
+            SegmentId::Cost => 40,
+            SegmentId::Spend => 40,
+            SegmentId::Tokens => 35,
+            SegmentId::Session => 30,
+            SegmentId::OutputStyle => 20,
+            SegmentId::Update => 10,
+        }
+    }
+
+    /// Segment-specific `options` keys, on top of the ones every segment takes
+    /// (see [`COMMON_OPTIONS`])
+    pub fn known_options(&self) -> &'static [&'static str] {
+        match self {
+            SegmentId::Git => &[
+                "show_sha",
+                "status_timeout_ms",
+                "show_staged",
+                "show_modified",
+                "show_untracked",
+                "show_conflicted",
+                "show_stash",
+                "show_operation",
+            ],
+            SegmentId::Usage => &[
+                "api_base_url",
+                "cache_duration",
+                "timeout",
+                "credential_helper",
+                "display",
+                "reset_format",
+                "show_burn_rate",
+                "show_model_bucket",
+            ],
+            SegmentId::Cost => &["estimate"],
+            SegmentId::Custom => &["command", "timeout_ms", "cache_duration"],
+            _ => &[],
+        }
+    }
+
+    /// Default minimum text width when ellipsizing; `None` means the text is never shortened
+    pub fn default_min_width(&self) -> Option<usize> {
+        match self {
+            SegmentId::Directory | SegmentId::Git => Some(8),
+            _ => None,
+        }
+    }
+}
+
+impl SegmentConfig {
+    /// Instance name from the `name` option, used to tell apart multiple segments of one kind
+    pub fn instance_name(&self) -> Option<&str> {
+        self.options.get("name").and_then(|v| v.as_str())
+    }
+
+    /// Drop priority from the `priority` option, falling back to the segment default
+    pub fn priority(&self) -> u64 {
+        self.options
+            .get("priority")
+            .and_then(|v| v.as_u64())
+            .unwrap_or_else(|| self.id.default_priority())
+    }
+
+    /// Minimum text width from the `min_width` option, falling back to the segment default
+    pub fn min_width(&self) -> Option<usize> {
+        self.options
+            .get("min_width")
+            .and_then(|v| v.as_u64())
+            .map(|w| w as usize)
+            .or_else(|| self.id.default_min_width())
+    }
+
+    /// Apply the first threshold rule matching `metadata` over the base colors and styles
+    pub fn apply_thresholds(&mut self, metadata: &HashMap<String, String>) {
+        let Some(rule) = self.thresholds.iter().find(|r| r.matches(metadata)) else {
+            return;
+        };
+
+        let colors = rule.colors.clone();
+        let styles = rule.styles.clone();
+
+        if colors.icon.is_some() {
+            self.colors.icon = colors.icon;
+        }
+        if colors.text.is_some() {
+            self.colors.text = colors.text;
+        }
+        if colors.background.is_some() {
+            self.colors.background = colors.background;
+        }
+        if let Some(styles) = styles {
+            self.styles = styles;
+        }
+    }
+
+    /// Maximum text width from the `max_width` option; longer text is always ellipsized
+    pub fn max_width(&self) -> Option<usize> {
+        self.options
+            .get("max_width")
+            .and_then(|v| v.as_u64())
+            .map(|w| w as usize)
+    }
+}
+
 impl Config {
     /// Check if current config matches the specified theme preset
     pub fn matches_theme(&self, theme_name: &str) -> bool {
@@ -275,6 +505,7 @@ impl Config {
     fn segment_matches(&self, current: &SegmentConfig, preset: &SegmentConfig) -> bool {
         current.id == preset.id
             && current.enabled == preset.enabled
+            && current.line == preset.line
             && current.icon.plain == preset.icon.plain
             && current.icon.nerd_font == preset.icon.nerd_font
             && self.color_matches(&current.colors.icon, &preset.colors.icon)
@@ -282,6 +513,7 @@ impl Config {
             && self.color_matches(&current.colors.background, &preset.colors.background)
             && current.styles.text_bold == preset.styles.text_bold
             && current.options == preset.options
+            && current.thresholds == preset.thresholds
     }
 
     /// Compare two optional colors for equality
@@ -400,6 +632,10 @@ pub type Usage = RawUsage;
 
 #[derive(Deserialize)]
 pub struct Message {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
     pub usage: Option<Usage>,
 }
 
@@ -414,3 +650,67 @@ pub struct TranscriptEntry {
     pub parent_uuid: Option<String>,
     pub summary: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(metric: &str, operator: ThresholdOperator, value: f64) -> ThresholdRule {
+        ThresholdRule {
+            metric: metric.to_string(),
+            operator,
+            value,
+            colors: ColorConfig::default(),
+            styles: None,
+        }
+    }
+
+    fn metadata(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn operators_compare_against_the_value() {
+        use ThresholdOperator::*;
+        let cases = [
+            (GreaterOrEqual, "80", true),
+            (GreaterOrEqual, "79.9", false),
+            (Greater, "80", false),
+            (Greater, "80.1", true),
+            (LessOrEqual, "80", true),
+            (LessOrEqual, "80.1", false),
+            (Less, "80", false),
+            (Less, "-1", true),
+        ];
+        for (operator, actual, expected) in cases {
+            assert_eq!(
+                rule("percentage", operator, 80.0).matches(&metadata(&[("percentage", actual)])),
+                expected,
+                "{:?} {}",
+                operator,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn missing_or_non_numeric_metrics_never_match() {
+        let rule = rule("percentage", ThresholdOperator::Less, 50.0);
+        assert!(!rule.matches(&metadata(&[])));
+        assert!(!rule.matches(&metadata(&[("other", "10")])));
+        assert!(!rule.matches(&metadata(&[("percentage", "ten")])));
+        assert!(!rule.matches(&metadata(&[("percentage", "")])));
+    }
+
+    #[test]
+    fn operator_defaults_to_greater_or_equal() {
+        let rule: ThresholdRule = toml::from_str("metric = \"cost\"\nvalue = 5.0\n").unwrap();
+        assert_eq!(rule.operator, ThresholdOperator::GreaterOrEqual);
+        let rule: ThresholdRule =
+            toml::from_str("metric = \"cost\"\noperator = \"<\"\nvalue = 5.0\n").unwrap();
+        assert_eq!(rule.operator, ThresholdOperator::Less);
+    }
+}
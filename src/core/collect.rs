@@ -0,0 +1,640 @@
+//! Segment collection under a deadline.
+//!
+//! Each enabled segment is collected on its own thread, and the statusline is
+//! rendered once all of them finish or the deadline (`deadline_ms` under
+//! `[style]`) passes. A segment that misses the deadline shows the value it
+//! last rendered in the same directory (or the same session, for segments
+//! about the session), or is left out, and a background
+//! refresh collects it without a deadline for the next render. The threads
+//! are detached rather than scoped, since a scope would wait for the late ones.
+
+use crate::config::{Config, InputData, SegmentConfig, SegmentId};
+use crate::core::segments::SegmentData;
+use crate::refresh::{self, RefreshTask};
+use crate::utils::lock::{self, FileLock};
+use crate::utils::{dry_run, paths};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+/// Deadline when `deadline_ms` is unset
+pub const DEFAULT_DEADLINE: Duration = Duration::from_millis(100);
+
+/// Cached values not rendered for this many days are dropped
+const CACHE_RETENTION_DAYS: i64 = 7;
+
+/// How one configured segment fared
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    Disabled,
+    /// Finished in time; `None` when the segment had nothing to show
+    Collected(Option<SegmentData>),
+    /// Missed the deadline; the cached value, if there is one
+    Late(Option<SegmentData>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Collected {
+    pub outcome: Outcome,
+    /// Time spent in `collect`, or waiting for it when the segment is late
+    pub duration: Duration,
+}
+
+pub fn collect_all_segments(
+    config: &Config,
+    input: &InputData,
+) -> Vec<(SegmentConfig, SegmentData)> {
+    let collected = collect_with_deadline(config, input, deadline(config));
+
+    config
+        .segments
+        .iter()
+        .zip(collected)
+        .filter_map(|(segment_config, collected)| match collected.outcome {
+            Outcome::Collected(Some(data)) | Outcome::Late(Some(data)) => {
+                Some((segment_config.clone(), data))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The config's deadline; `None` means wait for every segment
+pub fn deadline(config: &Config) -> Option<Duration> {
+    match config.style.deadline_ms {
+        Some(0) => None,
+        Some(ms) => Some(Duration::from_millis(ms)),
+        None => Some(DEFAULT_DEADLINE),
+    }
+}
+
+/// Collect every configured segment concurrently, giving up on the ones still
+/// running at `deadline`. Returns one entry per segment, in `config.segments` order.
+pub fn collect_with_deadline(
+    config: &Config,
+    input: &InputData,
+    deadline: Option<Duration>,
+) -> Vec<Collected> {
+    let started = Instant::now();
+    let shared = Arc::new((config.clone(), input.clone()));
+    let (sender, receiver) = mpsc::channel();
+
+    let mut results: Vec<Option<Collected>> = vec![None; config.segments.len()];
+    let mut pending = 0;
+    for (index, segment_config) in config.segments.iter().enumerate() {
+        // Skip disabled segments to avoid unnecessary API requests
+        if !segment_config.enabled {
+            results[index] = Some(Collected {
+                outcome: Outcome::Disabled,
+                duration: Duration::ZERO,
+            });
+            continue;
+        }
+
+        let shared = Arc::clone(&shared);
+        let sender = sender.clone();
+        let spawned = std::thread::Builder::new()
+            .name(format!("segment-{}", index))
+            .spawn(move || {
+                let (config, input) = &*shared;
+                let segment_started = Instant::now();
+                let data = collect_segment(config, &config.segments[index], input);
+                let _ = sender.send((index, data, segment_started.elapsed()));
+            });
+
+        match spawned {
+            Ok(_) => pending += 1,
+            // Out of threads: collect it here instead
+            Err(_) => {
+                let segment_started = Instant::now();
+                let data = collect_segment(config, segment_config, input);
+                results[index] = Some(Collected {
+                    outcome: Outcome::Collected(data),
+                    duration: segment_started.elapsed(),
+                });
+            }
+        }
+    }
+    drop(sender);
+
+    while pending > 0 {
+        let received = match deadline {
+            Some(deadline) => deadline
+                .checked_sub(started.elapsed())
+                .and_then(|remaining| receiver.recv_timeout(remaining).ok()),
+            None => receiver.recv().ok(),
+        };
+        let Some((index, data, duration)) = received else {
+            break;
+        };
+        results[index] = Some(Collected {
+            outcome: Outcome::Collected(data),
+            duration,
+        });
+        pending -= 1;
+    }
+
+    let cache = SegmentCache::load();
+    let waited = started.elapsed();
+    let collected: Vec<Collected> = results
+        .into_iter()
+        .zip(&config.segments)
+        .map(|(collected, segment_config)| {
+            collected.unwrap_or_else(|| Collected {
+                outcome: Outcome::Late(cache.get(input, segment_config)),
+                duration: waited,
+            })
+        })
+        .collect();
+
+    // Without a deadline nothing is ever late, so there's nothing to cache
+    if deadline.is_some() {
+        cache.update(input, config, &collected, false);
+    }
+    if pending > 0 {
+        refresh_later(config, input, &collected);
+    }
+    collected
+}
+
+/// Segments that wait on git, credential stores or commands, and so are the
+/// ones that miss the deadline
+fn may_be_late(id: SegmentId) -> bool {
+    matches!(id, SegmentId::Git | SegmentId::Usage | SegmentId::Custom)
+}
+
+/// What a late render hands to the background refresh: its input and the
+/// effective config, project files and overrides included
+#[derive(Serialize, Deserialize)]
+struct RefreshInput {
+    input: InputData,
+    config: Config,
+}
+
+/// Queue a background collection of the late segments without a deadline,
+/// so segments that keep missing it still get fresh cached values
+fn refresh_later(config: &Config, input: &InputData, collected: &[Collected]) {
+    let mut late = config.clone();
+    late.segments = config
+        .segments
+        .iter()
+        .zip(collected)
+        .filter(|(_, collected)| matches!(collected.outcome, Outcome::Late(_)))
+        .map(|(segment_config, _)| segment_config.clone())
+        .collect();
+    // Scoped by the cache entries it fills, so a refresh for one directory
+    // doesn't hold back another's
+    let key = late
+        .segments
+        .iter()
+        .map(|segment_config| SegmentCache::key(input, segment_config))
+        .collect::<Vec<_>>()
+        .join("\n");
+    refresh::spawn_scoped(
+        RefreshTask::Segments,
+        &key,
+        &RefreshInput {
+            input: input.clone(),
+            config: late,
+        },
+    );
+}
+
+/// Entry point of `ccline refresh segments`: collect the last late render's
+/// late segments with no deadline and cache what they show
+pub fn refresh(scope: Option<&str>) {
+    let Some(RefreshInput { input, config }) = refresh::read_input(RefreshTask::Segments, scope)
+    else {
+        return;
+    };
+    let collected = collect_with_deadline(&config, &input, None);
+    SegmentCache::load().update(&input, &config, &collected, true);
+}
+
+/// Last value each segment rendered, per directory or session
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SegmentCache {
+    entries: HashMap<String, CachedSegment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedSegment {
+    primary: String,
+    secondary: String,
+    metadata: HashMap<String, String>,
+    cached_at: String,
+}
+
+impl SegmentCache {
+    fn path() -> PathBuf {
+        paths::cache_dir().join(".segment_cache.json")
+    }
+
+    fn load() -> Self {
+        std::fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Segments about the session are keyed by its transcript, so another
+    /// session in the same directory doesn't show their values
+    fn key(input: &InputData, segment_config: &SegmentConfig) -> String {
+        let session_scoped = matches!(
+            segment_config.id,
+            SegmentId::Model
+                | SegmentId::ContextWindow
+                | SegmentId::Cost
+                | SegmentId::Session
+                | SegmentId::Tokens
+                | SegmentId::Budget
+                | SegmentId::OutputStyle
+        );
+        let scope = if session_scoped && !input.transcript_path.is_empty() {
+            &input.transcript_path
+        } else {
+            &input.workspace.current_dir
+        };
+        match segment_config.instance_name() {
+            Some(name) => format!("{}\n{:?}:{}", scope, segment_config.id, name),
+            None => format!("{}\n{:?}", scope, segment_config.id),
+        }
+    }
+
+    fn get(&self, input: &InputData, segment_config: &SegmentConfig) -> Option<SegmentData> {
+        self.entries
+            .get(&Self::key(input, segment_config))
+            .map(|cached| SegmentData {
+                primary: cached.primary.clone(),
+                secondary: cached.secondary.clone(),
+                metadata: cached.metadata.clone(),
+            })
+    }
+
+    /// Whether storing `data` would change the cache
+    fn differs(&self, key: &str, data: Option<&SegmentData>) -> bool {
+        match (self.entries.get(key), data) {
+            (Some(cached), Some(data)) => {
+                cached.primary != data.primary
+                    || cached.secondary != data.secondary
+                    || cached.metadata != data.metadata
+            }
+            (None, None) => false,
+            _ => true,
+        }
+    }
+
+    /// Store the segments that finished in time; empty ones are removed so a
+    /// late render doesn't bring them back. Unless `all` is set, only segments
+    /// that can be slow or have been late before are stored, so renders don't
+    /// rewrite the cache for values that are never read back.
+    fn update(&self, input: &InputData, config: &Config, collected: &[Collected], all: bool) {
+        if dry_run::is_enabled() {
+            return;
+        }
+        let finished: Vec<(String, Option<&SegmentData>)> = config
+            .segments
+            .iter()
+            .zip(collected)
+            .filter_map(|(segment_config, collected)| {
+                let Outcome::Collected(data) = &collected.outcome else {
+                    return None;
+                };
+                let key = Self::key(input, segment_config);
+                let wanted =
+                    all || may_be_late(segment_config.id) || self.entries.contains_key(&key);
+                wanted.then_some((key, data.as_ref()))
+            })
+            .collect();
+
+        // Cheap unlocked check so unchanged renders don't contend for the lock
+        if !finished.iter().any(|(key, data)| self.differs(key, *data)) {
+            return;
+        }
+
+        let path = Self::path();
+        let Some(_lock) =
+            FileLock::acquire(&path.with_extension("json.lock"), Duration::from_millis(20))
+        else {
+            return;
+        };
+
+        let mut cache = Self::load();
+        let now = Utc::now();
+        for (key, data) in finished {
+            match data {
+                Some(data) => {
+                    cache.entries.insert(
+                        key,
+                        CachedSegment {
+                            primary: data.primary.clone(),
+                            secondary: data.secondary.clone(),
+                            metadata: data.metadata.clone(),
+                            cached_at: now.to_rfc3339(),
+                        },
+                    );
+                }
+                None => {
+                    cache.entries.remove(&key);
+                }
+            }
+        }
+
+        let cutoff = now - ChronoDuration::days(CACHE_RETENTION_DAYS);
+        cache.entries.retain(|_, cached| {
+            DateTime::parse_from_rfc3339(&cached.cached_at)
+                .is_ok_and(|cached_at| cached_at.with_timezone(&Utc) > cutoff)
+        });
+
+        if let Ok(json) = serde_json::to_string_pretty(&cache) {
+            let _ = lock::write_atomic(&path, json.as_bytes());
+        }
+    }
+}
+
+/// Build one segment from its options and collect its data
+pub fn collect_segment(
+    config: &Config,
+    segment_config: &SegmentConfig,
+    input: &InputData,
+) -> Option<SegmentData> {
+    use crate::core::segments::*;
+
+    match segment_config.id {
+        crate::config::SegmentId::Model => {
+            let segment = ModelSegment::new();
+            segment.collect(input)
+        }
+        crate::config::SegmentId::Directory => {
+            let segment = DirectorySegment::new();
+            segment.collect(input)
+        }
+        crate::config::SegmentId::Git => {
+            let show_sha = segment_config
+                .options
+                .get("show_sha")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let status_timeout = segment_config
+                .options
+                .get("status_timeout_ms")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(500);
+            let defaults = GitElements::default();
+            let flag = |key: &str, default: bool| {
+                segment_config
+                    .options
+                    .get(key)
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(default)
+            };
+            let elements = GitElements {
+                staged: flag("show_staged", defaults.staged),
+                modified: flag("show_modified", defaults.modified),
+                untracked: flag("show_untracked", defaults.untracked),
+                conflicted: flag("show_conflicted", defaults.conflicted),
+                stash: flag("show_stash", defaults.stash),
+                operation: flag("show_operation", defaults.operation),
+            };
+            let segment = GitSegment::new()
+                .with_sha(show_sha)
+                .with_status_timeout(std::time::Duration::from_millis(status_timeout))
+                .with_elements(elements);
+            segment.collect(input)
+        }
+        crate::config::SegmentId::ContextWindow => {
+            let segment = ContextWindowSegment::new();
+            segment.collect(input)
+        }
+        crate::config::SegmentId::Usage => {
+            let segment = UsageSegment::new().with_options(segment_config.options.clone());
+            segment.collect(input)
+        }
+        crate::config::SegmentId::Cost => {
+            let estimate = segment_config
+                .options
+                .get("estimate")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true);
+            let segment = CostSegment::new().with_estimate(estimate);
+            segment.collect(input)
+        }
+        crate::config::SegmentId::Session => {
+            let segment = SessionSegment::new();
+            segment.collect(input)
+        }
+        crate::config::SegmentId::Budget => {
+            let segment = BudgetSegment::new(config.budget.clone());
+            segment.collect(input)
+        }
+        crate::config::SegmentId::Spend => {
+            let segment = SpendSegment::new();
+            segment.collect(input)
+        }
+        crate::config::SegmentId::Tokens => {
+            let segment = TokensSegment::new();
+            segment.collect(input)
+        }
+        crate::config::SegmentId::OutputStyle => {
+            let segment = OutputStyleSegment::new();
+            segment.collect(input)
+        }
+        crate::config::SegmentId::Update => {
+            let segment = UpdateSegment::new();
+            segment.collect(input)
+        }
+        crate::config::SegmentId::Custom => {
+            let command = segment_config
+                .options
+                .get("command")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            if command.is_empty() {
+                return None;
+            }
+            let timeout_ms = segment_config
+                .options
+                .get("timeout_ms")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(500);
+            let cache_duration = segment_config
+                .options
+                .get("cache_duration")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0);
+            let segment =
+                CustomSegment::new(segment_config.instance_name().unwrap_or("custom"), command)
+                    .with_timeout_ms(timeout_ms)
+                    .with_cache_duration(cache_duration);
+            segment.collect(input)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::project::segment_template;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ccline-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn input(dir: &std::path::Path, transcript: &str) -> InputData {
+        serde_json::from_value(serde_json::json!({
+            "model": { "id": "claude-sonnet-4-5", "display_name": "Sonnet 4.5" },
+            "workspace": { "current_dir": dir.to_string_lossy() },
+            "transcript_path": transcript,
+        }))
+        .unwrap()
+    }
+
+    fn custom(name: &str, command: &str) -> SegmentConfig {
+        let mut segment = segment_template(SegmentId::Custom, "default");
+        segment.options.insert("name".to_string(), name.into());
+        segment
+            .options
+            .insert("command".to_string(), command.into());
+        segment
+    }
+
+    fn config(segments: Vec<SegmentConfig>) -> Config {
+        Config {
+            segments,
+            ..Config::default()
+        }
+    }
+
+    fn collected(data: Option<&str>) -> Collected {
+        Collected {
+            outcome: Outcome::Collected(data.map(|primary| SegmentData {
+                primary: primary.to_string(),
+                secondary: String::new(),
+                metadata: HashMap::new(),
+            })),
+            duration: Duration::ZERO,
+        }
+    }
+
+    fn primary(collected: &Collected) -> Option<&str> {
+        match &collected.outcome {
+            Outcome::Collected(data) | Outcome::Late(data) => {
+                data.as_ref().map(|data| data.primary.as_str())
+            }
+            Outcome::Disabled => None,
+        }
+    }
+
+    #[test]
+    fn results_follow_config_order() {
+        let dir = temp_dir("collect-order");
+        let config = config(vec![
+            custom("slow", "sleep 0.2; echo slow"),
+            segment_template(SegmentId::Directory, "default"),
+            custom("fast", "echo fast"),
+        ]);
+
+        let collected = collect_with_deadline(&config, &input(&dir, ""), None);
+
+        let primaries: Vec<_> = collected.iter().map(primary).collect();
+        let dir_name = dir.file_name().unwrap().to_str();
+        assert_eq!(primaries, [Some("slow"), dir_name, Some("fast")]);
+        assert!(collected
+            .iter()
+            .all(|collected| matches!(collected.outcome, Outcome::Collected(_))));
+    }
+
+    #[test]
+    fn late_segments_render_from_cache_or_are_left_out() {
+        paths::use_test_dir();
+        let dir = temp_dir("collect-late");
+        let input = input(&dir, "");
+        let cached = custom("cached", "sleep 1");
+        let config = config(vec![custom("uncached", "sleep 1"), cached.clone()]);
+        SegmentCache::default().update(
+            &input,
+            &self::config(vec![cached]),
+            &[collected(Some("from cache"))],
+            true,
+        );
+
+        let collected = collect_with_deadline(&config, &input, Some(Duration::from_millis(50)));
+
+        assert!(matches!(collected[0].outcome, Outcome::Late(None)));
+        assert!(matches!(collected[1].outcome, Outcome::Late(Some(_))));
+        assert_eq!(primary(&collected[1]), Some("from cache"));
+    }
+
+    #[test]
+    fn only_segments_that_may_be_late_are_cached() {
+        paths::use_test_dir();
+        let dir = temp_dir("collect-cached");
+        let input = input(&dir, "");
+        let segments: Vec<SegmentConfig> = [
+            SegmentId::Model,
+            SegmentId::Directory,
+            SegmentId::Git,
+            SegmentId::Usage,
+        ]
+        .into_iter()
+        .map(|id| segment_template(id, "default"))
+        .chain([custom("mine", "true")])
+        .collect();
+        let config = config(segments);
+        let results: Vec<Collected> = (0..config.segments.len())
+            .map(|_| collected(Some("shown")))
+            .collect();
+
+        SegmentCache::default().update(&input, &config, &results, false);
+        let cache = SegmentCache::load();
+        let cached: Vec<SegmentId> = config
+            .segments
+            .iter()
+            .filter(|segment| cache.get(&input, segment).is_some())
+            .map(|segment| segment.id)
+            .collect();
+        assert_eq!(
+            cached,
+            [SegmentId::Git, SegmentId::Usage, SegmentId::Custom]
+        );
+
+        // Refreshes store everything
+        SegmentCache::load().update(&input, &config, &results, true);
+        let cache = SegmentCache::load();
+        assert!(config
+            .segments
+            .iter()
+            .all(|segment| cache.get(&input, segment).is_some()));
+    }
+
+    #[test]
+    fn session_segments_are_keyed_by_transcript() {
+        let dir = temp_dir("collect-keys");
+        let first = input(&dir, "/sessions/first.jsonl");
+        let second = input(&dir, "/sessions/second.jsonl");
+        let cost = segment_template(SegmentId::Cost, "default");
+        let git = segment_template(SegmentId::Git, "default");
+
+        assert_ne!(
+            SegmentCache::key(&first, &cost),
+            SegmentCache::key(&second, &cost)
+        );
+        assert_eq!(
+            SegmentCache::key(&first, &git),
+            SegmentCache::key(&second, &git)
+        );
+        // Without a transcript, session segments fall back to the directory
+        let no_transcript = input(&dir, "");
+        assert!(SegmentCache::key(&no_transcript, &cost)
+            .starts_with(&no_transcript.workspace.current_dir));
+        assert_ne!(
+            SegmentCache::key(&first, &custom("a", "true")),
+            SegmentCache::key(&first, &custom("b", "true"))
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! Diagnostics for `--debug` and `--debug-report`: how long each segment took,
+//! why empty ones rendered nothing, and how the model was resolved.
+
+use crate::config::{Config, InputData, ModelConfig, ModelResolution, SegmentConfig, SegmentId};
+use crate::core::collect::{self, Outcome};
+use crate::core::segments::SegmentData;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceStatus {
+    Rendered,
+    /// `collect` returned `None`
+    Empty,
+    /// Missed the deadline; rendered from cache if `primary` is set
+    Late,
+    Disabled,
+}
+
+/// What one configured segment produced
+#[derive(Debug, Clone, Serialize)]
+pub struct SegmentTrace {
+    pub id: SegmentId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub status: TraceStatus,
+    /// Likely cause when the segment is empty
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub duration_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub primary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secondary: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Everything `--debug` prints, and what `--debug-report` writes as JSON
+#[derive(Debug, Clone, Serialize)]
+pub struct DebugReport {
+    pub model: ModelResolution,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_error: Option<String>,
+    pub segments: Vec<SegmentTrace>,
+    pub collect_ms: f64,
+    /// `None` when collection waits for every segment
+    pub deadline_ms: Option<f64>,
+    pub render_ms: f64,
+    pub max_width: Option<u16>,
+    pub statusline: String,
+}
+
+impl DebugReport {
+    pub fn new(input: &InputData) -> Self {
+        Self {
+            model: ModelConfig::load().explain(&input.model.id),
+            config_error: None,
+            segments: Vec::new(),
+            collect_ms: 0.0,
+            deadline_ms: None,
+            render_ms: 0.0,
+            max_width: None,
+            statusline: String::new(),
+        }
+    }
+
+    /// Same as [`collect_all_segments`](crate::core::collect_all_segments),
+    /// recording a trace for every configured segment
+    pub fn collect(
+        &mut self,
+        config: &Config,
+        input: &InputData,
+    ) -> Vec<(SegmentConfig, SegmentData)> {
+        let started = Instant::now();
+        let deadline = collect::deadline(config);
+        let collected = collect::collect_with_deadline(config, input, deadline);
+        self.collect_ms = millis(started.elapsed());
+        self.deadline_ms = deadline.map(millis);
+
+        let mut results = Vec::new();
+        for (segment_config, collected) in config.segments.iter().zip(collected) {
+            let mut trace = SegmentTrace {
+                id: segment_config.id,
+                name: segment_config.instance_name().map(|name| name.to_string()),
+                status: TraceStatus::Disabled,
+                reason: None,
+                duration_ms: millis(collected.duration),
+                primary: None,
+                secondary: None,
+                metadata: BTreeMap::new(),
+            };
+
+            let data = match collected.outcome {
+                Outcome::Disabled => None,
+                Outcome::Collected(data) => {
+                    trace.status = TraceStatus::Rendered;
+                    if data.is_none() {
+                        trace.status = TraceStatus::Empty;
+                        trace.reason = Some(empty_reason(config, segment_config, input));
+                    }
+                    data
+                }
+                Outcome::Late(data) => {
+                    trace.status = TraceStatus::Late;
+                    trace.reason = Some(match data {
+                        Some(_) => "missed the deadline; showing its cached value".to_string(),
+                        None => "missed the deadline and nothing is cached; omitted".to_string(),
+                    });
+                    data
+                }
+            };
+
+            if let Some(data) = data {
+                trace.primary = Some(data.primary.clone());
+                trace.secondary = Some(data.secondary.clone());
+                trace.metadata = data.metadata.clone().into_iter().collect();
+                results.push((segment_config.clone(), data));
+            }
+            self.segments.push(trace);
+        }
+
+        results
+    }
+
+    /// Record the rendered statusline with ANSI codes stripped
+    pub fn finish(&mut self, statusline: &str, render_time: Duration) {
+        self.statusline = strip_ansi(statusline);
+        self.render_ms = millis(render_time);
+    }
+
+    /// Human-readable report on stderr, out of the way of the statusline
+    pub fn print(&self) {
+        let model = &self.model;
+        eprintln!("── ccline debug ──");
+        eprintln!(
+            "model:      {} → {} ({})",
+            model.model_id,
+            model.display_name.as_deref().unwrap_or("(not recognized)"),
+            model.matched
+        );
+        eprintln!(
+            "context:    {}{}{}",
+            model.context_limit,
+            if model.default_context_limit {
+                " (default)"
+            } else {
+                ""
+            },
+            model
+                .modifier
+                .as_ref()
+                .map(|modifier| format!(", modifier `{}`", modifier.pattern))
+                .unwrap_or_default()
+        );
+        eprintln!(
+            "pricing:    {}",
+            model
+                .pricing
+                .map(|pricing| pricing.describe())
+                .unwrap_or_else(|| "unknown; costs aren't estimated".to_string())
+        );
+        if let Some(error) = &self.config_error {
+            eprintln!("config:     {}", error);
+        }
+        if let Some(width) = self.max_width {
+            eprintln!("max width:  {}", width);
+        }
+
+        eprintln!(
+            "segments:   collected in {:.1}ms ({}), rendered in {:.1}ms",
+            self.collect_ms,
+            self.deadline_ms
+                .map(|deadline| format!("deadline {:.0}ms", deadline))
+                .unwrap_or_else(|| "no deadline".to_string()),
+            self.render_ms
+        );
+        for trace in &self.segments {
+            let label = match &trace.name {
+                Some(name) => format!("{:?}:{}", trace.id, name),
+                None => format!("{:?}", trace.id),
+            };
+            let reason = trace.reason.as_deref().unwrap_or_default();
+            match trace.status {
+                TraceStatus::Disabled => eprintln!("  {:<16} {:>8}  disabled", label, "-"),
+                TraceStatus::Empty => {
+                    eprintln!(
+                        "  {:<16} {:>6.1}ms  empty: {}",
+                        label, trace.duration_ms, reason
+                    )
+                }
+                TraceStatus::Late => {
+                    eprintln!(
+                        "  {:<16} {:>6.1}ms  late: {}",
+                        label, trace.duration_ms, reason
+                    )
+                }
+                TraceStatus::Rendered => eprintln!(
+                    "  {:<16} {:>6.1}ms  {:?} {:?}",
+                    label,
+                    trace.duration_ms,
+                    trace.primary.as_deref().unwrap_or_default(),
+                    trace.secondary.as_deref().unwrap_or_default()
+                ),
+            }
+            if trace.status == TraceStatus::Late {
+                if let Some(primary) = &trace.primary {
+                    eprintln!(
+                        "  {:<16} {:>8}  {:?} {:?}",
+                        "",
+                        "",
+                        primary,
+                        trace.secondary.as_deref().unwrap_or_default()
+                    );
+                }
+            }
+            for (key, value) in &trace.metadata {
+                eprintln!("  {:<16} {:>8}  {} = {}", "", "", key, value);
+            }
+        }
+        eprintln!("statusline: {}", self.statusline);
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n")
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(())
+    }
+}
+
+/// Best guess at why a segment collected nothing, from what it depends on
+fn empty_reason(config: &Config, segment_config: &SegmentConfig, input: &InputData) -> String {
+    let cost = input.cost.as_ref();
+    let reason = match segment_config.id {
+        SegmentId::Git => "not inside a git repository, or git failed or timed out",
+        SegmentId::ContextWindow => "no usage found in the transcript",
+        SegmentId::Usage => {
+            "no OAuth credentials, or no usage cached yet (a background refresh fetches it)"
+        }
+        SegmentId::Cost if cost.and_then(|c| c.total_cost_usd).is_none() => {
+            "no cost.total_cost_usd in the input, and no estimate from the transcript \
+             (unknown model pricing, or `estimate = false`)"
+        }
+        SegmentId::Session if cost.is_none() => "no cost object in the input",
+        SegmentId::Session if cost.and_then(|c| c.total_duration_ms).is_none() => {
+            "no cost.total_duration_ms in the input"
+        }
+        SegmentId::Budget if config.budget.is_empty() => "no budgets set in [budget]",
+        SegmentId::Budget => "no spend recorded for any budgeted period",
+        SegmentId::Spend => "nothing recorded in the spend ledger yet",
+        SegmentId::Tokens => "transcript missing or has no assistant messages",
+        SegmentId::OutputStyle => "no output_style in the input",
+        SegmentId::Update => "no update available or being installed",
+        SegmentId::Custom
+            if segment_config
+                .options
+                .get("command")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .is_empty() =>
+        {
+            "no command configured; a project file's command is ignored until it's trusted \
+             (see `ccline check`)"
+        }
+        SegmentId::Custom => "command failed, timed out or printed nothing",
+        _ => "the input had nothing to show",
+    };
+    reason.to_string()
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn strip_ansi(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            // Skip `[`, parameters and the final letter
+            for ch in chars.by_ref() {
+                if ch.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            plain.push(ch);
+        }
+    }
+    plain
+}
@@ -1,4 +1,10 @@
+pub mod collect;
+pub mod debug;
 pub mod segments;
 pub mod statusline;
+pub mod template;
 
-pub use statusline::{collect_all_segments, StatusLineGenerator};
+pub use collect::collect_all_segments;
+pub use debug::DebugReport;
+pub use statusline::StatusLineGenerator;
+pub use template::Template;
@@ -0,0 +1,130 @@
+use super::{Segment, SegmentData};
+use crate::config::{BudgetConfig, InputData, SegmentId};
+use crate::ledger::{format_usd, Ledger};
+use std::collections::HashMap;
+
+/// Remaining budget for whichever configured limit is closest to being used up.
+///
+/// Session spend comes from Claude Code; daily and monthly spend come from the
+/// cost ledger, which already includes the current session.
+pub struct BudgetSegment {
+    budget: BudgetConfig,
+}
+
+struct BudgetStatus {
+    scope: &'static str,
+    label: &'static str,
+    limit: f64,
+    spent: f64,
+}
+
+impl BudgetStatus {
+    fn used_percent(&self) -> f64 {
+        self.spent / self.limit * 100.0
+    }
+
+    fn remaining(&self) -> f64 {
+        self.limit - self.spent
+    }
+}
+
+impl BudgetSegment {
+    pub fn new(budget: BudgetConfig) -> Self {
+        Self { budget }
+    }
+}
+
+impl Segment for BudgetSegment {
+    fn collect(&self, input: &InputData) -> Option<SegmentData> {
+        if self.budget.is_empty() {
+            return None;
+        }
+
+        let mut statuses = Vec::new();
+
+        if let Some(limit) = self.budget.session {
+            if let Some(spent) = input.cost.as_ref().and_then(|c| c.total_cost_usd) {
+                statuses.push(BudgetStatus {
+                    scope: "session",
+                    label: "session",
+                    limit,
+                    spent,
+                });
+            }
+        }
+
+        if self.budget.daily.is_some() || self.budget.monthly.is_some() {
+            let ledger = Ledger::load();
+            if let Some(limit) = self.budget.daily {
+                statuses.push(BudgetStatus {
+                    scope: "daily",
+                    label: "today",
+                    limit,
+                    spent: ledger.summary_since(Ledger::today()).total,
+                });
+            }
+            if let Some(limit) = self.budget.monthly {
+                statuses.push(BudgetStatus {
+                    scope: "monthly",
+                    label: "this month",
+                    limit,
+                    spent: ledger.summary_since(Ledger::month_start()).total,
+                });
+            }
+        }
+
+        let mut metadata = HashMap::new();
+        for status in &statuses {
+            metadata.insert(
+                format!("{}_used_percent", status.scope),
+                status.used_percent().to_string(),
+            );
+            metadata.insert(
+                format!("{}_remaining", status.scope),
+                status.remaining().to_string(),
+            );
+        }
+
+        // Show the tightest budget
+        let tightest = statuses
+            .iter()
+            .max_by(|a, b| a.used_percent().total_cmp(&b.used_percent()))?;
+
+        metadata.insert("scope".to_string(), tightest.scope.to_string());
+        metadata.insert(
+            "used_percent".to_string(),
+            tightest.used_percent().to_string(),
+        );
+        metadata.insert("remaining".to_string(), tightest.remaining().to_string());
+        metadata.insert(
+            "remaining_display".to_string(),
+            format_usd(tightest.remaining().abs()),
+        );
+        metadata.insert("limit".to_string(), tightest.limit.to_string());
+        metadata.insert("spent".to_string(), tightest.spent.to_string());
+
+        let primary = if tightest.remaining() >= 0.0 {
+            format!(
+                "{} left {}",
+                format_usd(tightest.remaining()),
+                tightest.label
+            )
+        } else {
+            format!(
+                "{} over {}",
+                format_usd(-tightest.remaining()),
+                tightest.label
+            )
+        };
+
+        Some(SegmentData {
+            primary,
+            secondary: format!("({:.0}%)", tightest.used_percent()),
+            metadata,
+        })
+    }
+
+    fn id(&self) -> SegmentId {
+        SegmentId::Budget
+    }
+}
@@ -1,5 +1,6 @@
 use super::{Segment, SegmentData};
 use crate::config::{InputData, ModelConfig, SegmentId, TranscriptEntry};
+use crate::utils::transcript::{self, ReverseLines, TranscriptCache, TranscriptCacheEntry};
 use std::collections::HashMap;
 use std::fs;
 use std::io::{BufRead, BufReader};
@@ -38,12 +39,7 @@ impl Segment for ContextWindowSegment {
                 };
 
                 let tokens = if context_used_token >= 1000 {
-                    let k_value = context_used_token as f64 / 1000.0;
-                    if k_value.fract() == 0.0 {
-                        format!("{}k", k_value as u32)
-                    } else {
-                        format!("{:.1}k", k_value)
-                    }
+                    format_thousands(context_used_token)
                 } else {
                     context_used_token.to_string()
                 };
@@ -62,6 +58,8 @@ impl Segment for ContextWindowSegment {
                 let context_used_rate = (context_used_token as f64 / context_limit as f64) * 100.0;
                 metadata.insert("tokens".to_string(), context_used_token.to_string());
                 metadata.insert("percentage".to_string(), context_used_rate.to_string());
+                metadata.insert("tokens_k".to_string(), format_thousands(context_used_token));
+                metadata.insert("percentage_display".to_string(), percentage_display.clone());
             }
             None => {
                 metadata.insert("tokens".to_string(), "-".to_string());
@@ -69,6 +67,7 @@ impl Segment for ContextWindowSegment {
             }
         }
         metadata.insert("limit".to_string(), context_limit.to_string());
+        metadata.insert("limit_k".to_string(), format_thousands(context_limit));
         metadata.insert("model".to_string(), input.model.id.clone());
 
         Some(SegmentData {
@@ -83,6 +82,16 @@ impl Segment for ContextWindowSegment {
     }
 }
 
+/// Format a token count in thousands, e.g. `156400` -> `156.4k`, `200000` -> `200k`
+fn format_thousands(tokens: u32) -> String {
+    let k_value = tokens as f64 / 1000.0;
+    if k_value.fract() == 0.0 {
+        format!("{}k", k_value as u32)
+    } else {
+        format!("{:.1}k", k_value)
+    }
+}
+
 fn parse_transcript_usage<P: AsRef<Path>>(transcript_path: P) -> Option<u32> {
     let path = transcript_path.as_ref();
 
@@ -101,38 +110,62 @@ fn parse_transcript_usage<P: AsRef<Path>>(transcript_path: P) -> Option<u32> {
     None
 }
 
+/// Context usage from the newest assistant message in the transcript.
+///
+/// The file is read backwards from the end. The cache records how far the
+/// file had been parsed, so later refreshes only look at appended lines and
+/// fall back to the cached value when none of them carry usage.
 fn try_parse_transcript_file(path: &Path) -> Option<u32> {
     let file = fs::File::open(path).ok()?;
-    let reader = BufReader::new(file);
-    let lines: Vec<String> = reader
-        .lines()
-        .collect::<Result<Vec<_>, _>>()
-        .unwrap_or_default();
-
-    if lines.is_empty() {
-        return None;
+    let metadata = file.metadata().ok()?;
+
+    let mut cache = TranscriptCache::<Option<u32>>::load(".transcript_context_cache.json");
+    let cached = cache.get(path, &metadata).cloned();
+    let floor = cached.as_ref().map_or(0, |entry| entry.offset);
+    let complete_len = transcript::complete_len(&file, metadata.len());
+
+    let lines = ReverseLines::new(file, floor, complete_len);
+    let context_tokens =
+        find_latest_usage(lines, path).or_else(|| cached.as_ref().and_then(|entry| entry.value));
+
+    let unchanged = cached
+        .as_ref()
+        .is_some_and(|entry| entry.offset == complete_len && entry.value == context_tokens);
+    if !unchanged {
+        cache.insert(
+            path,
+            TranscriptCacheEntry {
+                inode: transcript::file_inode(&metadata),
+                offset: complete_len,
+                value: context_tokens,
+                updated_at: String::new(),
+            },
+        );
+        cache.save();
     }
 
-    // Check if the last line is a summary
-    let last_line = lines.last()?.trim();
-    if let Ok(entry) = serde_json::from_str::<TranscriptEntry>(last_line) {
-        if entry.r#type.as_deref() == Some("summary") {
-            // Handle summary case: find usage by leafUuid
-            if let Some(leaf_uuid) = &entry.leaf_uuid {
-                let project_dir = path.parent()?;
-                return find_usage_by_leaf_uuid(leaf_uuid, project_dir);
-            }
-        }
-    }
+    context_tokens
+}
+
+/// Scan lines newest first for the last assistant usage
+fn find_latest_usage(lines: impl Iterator<Item = String>, path: &Path) -> Option<u32> {
+    let mut is_last_line = true;
 
-    // Normal case: find the last assistant message in current file
-    for line in lines.iter().rev() {
+    for line in lines {
         let line = line.trim();
         if line.is_empty() {
             continue;
         }
 
         if let Ok(entry) = serde_json::from_str::<TranscriptEntry>(line) {
+            // A trailing summary points at the message it summarizes: find usage by leafUuid
+            if is_last_line && entry.r#type.as_deref() == Some("summary") {
+                if let Some(leaf_uuid) = &entry.leaf_uuid {
+                    let project_dir = path.parent()?;
+                    return find_usage_by_leaf_uuid(leaf_uuid, project_dir);
+                }
+            }
+
             if entry.r#type.as_deref() == Some("assistant") {
                 if let Some(message) = &entry.message {
                     if let Some(raw_usage) = &message.usage {
@@ -142,6 +175,8 @@ fn try_parse_transcript_file(path: &Path) -> Option<u32> {
                 }
             }
         }
+
+        is_last_line = false;
     }
 
     None
@@ -167,70 +202,43 @@ fn find_usage_by_leaf_uuid(leaf_uuid: &str, project_dir: &Path) -> Option<u32> {
     None
 }
 
-fn search_uuid_in_file(path: &Path, target_uuid: &str) -> Option<u32> {
+/// Stream the non-empty transcript entries of a file, oldest first
+fn transcript_entries(path: &Path) -> Option<impl Iterator<Item = TranscriptEntry>> {
     let file = fs::File::open(path).ok()?;
-    let reader = BufReader::new(file);
-    let lines: Vec<String> = reader
-        .lines()
-        .collect::<Result<Vec<_>, _>>()
-        .unwrap_or_default();
-
-    // Find the message with target_uuid
-    for line in &lines {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
+    Some(
+        BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str::<TranscriptEntry>(line.trim()).ok()),
+    )
+}
 
-        if let Ok(entry) = serde_json::from_str::<TranscriptEntry>(line) {
-            if let Some(uuid) = &entry.uuid {
-                if uuid == target_uuid {
-                    // Found the target message, check its type
-                    if entry.r#type.as_deref() == Some("assistant") {
-                        // Direct assistant message with usage
-                        if let Some(message) = &entry.message {
-                            if let Some(raw_usage) = &message.usage {
-                                let normalized = raw_usage.clone().normalize();
-                                return Some(normalized.display_tokens());
-                            }
-                        }
-                    } else if entry.r#type.as_deref() == Some("user") {
-                        // User message, need to find the parent assistant message
-                        if let Some(parent_uuid) = &entry.parent_uuid {
-                            return find_assistant_message_by_uuid(&lines, parent_uuid);
-                        }
-                    }
-                    break;
-                }
-            }
-        }
+fn assistant_usage(entry: &TranscriptEntry) -> Option<u32> {
+    if entry.r#type.as_deref() != Some("assistant") {
+        return None;
     }
-
-    None
+    let raw_usage = entry.message.as_ref()?.usage.as_ref()?;
+    Some(raw_usage.clone().normalize().display_tokens())
 }
 
-fn find_assistant_message_by_uuid(lines: &[String], target_uuid: &str) -> Option<u32> {
-    for line in lines {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
-
-        if let Ok(entry) = serde_json::from_str::<TranscriptEntry>(line) {
-            if let Some(uuid) = &entry.uuid {
-                if uuid == target_uuid && entry.r#type.as_deref() == Some("assistant") {
-                    if let Some(message) = &entry.message {
-                        if let Some(raw_usage) = &message.usage {
-                            let normalized = raw_usage.clone().normalize();
-                            return Some(normalized.display_tokens());
-                        }
-                    }
-                }
-            }
-        }
+fn search_uuid_in_file(path: &Path, target_uuid: &str) -> Option<u32> {
+    // Find the message with target_uuid
+    let entry =
+        transcript_entries(path)?.find(|entry| entry.uuid.as_deref() == Some(target_uuid))?;
+
+    match entry.r#type.as_deref() {
+        // Direct assistant message with usage
+        Some("assistant") => assistant_usage(&entry),
+        // User message, need to find the parent assistant message
+        Some("user") => find_assistant_message_by_uuid(path, entry.parent_uuid.as_deref()?),
+        _ => None,
     }
+}
 
-    None
+fn find_assistant_message_by_uuid(path: &Path, target_uuid: &str) -> Option<u32> {
+    transcript_entries(path)?
+        .filter(|entry| entry.uuid.as_deref() == Some(target_uuid))
+        .find_map(|entry| assistant_usage(&entry))
 }
 
 fn try_find_usage_from_project_history(transcript_path: &Path) -> Option<u32> {
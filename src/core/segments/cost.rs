@@ -1,37 +1,118 @@
 use super::{Segment, SegmentData};
-use crate::config::{InputData, SegmentId};
+use crate::config::{InputData, ModelConfig, SegmentId};
+use crate::utils::transcript;
 use std::collections::HashMap;
 
-#[derive(Default)]
-pub struct CostSegment;
+/// Relative difference above which upstream and estimated cost are both shown
+const DISAGREEMENT_RATIO: f64 = 0.05;
+
+pub struct CostSegment {
+    estimate: bool,
+}
+
+impl Default for CostSegment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl CostSegment {
     pub fn new() -> Self {
-        Self
+        Self { estimate: true }
     }
-}
 
-impl Segment for CostSegment {
-    fn collect(&self, input: &InputData) -> Option<SegmentData> {
-        let cost_data = input.cost.as_ref()?;
+    /// Estimate cost from transcript usage and `models.toml` pricing
+    pub fn with_estimate(mut self, estimate: bool) -> Self {
+        self.estimate = estimate;
+        self
+    }
+
+    fn format_cost(cost: f64) -> String {
+        if cost == 0.0 || cost < 0.01 {
+            "$0".to_string()
+        } else {
+            format!("${:.2}", cost)
+        }
+    }
+
+    /// Sum transcript usage priced per model. Messages without a model fall back
+    /// to the session model. Returns `None` if no message has known pricing.
+    fn estimate_cost(input: &InputData) -> Option<f64> {
+        let usage = transcript::transcript_usage(input.transcript_path.as_ref())?;
+        let model_config = ModelConfig::load();
 
-        // Primary display: total cost
-        let primary = if let Some(cost) = cost_data.total_cost_usd {
-            if cost == 0.0 || cost < 0.01 {
-                "$0".to_string()
+        let mut total = None;
+        for (model, totals) in &usage.by_model {
+            let model_id = if model == "unknown" {
+                &input.model.id
             } else {
-                format!("${:.2}", cost)
+                model
+            };
+            let Some(pricing) = model_config.get_pricing(model_id) else {
+                continue;
+            };
+            let long_context = usage
+                .long_context_by_model
+                .get(model)
+                .copied()
+                .unwrap_or_default();
+            for (totals, pricing) in [
+                (totals.minus(&long_context), pricing),
+                (long_context, pricing.long_context_rates()),
+            ] {
+                *total.get_or_insert(0.0) += pricing.cost_usd(
+                    totals.input_tokens,
+                    totals.output_tokens,
+                    totals.cache_creation_input_tokens,
+                    totals.cache_read_input_tokens,
+                );
             }
+        }
+        total
+    }
+
+    fn disagrees(upstream: f64, estimated: f64) -> bool {
+        let diff = (upstream - estimated).abs();
+        diff >= 0.01 && diff > upstream.max(estimated) * DISAGREEMENT_RATIO
+    }
+}
+
+impl Segment for CostSegment {
+    fn collect(&self, input: &InputData) -> Option<SegmentData> {
+        let upstream = input.cost.as_ref().and_then(|c| c.total_cost_usd);
+        let estimated = if self.estimate {
+            Self::estimate_cost(input)
         } else {
-            return None;
+            None
+        };
+
+        // Primary display: upstream cost, or the estimate marked with `~`
+        let (primary, cost, source) = match (upstream, estimated) {
+            (Some(cost), _) => (Self::format_cost(cost), cost, "upstream"),
+            (None, Some(cost)) => (format!("~{}", Self::format_cost(cost)), cost, "estimate"),
+            (None, None) => return None,
         };
 
-        // Secondary display: empty for cost segment
-        let secondary = String::new();
+        // Secondary display: the estimate, when it disagrees with upstream
+        let secondary = match (upstream, estimated) {
+            (Some(upstream), Some(estimated)) if Self::disagrees(upstream, estimated) => {
+                format!("(~{})", Self::format_cost(estimated))
+            }
+            _ => String::new(),
+        };
 
         let mut metadata = HashMap::new();
-        if let Some(cost) = cost_data.total_cost_usd {
-            metadata.insert("cost".to_string(), cost.to_string());
+        metadata.insert("cost".to_string(), cost.to_string());
+        metadata.insert("cost_source".to_string(), source.to_string());
+        if let Some(upstream) = upstream {
+            metadata.insert("upstream_cost".to_string(), upstream.to_string());
+        }
+        if let Some(estimated) = estimated {
+            metadata.insert("estimated_cost".to_string(), estimated.to_string());
+            metadata.insert(
+                "estimated_cost_display".to_string(),
+                Self::format_cost(estimated),
+            );
         }
 
         Some(SegmentData {
@@ -0,0 +1,198 @@
+use super::{Segment, SegmentData};
+use crate::config::{InputData, SegmentId};
+use crate::utils::{dry_run, paths, process};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CustomCommandCache {
+    output: String,
+    cached_at: String,
+}
+
+/// Runs a user-defined shell command and displays its first line of output.
+///
+/// The command receives the Claude Code input as JSON on stdin and as
+/// `CCLINE_*` environment variables. Output is cached per command and
+/// working directory, and a stale cache entry is shown if the command fails.
+pub struct CustomSegment {
+    name: String,
+    command: String,
+    timeout_ms: u64,
+    cache_duration: u64,
+}
+
+impl CustomSegment {
+    pub fn new(name: &str, command: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            command: command.to_string(),
+            timeout_ms: 500,
+            cache_duration: 0,
+        }
+    }
+
+    pub fn with_timeout_ms(mut self, timeout_ms: u64) -> Self {
+        self.timeout_ms = timeout_ms;
+        self
+    }
+
+    pub fn with_cache_duration(mut self, cache_duration: u64) -> Self {
+        self.cache_duration = cache_duration;
+        self
+    }
+
+    fn get_cache_path(&self, working_dir: &str) -> Option<PathBuf> {
+        // A stable hash, so cache files outlive toolchain updates
+        let mut hasher = Sha256::new();
+        hasher.update(self.command.as_bytes());
+        hasher.update([0]);
+        hasher.update(working_dir.as_bytes());
+        let hash = format!("{:x}", hasher.finalize());
+
+        let file_name: String = self
+            .name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+
+        Some(paths::cache_dir().join(".custom_cache").join(format!(
+            "{}-{}.json",
+            file_name,
+            &hash[..16]
+        )))
+    }
+
+    fn load_cache(&self, working_dir: &str) -> Option<CustomCommandCache> {
+        let cache_path = self.get_cache_path(working_dir)?;
+        let content = std::fs::read_to_string(cache_path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn save_cache(&self, working_dir: &str, cache: &CustomCommandCache) {
+        if let Some(cache_path) = self.get_cache_path(working_dir) {
+            if let Some(parent) = cache_path.parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            if let Ok(json) = serde_json::to_string_pretty(cache) {
+                let _ = std::fs::write(&cache_path, json);
+            }
+        }
+    }
+
+    fn is_cache_valid(&self, cache: &CustomCommandCache) -> bool {
+        if self.cache_duration == 0 {
+            return false;
+        }
+
+        if let Ok(cached_at) = DateTime::parse_from_rfc3339(&cache.cached_at) {
+            let elapsed = Utc::now().signed_duration_since(cached_at.with_timezone(&Utc));
+            elapsed.num_seconds() < self.cache_duration as i64
+        } else {
+            false
+        }
+    }
+
+    fn shell_command(&self) -> Command {
+        if cfg!(windows) {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", &self.command]);
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", &self.command]);
+            cmd
+        }
+    }
+
+    /// Run the command with the input exposed on stdin and in the environment.
+    /// Returns `None` on spawn failure, non-zero exit or timeout.
+    fn run_command(&self, input: &InputData) -> Option<String> {
+        let mut cmd = self.shell_command();
+
+        if std::path::Path::new(&input.workspace.current_dir).is_dir() {
+            cmd.current_dir(&input.workspace.current_dir);
+        }
+
+        cmd.env("CCLINE_SEGMENT_NAME", &self.name)
+            .env("CCLINE_MODEL_ID", &input.model.id)
+            .env("CCLINE_MODEL_NAME", &input.model.display_name)
+            .env("CCLINE_CURRENT_DIR", &input.workspace.current_dir)
+            .env("CCLINE_TRANSCRIPT_PATH", &input.transcript_path);
+
+        if let Some(cost) = input.cost.as_ref().and_then(|c| c.total_cost_usd) {
+            cmd.env("CCLINE_COST_USD", cost.to_string());
+        }
+        if let Some(style) = &input.output_style {
+            cmd.env("CCLINE_OUTPUT_STYLE", &style.name);
+        }
+
+        let input_json = serde_json::to_vec(input).ok()?;
+        let output = process::output_with_timeout(
+            &mut cmd,
+            Some(&input_json),
+            Duration::from_millis(self.timeout_ms),
+        )?;
+
+        if output.status.success() {
+            Some(String::from_utf8_lossy(&output.stdout).into_owned())
+        } else {
+            None
+        }
+    }
+}
+
+impl Segment for CustomSegment {
+    fn collect(&self, input: &InputData) -> Option<SegmentData> {
+        let working_dir = &input.workspace.current_dir;
+        let cached_data = self.load_cache(working_dir);
+
+        let (output, from_cache) = match cached_data {
+            Some(cache) if self.is_cache_valid(&cache) => (cache.output, true),
+            // A dry run shows the last output, however old
+            cached_data if dry_run::is_enabled() => (cached_data?.output, true),
+            cached_data => match self.run_command(input) {
+                Some(output) => {
+                    if self.cache_duration > 0 {
+                        self.save_cache(
+                            working_dir,
+                            &CustomCommandCache {
+                                output: output.clone(),
+                                cached_at: Utc::now().to_rfc3339(),
+                            },
+                        );
+                    }
+                    (output, false)
+                }
+                // Fall back to stale cache rather than flickering the segment away
+                None => (cached_data?.output, true),
+            },
+        };
+
+        let primary = output
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())?
+            .to_string();
+
+        let mut metadata = HashMap::new();
+        metadata.insert("name".to_string(), self.name.clone());
+        metadata.insert("output".to_string(), output.trim().to_string());
+        metadata.insert("cached".to_string(), from_cache.to_string());
+
+        Some(SegmentData {
+            primary,
+            secondary: String::new(),
+            metadata,
+        })
+    }
+
+    fn id(&self) -> SegmentId {
+        SegmentId::Custom
+    }
+}
@@ -1,15 +1,24 @@
 use super::{Segment, SegmentData};
 use crate::config::{InputData, SegmentId};
+use crate::utils::process;
 use std::collections::HashMap;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
 
 #[derive(Debug)]
 pub struct GitInfo {
     pub branch: String,
     pub status: GitStatus,
+    pub counts: FileCounts,
     pub ahead: u32,
     pub behind: u32,
     pub sha: Option<String>,
+    pub stash: u32,
+    pub operation: Option<GitOperation>,
+    /// HEAD is not on a branch; `branch` holds a tag or short SHA instead
+    pub detached: bool,
+    pub tag: Option<String>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -17,10 +26,140 @@ pub enum GitStatus {
     Clean,
     Dirty,
     Conflicts,
+    /// Status could not be determined within the time budget
+    Unknown,
+}
+
+/// Number of files in each working tree state
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FileCounts {
+    pub staged: u32,
+    pub modified: u32,
+    pub untracked: u32,
+    pub conflicted: u32,
+}
+
+impl FileCounts {
+    pub fn status(&self) -> GitStatus {
+        if self.conflicted > 0 {
+            GitStatus::Conflicts
+        } else if self.staged + self.modified + self.untracked > 0 {
+            GitStatus::Dirty
+        } else {
+            GitStatus::Clean
+        }
+    }
+}
+
+/// A multi-step operation that is paused waiting for the user
+#[derive(Debug, Clone, PartialEq)]
+pub enum GitOperation {
+    Rebase {
+        step: Option<u32>,
+        total: Option<u32>,
+    },
+    Merge,
+    CherryPick,
+    Revert,
+    Bisect,
+}
+
+impl GitOperation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GitOperation::Rebase { .. } => "rebase",
+            GitOperation::Merge => "merge",
+            GitOperation::CherryPick => "cherry-pick",
+            GitOperation::Revert => "revert",
+            GitOperation::Bisect => "bisect",
+        }
+    }
+
+    /// Label in the style of git's own prompt, e.g. `REBASE 2/5`
+    pub fn display(&self) -> String {
+        match self {
+            GitOperation::Rebase {
+                step: Some(step),
+                total: Some(total),
+            } => format!("REBASE {}/{}", step, total),
+            GitOperation::Rebase { .. } => "REBASE".to_string(),
+            GitOperation::Merge => "MERGING".to_string(),
+            GitOperation::CherryPick => "CHERRY-PICKING".to_string(),
+            GitOperation::Revert => "REVERTING".to_string(),
+            GitOperation::Bisect => "BISECTING".to_string(),
+        }
+    }
+}
+
+/// Detect an in-progress operation from the marker files git leaves in the git dir
+pub fn read_operation(git_dir: &Path) -> Option<GitOperation> {
+    let read_number =
+        |path: PathBuf| -> Option<u32> { std::fs::read_to_string(path).ok()?.trim().parse().ok() };
+
+    let rebase_merge = git_dir.join("rebase-merge");
+    if rebase_merge.is_dir() {
+        return Some(GitOperation::Rebase {
+            step: read_number(rebase_merge.join("msgnum")),
+            total: read_number(rebase_merge.join("end")),
+        });
+    }
+
+    let rebase_apply = git_dir.join("rebase-apply");
+    if rebase_apply.is_dir() {
+        return Some(GitOperation::Rebase {
+            step: read_number(rebase_apply.join("next")),
+            total: read_number(rebase_apply.join("last")),
+        });
+    }
+
+    if git_dir.join("MERGE_HEAD").exists() {
+        Some(GitOperation::Merge)
+    } else if git_dir.join("CHERRY_PICK_HEAD").exists() {
+        Some(GitOperation::CherryPick)
+    } else if git_dir.join("REVERT_HEAD").exists() {
+        Some(GitOperation::Revert)
+    } else if git_dir.join("BISECT_LOG").exists() {
+        Some(GitOperation::Bisect)
+    } else {
+        None
+    }
+}
+
+/// Count stash entries from the stash reflog, which lives in the common git dir
+pub fn count_stashes(common_dir: &Path) -> u32 {
+    std::fs::read_to_string(common_dir.join("logs").join("refs").join("stash"))
+        .map(|log| log.lines().filter(|line| !line.is_empty()).count() as u32)
+        .unwrap_or(0)
+}
+
+/// Which optional parts of the status are rendered. Metadata always has all of them.
+#[derive(Debug, Clone, Copy)]
+pub struct GitElements {
+    pub staged: bool,
+    pub modified: bool,
+    pub untracked: bool,
+    pub conflicted: bool,
+    pub stash: bool,
+    pub operation: bool,
+}
+
+impl Default for GitElements {
+    fn default() -> Self {
+        Self {
+            staged: false,
+            modified: false,
+            untracked: false,
+            conflicted: false,
+            stash: false,
+            operation: true,
+        }
+    }
 }
 
 pub struct GitSegment {
     show_sha: bool,
+    status_timeout: Duration,
+    elements: GitElements,
 }
 
 impl Default for GitSegment {
@@ -31,7 +170,11 @@ impl Default for GitSegment {
 
 impl GitSegment {
     pub fn new() -> Self {
-        Self { show_sha: false }
+        Self {
+            show_sha: false,
+            status_timeout: Duration::from_millis(500),
+            elements: GitElements::default(),
+        }
     }
 
     pub fn with_sha(mut self, show_sha: bool) -> Self {
@@ -39,135 +182,194 @@ impl GitSegment {
         self
     }
 
+    /// Time budget for all git calls; status still running when it runs out is
+    /// reported as unknown, and other missing details are left out
+    pub fn with_status_timeout(mut self, status_timeout: Duration) -> Self {
+        self.status_timeout = status_timeout;
+        self
+    }
+
+    pub fn with_elements(mut self, elements: GitElements) -> Self {
+        self.elements = elements;
+        self
+    }
+
     fn get_git_info(&self, working_dir: &str) -> Option<GitInfo> {
-        if !self.is_git_repository(working_dir) {
-            return None;
+        #[cfg(feature = "native-git")]
+        if let Some(info) =
+            super::git_native::get_git_info(working_dir, self.show_sha, self.status_timeout)
+        {
+            return Some(info);
         }
 
-        let branch = self
-            .get_branch(working_dir)
-            .unwrap_or_else(|| "detached".to_string());
-        let status = self.get_status(working_dir);
-        let (ahead, behind) = self.get_ahead_behind(working_dir);
+        self.get_git_info_cli(working_dir)
+    }
+
+    fn get_git_info_cli(&self, working_dir: &str) -> Option<GitInfo> {
+        let deadline = Instant::now() + self.status_timeout;
+        let (git_dir, common_dir) = Self::get_git_dirs(working_dir, deadline)?;
+
+        // Branch first: it's cheap, and without it there's nothing worth showing
+        let branch = Self::get_branch(working_dir, deadline)?;
+        let (status, counts) = Self::get_status(working_dir, deadline);
+        let (ahead, behind) = Self::get_ahead_behind(working_dir, deadline);
         let sha = if self.show_sha {
-            self.get_sha(working_dir)
+            Self::get_sha(working_dir, deadline)
         } else {
             None
         };
 
+        let (branch, detached, tag) = match branch {
+            Some(branch) => (branch, false, None),
+            None => {
+                let tag = Self::get_exact_tag(working_dir, deadline);
+                let name = tag
+                    .clone()
+                    .or_else(|| sha.clone())
+                    .or_else(|| Self::get_sha(working_dir, deadline))
+                    .unwrap_or_else(|| "detached".to_string());
+                (name, true, tag)
+            }
+        };
+
         Some(GitInfo {
             branch,
             status,
+            counts,
             ahead,
             behind,
             sha,
+            stash: count_stashes(&common_dir),
+            operation: read_operation(&git_dir),
+            detached,
+            tag,
         })
     }
 
-    fn is_git_repository(&self, working_dir: &str) -> bool {
-        Command::new("git")
-            .args(["--no-optional-locks", "rev-parse", "--git-dir"])
-            .current_dir(working_dir)
-            .output()
-            .map(|output| output.status.success())
-            .unwrap_or(false)
+    /// Run `git` in `working_dir`, killing it if it's still running at `deadline`
+    fn run_git(working_dir: &str, args: &[&str], deadline: Instant) -> Option<Output> {
+        process::output_with_timeout(
+            Command::new("git")
+                .arg("--no-optional-locks")
+                .args(args)
+                .current_dir(working_dir),
+            None,
+            deadline.saturating_duration_since(Instant::now()),
+        )
     }
 
-    fn get_branch(&self, working_dir: &str) -> Option<String> {
-        if let Ok(output) = Command::new("git")
-            .args(["--no-optional-locks", "branch", "--show-current"])
-            .current_dir(working_dir)
-            .output()
-        {
-            if output.status.success() {
-                let branch = String::from_utf8(output.stdout).ok()?.trim().to_string();
-                if !branch.is_empty() {
-                    return Some(branch);
-                }
-            }
+    /// Trimmed stdout of a successful `git` call, `None` if empty
+    fn git_line(working_dir: &str, args: &[&str], deadline: Instant) -> Option<String> {
+        let output = Self::run_git(working_dir, args, deadline)?;
+        if !output.status.success() {
+            return None;
         }
-
-        if let Ok(output) = Command::new("git")
-            .args(["--no-optional-locks", "symbolic-ref", "--short", "HEAD"])
-            .current_dir(working_dir)
-            .output()
-        {
-            if output.status.success() {
-                let branch = String::from_utf8(output.stdout).ok()?.trim().to_string();
-                if !branch.is_empty() {
-                    return Some(branch);
-                }
-            }
+        let text = String::from_utf8(output.stdout).ok()?.trim().to_string();
+        if text.is_empty() {
+            None
+        } else {
+            Some(text)
         }
+    }
 
-        None
+    /// Returns the git dir and common dir (they differ in linked worktrees),
+    /// or `None` outside a repository
+    fn get_git_dirs(working_dir: &str, deadline: Instant) -> Option<(PathBuf, PathBuf)> {
+        let text = Self::git_line(
+            working_dir,
+            &["rev-parse", "--absolute-git-dir", "--git-common-dir"],
+            deadline,
+        )?;
+        let mut lines = text.lines();
+        let git_dir = PathBuf::from(lines.next()?.trim());
+        // --git-common-dir may be relative to the working directory
+        let common_dir = lines
+            .next()
+            .map(|dir| Path::new(working_dir).join(dir.trim()))
+            .unwrap_or_else(|| git_dir.clone());
+
+        Some((git_dir, common_dir))
+    }
+
+    fn get_exact_tag(working_dir: &str, deadline: Instant) -> Option<String> {
+        Self::git_line(
+            working_dir,
+            &["describe", "--tags", "--exact-match", "HEAD"],
+            deadline,
+        )
     }
 
-    fn get_status(&self, working_dir: &str) -> GitStatus {
-        let output = Command::new("git")
-            .args(["--no-optional-locks", "status", "--porcelain"])
-            .current_dir(working_dir)
-            .output();
+    /// The current branch, `Some(None)` when HEAD is detached, or `None` if git
+    /// didn't answer within the budget
+    fn get_branch(working_dir: &str, deadline: Instant) -> Option<Option<String>> {
+        let output = Self::run_git(working_dir, &["branch", "--show-current"], deadline)?;
+        if output.status.success() {
+            let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            return Some((!branch.is_empty()).then_some(branch));
+        }
+        // Older git without --show-current
+        Some(Self::git_line(
+            working_dir,
+            &["symbolic-ref", "--short", "HEAD"],
+            deadline,
+        ))
+    }
 
-        match output {
-            Ok(output) if output.status.success() => {
+    fn get_status(working_dir: &str, deadline: Instant) -> (GitStatus, FileCounts) {
+        match Self::run_git(working_dir, &["status", "--porcelain=v2"], deadline) {
+            Some(output) if output.status.success() => {
                 let status_text = String::from_utf8(output.stdout).unwrap_or_default();
+                let counts = Self::parse_porcelain(&status_text);
+                (counts.status(), counts)
+            }
+            Some(_) => (GitStatus::Clean, FileCounts::default()),
+            None => (GitStatus::Unknown, FileCounts::default()),
+        }
+    }
 
-                if status_text.trim().is_empty() {
-                    return GitStatus::Clean;
-                }
+    /// Tally `git status --porcelain=v2` entries by their line type and XY code
+    fn parse_porcelain(status_text: &str) -> FileCounts {
+        let mut counts = FileCounts::default();
 
-                if status_text.contains("UU")
-                    || status_text.contains("AA")
-                    || status_text.contains("DD")
-                {
-                    GitStatus::Conflicts
-                } else {
-                    GitStatus::Dirty
+        for line in status_text.lines() {
+            let mut fields = line.split(' ');
+            match fields.next() {
+                Some("?") => counts.untracked += 1,
+                Some("u") => counts.conflicted += 1,
+                Some("1") | Some("2") => {
+                    let mut code = fields.next().unwrap_or_default().chars();
+                    let (Some(x), Some(y)) = (code.next(), code.next()) else {
+                        continue;
+                    };
+                    if x != '.' {
+                        counts.staged += 1;
+                    }
+                    if y != '.' {
+                        counts.modified += 1;
+                    }
                 }
+                // Branch headers (`#`) and ignored files (`!`)
+                _ => {}
             }
-            _ => GitStatus::Clean,
         }
+
+        counts
     }
 
-    fn get_ahead_behind(&self, working_dir: &str) -> (u32, u32) {
-        let ahead = self.get_commit_count(working_dir, "@{u}..HEAD");
-        let behind = self.get_commit_count(working_dir, "HEAD..@{u}");
+    fn get_ahead_behind(working_dir: &str, deadline: Instant) -> (u32, u32) {
+        let ahead = Self::get_commit_count(working_dir, "@{u}..HEAD", deadline);
+        let behind = Self::get_commit_count(working_dir, "HEAD..@{u}", deadline);
         (ahead, behind)
     }
 
-    fn get_commit_count(&self, working_dir: &str, range: &str) -> u32 {
-        let output = Command::new("git")
-            .args(["--no-optional-locks", "rev-list", "--count", range])
-            .current_dir(working_dir)
-            .output();
-
-        match output {
-            Ok(output) if output.status.success() => String::from_utf8(output.stdout)
-                .ok()
-                .and_then(|s| s.trim().parse().ok())
-                .unwrap_or(0),
-            _ => 0,
-        }
+    fn get_commit_count(working_dir: &str, range: &str, deadline: Instant) -> u32 {
+        Self::git_line(working_dir, &["rev-list", "--count", range], deadline)
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(0)
     }
 
-    fn get_sha(&self, working_dir: &str) -> Option<String> {
-        let output = Command::new("git")
-            .args(["--no-optional-locks", "rev-parse", "--short=7", "HEAD"])
-            .current_dir(working_dir)
-            .output()
-            .ok()?;
-
-        if output.status.success() {
-            let sha = String::from_utf8(output.stdout).ok()?.trim().to_string();
-            if sha.is_empty() {
-                None
-            } else {
-                Some(sha)
-            }
-        } else {
-            None
-        }
+    fn get_sha(working_dir: &str, deadline: Instant) -> Option<String> {
+        Self::git_line(working_dir, &["rev-parse", "--short=7", "HEAD"], deadline)
     }
 }
 
@@ -180,18 +382,59 @@ impl Segment for GitSegment {
         metadata.insert("status".to_string(), format!("{:?}", git_info.status));
         metadata.insert("ahead".to_string(), git_info.ahead.to_string());
         metadata.insert("behind".to_string(), git_info.behind.to_string());
+        metadata.insert("staged".to_string(), git_info.counts.staged.to_string());
+        metadata.insert("modified".to_string(), git_info.counts.modified.to_string());
+        metadata.insert(
+            "untracked".to_string(),
+            git_info.counts.untracked.to_string(),
+        );
+        metadata.insert(
+            "conflicted".to_string(),
+            git_info.counts.conflicted.to_string(),
+        );
+        metadata.insert("stash".to_string(), git_info.stash.to_string());
+        metadata.insert("detached".to_string(), git_info.detached.to_string());
 
         if let Some(ref sha) = git_info.sha {
             metadata.insert("sha".to_string(), sha.clone());
         }
+        if let Some(ref tag) = git_info.tag {
+            metadata.insert("tag".to_string(), tag.clone());
+        }
+        if let Some(ref operation) = git_info.operation {
+            metadata.insert("operation".to_string(), operation.name().to_string());
+            metadata.insert("operation_display".to_string(), operation.display());
+            if let GitOperation::Rebase { step, total } = operation {
+                if let Some(step) = step {
+                    metadata.insert("operation_step".to_string(), step.to_string());
+                }
+                if let Some(total) = total {
+                    metadata.insert("operation_total".to_string(), total.to_string());
+                }
+            }
+        }
 
-        let primary = git_info.branch;
-        let mut status_parts = Vec::new();
+        let status_icon = match git_info.status {
+            GitStatus::Clean => "✓",
+            GitStatus::Dirty => "●",
+            GitStatus::Conflicts => "⚠",
+            GitStatus::Unknown => "…",
+        };
+        metadata.insert("status_icon".to_string(), status_icon.to_string());
+
+        let elements = &self.elements;
+        let counts = &git_info.counts;
+        let mut status_parts = vec![status_icon.to_string()];
 
-        match git_info.status {
-            GitStatus::Clean => status_parts.push("✓".to_string()),
-            GitStatus::Dirty => status_parts.push("●".to_string()),
-            GitStatus::Conflicts => status_parts.push("⚠".to_string()),
+        for (show, symbol, count) in [
+            (elements.staged, "+", counts.staged),
+            (elements.modified, "~", counts.modified),
+            (elements.untracked, "?", counts.untracked),
+            (elements.conflicted, "!", counts.conflicted),
+        ] {
+            if show && count > 0 {
+                status_parts.push(format!("{}{}", symbol, count));
+            }
         }
 
         if git_info.ahead > 0 {
@@ -201,10 +444,24 @@ impl Segment for GitSegment {
             status_parts.push(format!("↓{}", git_info.behind));
         }
 
+        if elements.stash && git_info.stash > 0 {
+            status_parts.push(format!("≡{}", git_info.stash));
+        }
+        if elements.operation {
+            if let Some(ref operation) = git_info.operation {
+                status_parts.push(operation.display());
+            }
+        }
+
+        // A detached HEAD without a tag already shows the SHA as its name
         if let Some(ref sha) = git_info.sha {
-            status_parts.push(sha.clone());
+            if *sha != git_info.branch {
+                status_parts.push(sha.clone());
+            }
         }
 
+        let primary = git_info.branch;
+
         Some(SegmentData {
             primary,
             secondary: status_parts.join(" "),
@@ -216,3 +473,94 @@ impl Segment for GitSegment {
         SegmentId::Git
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_porcelain_counts_each_entry_type() {
+        let status = "\
+# branch.oid 0123456789abcdef0123456789abcdef01234567
+# branch.head main
+1 M. N... 100644 100644 100644 1111111 2222222 staged.rs
+1 .M N... 100644 100644 100644 1111111 1111111 modified.rs
+1 MM N... 100644 100644 100644 1111111 2222222 both.rs
+2 R. N... 100644 100644 100644 1111111 1111111 R100 new.rs\told.rs
+u UU N... 100644 100644 100644 100644 1111111 2222222 3333333 conflict.rs
+u AA N... 000000 100644 100644 100644 0000000 2222222 3333333 added.rs
+? untracked.rs
+? dir with spaces/file.rs
+! ignored.rs
+";
+        assert_eq!(
+            GitSegment::parse_porcelain(status),
+            FileCounts {
+                staged: 3,
+                modified: 2,
+                untracked: 2,
+                conflicted: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_porcelain_clean_tree_has_only_headers() {
+        let counts = GitSegment::parse_porcelain("# branch.oid abc\n# branch.head main\n");
+        assert_eq!(counts, FileCounts::default());
+        assert_eq!(counts.status(), GitStatus::Clean);
+    }
+
+    /// Fresh empty git dir for one test
+    fn git_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ccline-git-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn read_operation_detects_marker_files() {
+        for (marker, expected) in [
+            ("MERGE_HEAD", GitOperation::Merge),
+            ("CHERRY_PICK_HEAD", GitOperation::CherryPick),
+            ("REVERT_HEAD", GitOperation::Revert),
+            ("BISECT_LOG", GitOperation::Bisect),
+        ] {
+            let dir = git_dir(marker);
+            std::fs::write(dir.join(marker), "").unwrap();
+            assert_eq!(read_operation(&dir), Some(expected), "{}", marker);
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+
+        let dir = git_dir("none");
+        assert_eq!(read_operation(&dir), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn read_operation_reads_rebase_progress() {
+        let dir = git_dir("rebase-merge");
+        std::fs::create_dir(dir.join("rebase-merge")).unwrap();
+        std::fs::write(dir.join("rebase-merge").join("msgnum"), "2\n").unwrap();
+        std::fs::write(dir.join("rebase-merge").join("end"), "5\n").unwrap();
+        // A rebase stopped on a conflict also leaves MERGE_HEAD behind
+        std::fs::write(dir.join("MERGE_HEAD"), "").unwrap();
+        let operation = read_operation(&dir).unwrap();
+        assert_eq!(operation.display(), "REBASE 2/5");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let dir = git_dir("rebase-apply");
+        std::fs::create_dir(dir.join("rebase-apply")).unwrap();
+        std::fs::write(dir.join("rebase-apply").join("next"), "1").unwrap();
+        assert_eq!(
+            read_operation(&dir),
+            Some(GitOperation::Rebase {
+                step: Some(1),
+                total: None,
+            })
+        );
+        assert_eq!(read_operation(&dir).unwrap().display(), "REBASE");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,173 @@
+//! In-process git backend using libgit2, enabled with the `native-git` feature.
+//! Returns `None` whenever the repository can't be read so the caller can fall
+//! back to the `git` CLI.
+
+use super::git::{count_stashes, read_operation, FileCounts, GitInfo, GitStatus};
+use git2::{BranchType, DescribeOptions, ErrorCode, Oid, Repository, Status, StatusOptions};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+pub fn get_git_info(
+    working_dir: &str,
+    show_sha: bool,
+    status_timeout: Duration,
+) -> Option<GitInfo> {
+    let deadline = Instant::now() + status_timeout;
+    let repo = Repository::discover(working_dir).ok()?;
+    if repo.is_bare() {
+        return None;
+    }
+
+    // Start the index/worktree scan first so it overlaps with the cheap lookups below
+    let status_rx = spawn_status(repo.path().to_path_buf());
+
+    let head = match repo.head() {
+        Ok(head) => Some(head),
+        Err(e) if e.code() == ErrorCode::UnbornBranch => None,
+        Err(_) => return None,
+    };
+
+    let head_oid = head.as_ref().and_then(|h| h.target());
+    let short_sha = head_oid.map(|oid| oid.to_string().chars().take(7).collect::<String>());
+
+    let (branch, detached, tag) = match &head {
+        Some(head) if head.is_branch() => (
+            head.shorthand().unwrap_or("detached").to_string(),
+            false,
+            None,
+        ),
+        Some(_) => {
+            let tag = exact_tag(&repo);
+            let name = tag
+                .clone()
+                .or_else(|| short_sha.clone())
+                .unwrap_or_else(|| "detached".to_string());
+            (name, true, tag)
+        }
+        // Unborn branch: HEAD still names the branch that will be created
+        None => (
+            repo.find_reference("HEAD")
+                .ok()
+                .and_then(|r| {
+                    r.symbolic_target()
+                        .map(|t| t.trim_start_matches("refs/heads/").to_string())
+                })
+                .unwrap_or_else(|| "detached".to_string()),
+            false,
+            None,
+        ),
+    };
+
+    let (ahead, behind) = head
+        .as_ref()
+        .filter(|h| h.is_branch())
+        .and_then(|h| {
+            let local = h.target()?;
+            let local_branch = repo.find_branch(h.shorthand()?, BranchType::Local).ok()?;
+            let upstream = local_branch.upstream().ok()?.get().target()?;
+            spawn_ahead_behind(repo.path().to_path_buf(), local, upstream)
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .ok()
+                .flatten()
+        })
+        .map(|(a, b)| (a as u32, b as u32))
+        .unwrap_or((0, 0));
+
+    let sha = if show_sha { short_sha } else { None };
+
+    let (status, counts) = status_rx
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        .unwrap_or((GitStatus::Unknown, FileCounts::default()));
+
+    Some(GitInfo {
+        branch,
+        status,
+        counts,
+        ahead,
+        behind,
+        sha,
+        stash: count_stashes(repo.commondir()),
+        operation: read_operation(repo.path()),
+        detached,
+        tag,
+    })
+}
+
+/// A tag pointing exactly at HEAD, like `git describe --tags --exact-match`
+fn exact_tag(repo: &Repository) -> Option<String> {
+    let mut opts = DescribeOptions::new();
+    opts.describe_tags().max_candidates_tags(0);
+    repo.describe(&opts).ok()?.format(None).ok()
+}
+
+/// Compute working tree status on a background thread. If it misses the time
+/// budget the thread is abandoned; it holds no locks and dies with the process.
+fn spawn_status(repo_path: PathBuf) -> mpsc::Receiver<(GitStatus, FileCounts)> {
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let result = Repository::open(&repo_path)
+            .ok()
+            .and_then(|repo| {
+                let mut opts = StatusOptions::new();
+                opts.include_untracked(true)
+                    .recurse_untracked_dirs(false)
+                    .include_ignored(false);
+
+                let statuses = repo.statuses(Some(&mut opts)).ok()?;
+                let counts = count_statuses(statuses.iter().map(|e| e.status()));
+                Some((counts.status(), counts))
+            })
+            .unwrap_or((GitStatus::Unknown, FileCounts::default()));
+        let _ = tx.send(result);
+    });
+
+    rx
+}
+
+/// Count commits between HEAD and its upstream on a background thread, since a
+/// long-diverged branch can take a while to walk
+fn spawn_ahead_behind(
+    repo_path: PathBuf,
+    local: Oid,
+    upstream: Oid,
+) -> mpsc::Receiver<Option<(usize, usize)>> {
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let result = Repository::open(&repo_path)
+            .ok()
+            .and_then(|repo| repo.graph_ahead_behind(local, upstream).ok());
+        let _ = tx.send(result);
+    });
+
+    rx
+}
+
+fn count_statuses(statuses: impl Iterator<Item = Status>) -> FileCounts {
+    let staged = Status::INDEX_NEW
+        | Status::INDEX_MODIFIED
+        | Status::INDEX_DELETED
+        | Status::INDEX_RENAMED
+        | Status::INDEX_TYPECHANGE;
+    let modified =
+        Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE;
+
+    let mut counts = FileCounts::default();
+    for status in statuses {
+        if status.is_conflicted() {
+            counts.conflicted += 1;
+        } else if status.is_wt_new() {
+            counts.untracked += 1;
+        } else {
+            if status.intersects(staged) {
+                counts.staged += 1;
+            }
+            if status.intersects(modified) {
+                counts.modified += 1;
+            }
+        }
+    }
+    counts
+}
@@ -1,10 +1,16 @@
+pub mod budget;
 pub mod context_window;
 pub mod cost;
+pub mod custom;
 pub mod directory;
 pub mod git;
+#[cfg(feature = "native-git")]
+mod git_native;
 pub mod model;
 pub mod output_style;
 pub mod session;
+pub mod spend;
+pub mod tokens;
 pub mod update;
 pub mod usage;
 
@@ -25,12 +31,16 @@ pub struct SegmentData {
 }
 
 // Re-export all segment types
+pub use budget::BudgetSegment;
 pub use context_window::ContextWindowSegment;
 pub use cost::CostSegment;
+pub use custom::CustomSegment;
 pub use directory::DirectorySegment;
-pub use git::GitSegment;
+pub use git::{GitElements, GitSegment};
 pub use model::ModelSegment;
 pub use output_style::OutputStyleSegment;
 pub use session::SessionSegment;
+pub use spend::SpendSegment;
+pub use tokens::TokensSegment;
 pub use update::UpdateSegment;
 pub use usage::UsageSegment;
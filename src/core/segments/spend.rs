@@ -0,0 +1,46 @@
+use super::{Segment, SegmentData};
+use crate::config::{InputData, SegmentId};
+use crate::ledger::{format_usd, Ledger};
+use std::collections::HashMap;
+
+/// Spend across all sessions today and this week, from the cost ledger
+#[derive(Default)]
+pub struct SpendSegment;
+
+impl SpendSegment {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Segment for SpendSegment {
+    fn collect(&self, _input: &InputData) -> Option<SegmentData> {
+        let ledger = Ledger::load();
+        if ledger.sessions.is_empty() {
+            return None;
+        }
+
+        let today = ledger.summary_since(Ledger::today());
+        let week = ledger.summary_since(Ledger::week_start());
+        let month = ledger.summary_since(Ledger::month_start());
+
+        let mut metadata = HashMap::new();
+        metadata.insert("today".to_string(), today.total.to_string());
+        metadata.insert("week".to_string(), week.total.to_string());
+        metadata.insert("month".to_string(), month.total.to_string());
+        metadata.insert("today_display".to_string(), format_usd(today.total));
+        metadata.insert("week_display".to_string(), format_usd(week.total));
+        metadata.insert("month_display".to_string(), format_usd(month.total));
+        metadata.insert("sessions_today".to_string(), today.sessions.to_string());
+
+        Some(SegmentData {
+            primary: format!("{} today", format_usd(today.total)),
+            secondary: format!("· {} week", format_usd(week.total)),
+            metadata,
+        })
+    }
+
+    fn id(&self) -> SegmentId {
+        SegmentId::Spend
+    }
+}
@@ -0,0 +1,87 @@
+use super::{Segment, SegmentData};
+use crate::config::{InputData, SegmentId};
+use crate::utils::transcript;
+use std::collections::HashMap;
+
+/// Session token totals across all assistant messages, with the prompt cache hit ratio
+#[derive(Default)]
+pub struct TokensSegment;
+
+impl TokensSegment {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Compact token count, e.g. `840` -> `840`, `84000` -> `84k`, `1234567` -> `1.2M`
+    fn format_count(tokens: u64) -> String {
+        let (value, suffix) = if tokens >= 1_000_000 {
+            (tokens as f64 / 1_000_000.0, "M")
+        } else if tokens >= 1000 {
+            (tokens as f64 / 1000.0, "k")
+        } else {
+            return tokens.to_string();
+        };
+
+        let rounded = format!("{:.1}", value);
+        format!("{}{}", rounded.trim_end_matches(".0"), suffix)
+    }
+}
+
+impl Segment for TokensSegment {
+    fn collect(&self, input: &InputData) -> Option<SegmentData> {
+        let usage = transcript::transcript_usage(input.transcript_path.as_ref())?;
+        let totals = usage.totals();
+        if totals.messages == 0 {
+            return None;
+        }
+
+        let primary = format!(
+            "↑{} ↓{}",
+            Self::format_count(totals.total_input_tokens()),
+            Self::format_count(totals.output_tokens)
+        );
+        let cache_hit = totals.cache_hit_percent();
+        let secondary = cache_hit
+            .map(|percent| format!("⚡{:.0}% cached", percent))
+            .unwrap_or_default();
+
+        let mut metadata = HashMap::new();
+        metadata.insert("input".to_string(), totals.input_tokens.to_string());
+        metadata.insert("output".to_string(), totals.output_tokens.to_string());
+        metadata.insert(
+            "cache_creation".to_string(),
+            totals.cache_creation_input_tokens.to_string(),
+        );
+        metadata.insert(
+            "cache_read".to_string(),
+            totals.cache_read_input_tokens.to_string(),
+        );
+        metadata.insert(
+            "total_input".to_string(),
+            totals.total_input_tokens().to_string(),
+        );
+        metadata.insert(
+            "total_input_display".to_string(),
+            Self::format_count(totals.total_input_tokens()),
+        );
+        metadata.insert(
+            "output_display".to_string(),
+            Self::format_count(totals.output_tokens),
+        );
+        metadata.insert("messages".to_string(), totals.messages.to_string());
+        if let Some(percent) = cache_hit {
+            metadata.insert("cache_hit_percent".to_string(), percent.to_string());
+            metadata.insert("cache_hit_display".to_string(), format!("{:.0}%", percent));
+        }
+
+        Some(SegmentData {
+            primary,
+            secondary,
+            metadata,
+        })
+    }
+
+    fn id(&self) -> SegmentId {
+        SegmentId::Tokens
+    }
+}
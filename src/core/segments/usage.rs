@@ -1,19 +1,40 @@
 use super::{Segment, SegmentData};
 use crate::config::{InputData, SegmentId};
-use crate::utils::credentials;
-use chrono::{DateTime, Datelike, Duration, Local, Timelike, Utc};
+use crate::refresh::{self, RefreshTask};
+use crate::utils::credentials::{self, OAuthToken};
+use crate::utils::{claude_version, lock, paths};
+use chrono::{DateTime, Duration, Local, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Deserialize)]
 struct ApiUsageResponse {
-    five_hour: UsagePeriod,
-    seven_day: UsagePeriod,
+    #[serde(default)]
+    five_hour: Option<UsagePeriod>,
+    #[serde(default)]
+    seven_day: Option<UsagePeriod>,
+    // Other buckets, e.g. per-model weekly limits like `seven_day_opus`
+    #[serde(flatten)]
+    extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Deserialize)]
+impl ApiUsageResponse {
+    /// Extra buckets that carry a utilization; unused ones come back as `null`
+    fn buckets(&self) -> BTreeMap<String, UsagePeriod> {
+        self.extra
+            .iter()
+            .filter_map(|(name, value)| {
+                let period = serde_json::from_value(value.clone()).ok()?;
+                Some((name.clone(), period))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct UsagePeriod {
     utilization: f64,
+    #[serde(default)]
     resets_at: Option<String>,
 }
 
@@ -21,16 +42,108 @@ struct UsagePeriod {
 struct ApiUsageCache {
     five_hour_utilization: f64,
     seven_day_utilization: f64,
-    resets_at: Option<String>,
+    #[serde(default)]
+    five_hour_resets_at: Option<String>,
+    #[serde(default, alias = "resets_at")]
+    seven_day_resets_at: Option<String>,
     cached_at: String,
+    /// Extra buckets from the response, keyed by their API name
+    #[serde(default)]
+    buckets: BTreeMap<String, UsagePeriod>,
+    /// Recent five-hour utilization readings, oldest first
+    #[serde(default)]
+    five_hour_samples: Vec<UsageSample>,
+    /// Expiry of the token the cache was fetched with. Renders only look up
+    /// credentials again once it has passed or a fetch is due.
+    #[serde(default)]
+    token_expires_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UsageSample {
+    at: DateTime<Utc>,
+    utilization: f64,
+}
+
+/// Only samples this recent count towards the burn rate
+const BURN_RATE_WINDOW_MINUTES: i64 = 60;
+/// Samples must span at least this long before a rate is shown
+const BURN_RATE_MIN_SPAN_MINUTES: i64 = 5;
+const MAX_SAMPLES: usize = 30;
+
+/// Which usage window(s) the segment shows
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum UsageDisplay {
+    FiveHour,
+    SevenDay,
+    Both,
+    /// Whichever window is closer to its limit
+    Max,
+}
+
+impl UsageDisplay {
+    fn from_option(value: &str) -> Self {
+        match value {
+            "seven_day" | "7d" => Self::SevenDay,
+            "both" => Self::Both,
+            "max" => Self::Max,
+            _ => Self::FiveHour,
+        }
+    }
+}
+
+/// How reset times are rendered
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ResetFormat {
+    /// Countdown, e.g. `resets in 2h13m`
+    Relative,
+    /// Local time, e.g. `resets 15:40`
+    Absolute,
+}
+
+impl ResetFormat {
+    fn from_option(value: &str) -> Self {
+        match value {
+            "absolute" => Self::Absolute,
+            _ => Self::Relative,
+        }
+    }
+}
+
+struct UsageWindow {
+    /// Metadata key prefix
+    key: &'static str,
+    label: String,
+    utilization: f64,
+    resets_at: Option<DateTime<Utc>>,
+}
+
+impl UsageWindow {
+    fn percent(&self) -> u8 {
+        self.utilization.round() as u8
+    }
+}
+
+/// Five-hour consumption speed and when it would run out at that pace
+struct BurnRate {
+    percent_per_hour: f64,
+    limit_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Default)]
-pub struct UsageSegment;
+pub struct UsageSegment {
+    options: HashMap<String, serde_json::Value>,
+}
 
 impl UsageSegment {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Segment options from the effective config, including project files and overrides
+    pub fn with_options(mut self, options: HashMap<String, serde_json::Value>) -> Self {
+        self.options = options;
+        self
     }
 
     fn get_circle_icon(utilization: f64) -> String {
@@ -47,31 +160,157 @@ impl UsageSegment {
         }
     }
 
-    fn format_reset_time(reset_time_str: Option<&str>) -> String {
-        if let Some(time_str) = reset_time_str {
-            if let Ok(dt) = DateTime::parse_from_rfc3339(time_str) {
-                let mut local_dt = dt.with_timezone(&Local);
-                if local_dt.minute() > 45 {
-                    local_dt += Duration::hours(1);
+    fn format_reset_time(reset_at: Option<DateTime<Utc>>, format: ResetFormat) -> String {
+        let Some(reset_at) = reset_at else {
+            return "?".to_string();
+        };
+
+        match format {
+            ResetFormat::Relative => {
+                let minutes = reset_at
+                    .signed_duration_since(Utc::now())
+                    .num_minutes()
+                    .max(0);
+                let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+                let countdown = if days > 0 {
+                    format!("{}d{}h", days, hours)
+                } else if hours > 0 {
+                    format!("{}h{:02}m", hours, minutes)
+                } else {
+                    format!("{}m", minutes)
+                };
+                format!("resets in {}", countdown)
+            }
+            ResetFormat::Absolute => {
+                let local_dt = reset_at.with_timezone(&Local);
+                if local_dt.date_naive() == Local::now().date_naive() {
+                    format!("resets {}", local_dt.format("%H:%M"))
+                } else {
+                    format!("resets {}", local_dt.format("%a %H:%M"))
                 }
-                return format!(
-                    "{}-{}-{}",
-                    local_dt.month(),
-                    local_dt.day(),
-                    local_dt.hour()
-                );
             }
         }
-        "?".to_string()
+    }
+
+    /// Add a fresh reading to the history, starting over when the five-hour
+    /// window has rolled over since the last one
+    fn record_sample(
+        mut samples: Vec<UsageSample>,
+        previous_resets_at: Option<&str>,
+        resets_at: Option<&str>,
+        utilization: f64,
+    ) -> Vec<UsageSample> {
+        let now = Utc::now();
+        // The reported reset time jitters slightly between calls; only a real
+        // jump means a new window
+        let reset_moved = match (
+            previous_resets_at.and_then(Self::parse_time),
+            resets_at.and_then(Self::parse_time),
+        ) {
+            (Some(previous), Some(current)) => {
+                current.signed_duration_since(previous).num_minutes().abs() >= 10
+            }
+            (previous, current) => previous.is_some() != current.is_some(),
+        };
+        let window_reset = reset_moved
+            || samples
+                .last()
+                .is_some_and(|last| utilization < last.utilization);
+        if window_reset {
+            samples.clear();
+        }
+
+        samples.retain(|sample| {
+            now.signed_duration_since(sample.at).num_minutes() < BURN_RATE_WINDOW_MINUTES
+        });
+        samples.push(UsageSample {
+            at: now,
+            utilization,
+        });
+        if samples.len() > MAX_SAMPLES {
+            samples.drain(..samples.len() - MAX_SAMPLES);
+        }
+        samples
+    }
+
+    /// The extra bucket for the active model, e.g. `seven_day_opus` for
+    /// `claude-opus-4-6`. The most specific name wins if several match.
+    fn model_bucket<'a>(
+        buckets: &'a BTreeMap<String, UsagePeriod>,
+        model_id: &str,
+    ) -> Option<(&'a String, &'a UsagePeriod)> {
+        let model_id = model_id.to_lowercase();
+        buckets
+            .iter()
+            .filter(|(name, _)| {
+                let (_, model) = Self::split_bucket_name(name);
+                !model.is_empty() && model_id.contains(model)
+            })
+            .max_by_key(|(name, _)| Self::split_bucket_name(name).1.len())
+    }
+
+    /// Split a bucket name like `seven_day_opus` into its window and model parts
+    fn split_bucket_name(name: &str) -> (&str, &str) {
+        for window in ["five_hour", "seven_day"] {
+            if let Some(model) = name
+                .strip_prefix(window)
+                .and_then(|rest| rest.strip_prefix('_'))
+            {
+                return (window, model);
+            }
+        }
+        ("", name)
+    }
+
+    /// Short label for a bucket, e.g. `7d opus`
+    fn bucket_label(name: &str) -> String {
+        let (window, model) = Self::split_bucket_name(name);
+        let model = model.replace('_', " ");
+        match window {
+            "five_hour" => format!("5h {}", model),
+            "seven_day" => format!("7d {}", model),
+            _ => model,
+        }
+    }
+
+    fn parse_time(time_str: &str) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(time_str)
+            .ok()
+            .map(|dt| dt.with_timezone(&Utc))
+    }
+
+    /// Percent per hour over the sampled span, projected forward to 100%.
+    /// The projection is dropped if the window resets before then.
+    fn burn_rate(samples: &[UsageSample], resets_at: Option<&str>) -> Option<BurnRate> {
+        let first = samples.first()?;
+        let last = samples.last()?;
+        let span = last.at.signed_duration_since(first.at);
+        if span.num_minutes() < BURN_RATE_MIN_SPAN_MINUTES {
+            return None;
+        }
+
+        let hours = span.num_seconds() as f64 / 3600.0;
+        let percent_per_hour = (last.utilization - first.utilization) / hours;
+
+        let limit_at = if percent_per_hour > 0.0 && last.utilization < 100.0 {
+            let hours_left = (100.0 - last.utilization) / percent_per_hour;
+            let limit_at = last.at + Duration::seconds((hours_left * 3600.0) as i64);
+            match resets_at.and_then(Self::parse_time) {
+                Some(resets_at) if limit_at >= resets_at => None,
+                _ => Some(limit_at),
+            }
+        } else {
+            None
+        };
+
+        Some(BurnRate {
+            percent_per_hour,
+            limit_at,
+        })
     }
 
     fn get_cache_path() -> Option<std::path::PathBuf> {
-        let home = dirs::home_dir()?;
-        Some(
-            home.join(".claude")
-                .join("ccline")
-                .join(".api_usage_cache.json"),
-        )
+        Some(paths::cache_dir().join(".api_usage_cache.json"))
     }
 
     fn load_cache(&self) -> Option<ApiUsageCache> {
@@ -86,15 +325,105 @@ impl UsageSegment {
 
     fn save_cache(&self, cache: &ApiUsageCache) {
         if let Some(cache_path) = Self::get_cache_path() {
-            if let Some(parent) = cache_path.parent() {
-                let _ = std::fs::create_dir_all(parent);
-            }
             if let Ok(json) = serde_json::to_string_pretty(cache) {
-                let _ = std::fs::write(&cache_path, json);
+                let _ = lock::write_atomic(&cache_path, json.as_bytes());
             }
         }
     }
 
+    /// Shown instead of usage while the only token available has expired
+    fn auth_expired(token: &OAuthToken) -> SegmentData {
+        let mut metadata = HashMap::new();
+        metadata.insert("auth_expired".to_string(), "true".to_string());
+        metadata.insert("auth_source".to_string(), token.source.to_string());
+        if let Some(expires_at) = token.expires_at {
+            metadata.insert("auth_expires_at".to_string(), expires_at.to_rfc3339());
+        }
+
+        SegmentData {
+            primary: "auth expired".to_string(),
+            secondary: String::new(),
+            metadata,
+        }
+    }
+
+    /// Fetch usage from the API and update the cache; run by the background refresher
+    pub fn refresh(&self) {
+        let credential_helper = self
+            .options
+            .get("credential_helper")
+            .and_then(|v| v.as_str());
+        let Some(token) = credentials::load_token(credential_helper) else {
+            return;
+        };
+        if token.is_expired() {
+            return;
+        }
+
+        let api_base_url = self
+            .options
+            .get("api_base_url")
+            .and_then(|v| v.as_str())
+            .unwrap_or("https://api.anthropic.com");
+
+        let cache_duration = self
+            .options
+            .get("cache_duration")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(300);
+
+        let timeout = self
+            .options
+            .get("timeout")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(2);
+
+        let cached_data = self.load_cache();
+        // Another refresher may have just finished
+        if cached_data
+            .as_ref()
+            .is_some_and(|cache| self.is_cache_valid(cache, cache_duration))
+        {
+            return;
+        }
+
+        let Some(response) = self.fetch_api_usage(api_base_url, &token.access_token, timeout)
+        else {
+            return;
+        };
+
+        let (previous_resets_at, samples) = match cached_data {
+            Some(cache) => (cache.five_hour_resets_at, cache.five_hour_samples),
+            None => (None, Vec::new()),
+        };
+        let buckets = response.buckets();
+        let five_hour = response.five_hour.unwrap_or(UsagePeriod {
+            utilization: 0.0,
+            resets_at: None,
+        });
+        let seven_day = response.seven_day.unwrap_or(UsagePeriod {
+            utilization: 0.0,
+            resets_at: None,
+        });
+        let five_hour_samples = Self::record_sample(
+            samples,
+            previous_resets_at.as_deref(),
+            five_hour.resets_at.as_deref(),
+            five_hour.utilization,
+        );
+
+        self.save_cache(&ApiUsageCache {
+            five_hour_utilization: five_hour.utilization,
+            seven_day_utilization: seven_day.utilization,
+            five_hour_resets_at: five_hour.resets_at,
+            seven_day_resets_at: seven_day.resets_at,
+            cached_at: Utc::now().to_rfc3339(),
+            buckets,
+            five_hour_samples,
+            token_expires_at: token.expires_at.map(|expires_at| expires_at.to_rfc3339()),
+        });
+    }
+
     fn is_cache_valid(&self, cache: &ApiUsageCache, cache_duration: u64) -> bool {
         if let Ok(cached_at) = DateTime::parse_from_rfc3339(&cache.cached_at) {
             let now = Utc::now();
@@ -106,30 +435,14 @@ impl UsageSegment {
     }
 
     fn get_claude_code_version() -> String {
-        use std::process::Command;
-
-        let output = Command::new("npm")
-            .args(["view", "@anthropic-ai/claude-code", "version"])
-            .output();
-
-        match output {
-            Ok(output) if output.status.success() => {
-                let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                if !version.is_empty() {
-                    return format!("claude-code/{}", version);
-                }
-            }
-            _ => {}
+        match claude_version::installed_version() {
+            Some(version) => format!("claude-code/{}", version),
+            None => "claude-code".to_string(),
         }
-
-        "claude-code".to_string()
     }
 
     fn get_proxy_from_settings() -> Option<String> {
-        let home = std::env::var("HOME")
-            .or_else(|_| std::env::var("USERPROFILE"))
-            .ok()?;
-        let settings_path = format!("{}/.claude/settings.json", home);
+        let settings_path = paths::claude_settings_file();
 
         let content = std::fs::read_to_string(&settings_path).ok()?;
         let settings: serde_json::Value = serde_json::from_str(&content).ok()?;
@@ -181,85 +494,226 @@ impl UsageSegment {
 }
 
 impl Segment for UsageSegment {
-    fn collect(&self, _input: &InputData) -> Option<SegmentData> {
-        let token = credentials::get_oauth_token()?;
-
-        // Load config from file to get segment options
-        let config = crate::config::Config::load().ok()?;
-        let segment_config = config.segments.iter().find(|s| s.id == SegmentId::Usage);
-
-        let api_base_url = segment_config
-            .and_then(|sc| sc.options.get("api_base_url"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("https://api.anthropic.com");
-
-        let cache_duration = segment_config
-            .and_then(|sc| sc.options.get("cache_duration"))
+    fn collect(&self, input: &InputData) -> Option<SegmentData> {
+        let cache_duration = self
+            .options
+            .get("cache_duration")
             .and_then(|v| v.as_u64())
             .unwrap_or(300);
 
-        let timeout = segment_config
-            .and_then(|sc| sc.options.get("timeout"))
-            .and_then(|v| v.as_u64())
-            .unwrap_or(2);
+        let display = self
+            .options
+            .get("display")
+            .and_then(|v| v.as_str())
+            .map(UsageDisplay::from_option)
+            .unwrap_or(UsageDisplay::FiveHour);
 
-        let cached_data = self.load_cache();
-        let use_cached = cached_data
+        let reset_format = self
+            .options
+            .get("reset_format")
+            .and_then(|v| v.as_str())
+            .map(ResetFormat::from_option)
+            .unwrap_or(ResetFormat::Relative);
+
+        let show_burn_rate = self
+            .options
+            .get("show_burn_rate")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        let show_model_bucket = self
+            .options
+            .get("show_model_bucket")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        // Render from cache right away; a stale or missing cache is refreshed
+        // in the background and picked up on a later render
+        let cache = self.load_cache();
+        let fetch_due = !cache
             .as_ref()
-            .map(|cache| self.is_cache_valid(cache, cache_duration))
-            .unwrap_or(false);
-
-        let (five_hour_util, seven_day_util, resets_at) = if use_cached {
-            let cache = cached_data.unwrap();
-            (
-                cache.five_hour_utilization,
-                cache.seven_day_utilization,
-                cache.resets_at,
-            )
-        } else {
-            match self.fetch_api_usage(api_base_url, &token, timeout) {
-                Some(response) => {
-                    let cache = ApiUsageCache {
-                        five_hour_utilization: response.five_hour.utilization,
-                        seven_day_utilization: response.seven_day.utilization,
-                        resets_at: response.seven_day.resets_at.clone(),
-                        cached_at: Utc::now().to_rfc3339(),
-                    };
-                    self.save_cache(&cache);
-                    (
-                        response.five_hour.utilization,
-                        response.seven_day.utilization,
-                        response.seven_day.resets_at,
-                    )
-                }
-                None => {
-                    if let Some(cache) = cached_data {
-                        (
-                            cache.five_hour_utilization,
-                            cache.seven_day_utilization,
-                            cache.resets_at,
-                        )
-                    } else {
-                        return None;
-                    }
-                }
+            .is_some_and(|cache| self.is_cache_valid(cache, cache_duration));
+        let token_expired = cache
+            .as_ref()
+            .and_then(|cache| cache.token_expires_at.as_deref())
+            .and_then(Self::parse_time)
+            .is_some_and(|expires_at| expires_at <= Utc::now());
+        // Looking up credentials can mean running a helper or keychain tool,
+        // so it's only done when there is something to check
+        if fetch_due || token_expired {
+            let credential_helper = self
+                .options
+                .get("credential_helper")
+                .and_then(|v| v.as_str());
+            let token = credentials::load_token(credential_helper)?;
+            if token.is_expired() {
+                return Some(Self::auth_expired(&token));
+            }
+            if fetch_due {
+                refresh::spawn_with(RefreshTask::Usage, &self.options);
             }
+        }
+        let cache = cache?;
+
+        let five_hour = UsageWindow {
+            key: "five_hour",
+            label: "5h".to_string(),
+            utilization: cache.five_hour_utilization,
+            resets_at: cache
+                .five_hour_resets_at
+                .as_deref()
+                .and_then(Self::parse_time),
+        };
+        let seven_day = UsageWindow {
+            key: "seven_day",
+            label: "7d".to_string(),
+            utilization: cache.seven_day_utilization,
+            resets_at: cache
+                .seven_day_resets_at
+                .as_deref()
+                .and_then(Self::parse_time),
         };
+        let model_bucket = if show_model_bucket {
+            Self::model_bucket(&cache.buckets, &input.model.id)
+        } else {
+            None
+        };
+        let model = model_bucket.map(|(name, period)| UsageWindow {
+            key: "model",
+            label: Self::bucket_label(name),
+            utilization: period.utilization,
+            resets_at: period.resets_at.as_deref().and_then(Self::parse_time),
+        });
+
+        let mut shown: Vec<&UsageWindow> = match display {
+            UsageDisplay::FiveHour => vec![&five_hour],
+            UsageDisplay::SevenDay => vec![&seven_day],
+            UsageDisplay::Both => vec![&five_hour, &seven_day],
+            UsageDisplay::Max => vec![&five_hour, &seven_day],
+        };
+        shown.extend(model.as_ref());
+        if display == UsageDisplay::Max {
+            let highest =
+                shown
+                    .iter()
+                    .copied()
+                    .reduce(|a, b| if b.utilization > a.utilization { b } else { a });
+            shown = highest.into_iter().collect();
+        }
+        // Label the windows unless a single configured window is shown on its own
+        let labelled = shown.len() > 1 || display == UsageDisplay::Max;
 
-        let dynamic_icon = Self::get_circle_icon(seven_day_util / 100.0);
-        let five_hour_percent = five_hour_util.round() as u8;
-        let primary = format!("{}%", five_hour_percent);
-        let secondary = format!("· {}", Self::format_reset_time(resets_at.as_deref()));
+        let mut windows = vec![&five_hour, &seven_day];
+        windows.extend(model.as_ref());
 
         let mut metadata = HashMap::new();
-        metadata.insert("dynamic_icon".to_string(), dynamic_icon);
+        for (name, period) in &cache.buckets {
+            metadata.insert(
+                format!("{}_utilization", name),
+                period.utilization.to_string(),
+            );
+        }
+        if let Some((name, _)) = model_bucket {
+            metadata.insert("model_bucket".to_string(), name.clone());
+        }
+        for window in &windows {
+            metadata.insert(
+                format!("{}_utilization", window.key),
+                window.utilization.to_string(),
+            );
+            metadata.insert(
+                format!("{}_percent", window.key),
+                window.percent().to_string(),
+            );
+            metadata.insert(
+                format!("{}_reset_display", window.key),
+                Self::format_reset_time(window.resets_at, reset_format),
+            );
+            if let Some(resets_at) = window.resets_at {
+                metadata.insert(format!("{}_resets_at", window.key), resets_at.to_rfc3339());
+                metadata.insert(
+                    format!("{}_reset_minutes", window.key),
+                    resets_at
+                        .signed_duration_since(Utc::now())
+                        .num_minutes()
+                        .max(0)
+                        .to_string(),
+                );
+            }
+        }
+
+        let primary = shown
+            .iter()
+            .map(|window| {
+                if labelled {
+                    format!("{} {}%", window.label, window.percent())
+                } else {
+                    format!("{}%", window.percent())
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" · ");
+
+        let mut secondary_parts = Vec::new();
+        let burn_rate = if show_burn_rate && shown.iter().any(|window| window.key == "five_hour") {
+            Self::burn_rate(
+                &cache.five_hour_samples,
+                cache.five_hour_resets_at.as_deref(),
+            )
+        } else {
+            None
+        };
+        if let Some(burn_rate) = &burn_rate {
+            let rate_display = format!("{:+.0}%/h", burn_rate.percent_per_hour);
+            metadata.insert(
+                "burn_rate".to_string(),
+                burn_rate.percent_per_hour.to_string(),
+            );
+            metadata.insert("burn_rate_display".to_string(), rate_display.clone());
+            if burn_rate.percent_per_hour.round() > 0.0 {
+                secondary_parts.push(rate_display);
+            }
+            if let Some(limit_at) = burn_rate.limit_at {
+                let limit_display = limit_at.with_timezone(&Local).format("%H:%M").to_string();
+                metadata.insert("limit_at".to_string(), limit_at.to_rfc3339());
+                metadata.insert(
+                    "minutes_to_limit".to_string(),
+                    limit_at
+                        .signed_duration_since(Utc::now())
+                        .num_minutes()
+                        .max(0)
+                        .to_string(),
+                );
+                secondary_parts.push(format!("limit ~{}", limit_display));
+                metadata.insert("limit_display".to_string(), limit_display);
+            }
+        }
+
+        for window in &shown {
+            let reset_time = Self::format_reset_time(window.resets_at, reset_format);
+            if shown.len() > 1 {
+                secondary_parts.push(format!("{} {}", window.label, reset_time));
+            } else {
+                secondary_parts.push(reset_time);
+            }
+        }
+        let secondary = format!("· {}", secondary_parts.join(" · "));
+
+        // The most utilized of the shown windows drives the icon and `utilization`
+        let busiest = shown
+            .iter()
+            .max_by(|a, b| a.utilization.total_cmp(&b.utilization))
+            .copied()
+            .unwrap_or(&five_hour);
         metadata.insert(
-            "five_hour_utilization".to_string(),
-            five_hour_util.to_string(),
+            "dynamic_icon".to_string(),
+            Self::get_circle_icon(busiest.utilization / 100.0),
         );
+        metadata.insert("window".to_string(), busiest.key.to_string());
+        metadata.insert("utilization".to_string(), busiest.utilization.to_string());
         metadata.insert(
-            "seven_day_utilization".to_string(),
-            seven_day_util.to_string(),
+            "reset_time".to_string(),
+            Self::format_reset_time(busiest.resets_at, reset_format),
         );
 
         Some(SegmentData {
@@ -273,3 +727,190 @@ impl Segment for UsageSegment {
         SegmentId::Usage
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(minutes from now, utilization)` pairs, oldest first
+    type Readings = &'static [(i64, f64)];
+    /// Readings, resets_at, percent per hour, hours until the limit
+    type BurnRateCase<'a> = (Readings, Option<&'a str>, Option<f64>, Option<f64>);
+    /// Readings, previous and current resets_at, utilization, samples kept
+    type SampleCase<'a> = (Readings, Option<&'a str>, Option<&'a str>, f64, usize);
+
+    fn minutes_from_now(minutes: i64) -> DateTime<Utc> {
+        Utc::now() + Duration::minutes(minutes)
+    }
+
+    fn samples(readings: &[(i64, f64)]) -> Vec<UsageSample> {
+        readings
+            .iter()
+            .map(|&(minutes, utilization)| UsageSample {
+                at: minutes_from_now(minutes),
+                utilization,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn burn_rate_projects_when_the_limit_is_reached() {
+        let in_two_hours = minutes_from_now(120).to_rfc3339();
+        let in_ten_hours = minutes_from_now(600).to_rfc3339();
+
+        let cases: [BurnRateCase; 7] = [
+            (&[], None, None, None),
+            (&[(0, 10.0)], None, None, None),
+            (&[(-4, 10.0), (0, 20.0)], None, None, None),
+            (
+                &[(-60, 10.0), (-30, 15.0), (0, 20.0)],
+                None,
+                Some(10.0),
+                Some(8.0),
+            ),
+            (
+                &[(-60, 10.0), (0, 20.0)],
+                Some(&in_ten_hours),
+                Some(10.0),
+                Some(8.0),
+            ),
+            // The window resets before the limit would be reached
+            (
+                &[(-60, 10.0), (0, 20.0)],
+                Some(&in_two_hours),
+                Some(10.0),
+                None,
+            ),
+            (&[(-30, 40.0), (0, 40.0)], None, Some(0.0), None),
+        ];
+
+        for (readings, resets_at, rate, hours_left) in cases {
+            let samples = samples(readings);
+            let burn_rate = UsageSegment::burn_rate(&samples, resets_at);
+            assert_eq!(
+                burn_rate.as_ref().map(|b| b.percent_per_hour.round()),
+                rate,
+                "{:?}",
+                readings
+            );
+            let limit_at = burn_rate.and_then(|b| b.limit_at);
+            assert_eq!(
+                limit_at.map(|at| {
+                    let hours = at.signed_duration_since(samples.last().unwrap().at);
+                    (hours.num_seconds() as f64 / 3600.0).round()
+                }),
+                hours_left,
+                "{:?}",
+                readings
+            );
+        }
+    }
+
+    #[test]
+    fn record_sample_starts_over_when_the_window_resets() {
+        let resets_at = minutes_from_now(100).to_rfc3339();
+        let jittered = minutes_from_now(102).to_rfc3339();
+        let moved = minutes_from_now(400).to_rfc3339();
+
+        let cases: [SampleCase; 7] = [
+            (&[], None, Some(&resets_at), 10.0, 1),
+            (&[(-10, 5.0)], Some(&resets_at), Some(&resets_at), 10.0, 2),
+            (&[(-10, 5.0)], Some(&resets_at), Some(&jittered), 10.0, 2),
+            (&[(-10, 5.0)], Some(&resets_at), Some(&moved), 10.0, 1),
+            (&[(-10, 5.0)], None, Some(&resets_at), 10.0, 1),
+            // Utilization dropped, so the window reset between samples
+            (&[(-10, 50.0)], Some(&resets_at), Some(&resets_at), 3.0, 1),
+            // Samples older than the burn rate window are dropped
+            (&[(-90, 1.0), (-30, 2.0)], None, None, 3.0, 2),
+        ];
+
+        for (readings, previous, current, utilization, expected) in cases {
+            let recorded =
+                UsageSegment::record_sample(samples(readings), previous, current, utilization);
+            assert_eq!(recorded.len(), expected, "{:?} {:?}", readings, current);
+            assert_eq!(recorded.last().unwrap().utilization, utilization);
+        }
+
+        let full = vec![(0, 1.0); MAX_SAMPLES];
+        assert_eq!(
+            UsageSegment::record_sample(samples(&full), None, None, 1.0).len(),
+            MAX_SAMPLES
+        );
+    }
+
+    #[test]
+    fn relative_reset_times_count_down() {
+        let seconds = |s: i64| Some(Utc::now() + Duration::seconds(s));
+        for (reset_at, expected) in [
+            (None, "?"),
+            (seconds(7 * 60 + 30), "resets in 7m"),
+            (seconds(2 * 3600 + 5 * 60 + 30), "resets in 2h05m"),
+            (seconds(3 * 86400 + 4 * 3600 + 30), "resets in 3d4h"),
+            // A reset in the past shows as due now rather than negative
+            (seconds(-600), "resets in 0m"),
+        ] {
+            assert_eq!(
+                UsageSegment::format_reset_time(reset_at, ResetFormat::Relative),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn absolute_reset_times_name_the_day_unless_today() {
+        let in_three_days = Utc::now() + Duration::days(3);
+        let local = in_three_days.with_timezone(&Local);
+        assert_eq!(
+            UsageSegment::format_reset_time(Some(in_three_days), ResetFormat::Absolute),
+            format!("resets {}", local.format("%a %H:%M"))
+        );
+        assert_eq!(
+            UsageSegment::format_reset_time(None, ResetFormat::Absolute),
+            "?"
+        );
+    }
+
+    #[test]
+    fn bucket_names_split_into_window_and_model() {
+        for (name, expected) in [
+            ("seven_day_opus", ("seven_day", "opus")),
+            ("five_hour_sonnet", ("five_hour", "sonnet")),
+            ("seven_day", ("", "seven_day")),
+            ("seven_day_oauth_apps", ("seven_day", "oauth_apps")),
+            ("iguana_necktie", ("", "iguana_necktie")),
+        ] {
+            assert_eq!(UsageSegment::split_bucket_name(name), expected);
+        }
+    }
+
+    #[test]
+    fn model_bucket_prefers_the_most_specific_match() {
+        let period = UsagePeriod {
+            utilization: 1.0,
+            resets_at: None,
+        };
+        let buckets: BTreeMap<String, UsagePeriod> = [
+            "seven_day_opus",
+            "seven_day_opus_4",
+            "seven_day_sonnet",
+            "iguana_necktie",
+        ]
+        .into_iter()
+        .map(|name| (name.to_string(), period.clone()))
+        .collect();
+
+        for (model_id, expected) in [
+            ("claude-opus-4-6", Some("seven_day_opus")),
+            ("Claude-Sonnet-4-5", Some("seven_day_sonnet")),
+            ("claude-haiku-4-5", None),
+            ("unknown", None),
+        ] {
+            assert_eq!(
+                UsageSegment::model_bucket(&buckets, model_id).map(|(name, _)| name.as_str()),
+                expected,
+                "{}",
+                model_id
+            );
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use crate::config::{AnsiColor, Config, SegmentConfig, StyleMode};
 use crate::core::segments::SegmentData;
+use crate::core::template::Template;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-/// Strip ANSI escape sequences and return visible text length
+/// Strip ANSI escape sequences and return the text's width in terminal columns
 fn visible_width(text: &str) -> usize {
     let mut visible = String::new();
     let mut in_escape = false;
@@ -26,31 +28,88 @@ fn visible_width(text: &str) -> usize {
         }
     }
 
-    visible.chars().count()
+    visible.width()
+}
+
+/// Shorten text to `width` columns, marking the cut with an ellipsis
+fn ellipsize(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let mut result = String::new();
+    let mut used = 0;
+    for ch in text.chars() {
+        let ch_width = ch.width().unwrap_or(0);
+        if used + ch_width > width - 1 {
+            break;
+        }
+        used += ch_width;
+        result.push(ch);
+    }
+    result.push('…');
+    result
 }
 
 pub struct StatusLineGenerator {
     config: Config,
+    max_width: Option<usize>,
 }
 
 impl StatusLineGenerator {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        Self {
+            config,
+            max_width: None,
+        }
+    }
+
+    /// Limit each statusline row to `max_width` columns
+    pub fn with_max_width(mut self, max_width: Option<u16>) -> Self {
+        self.max_width = max_width.map(|w| w as usize);
+        self
     }
 
     pub fn generate(&self, segments: Vec<(SegmentConfig, SegmentData)>) -> String {
-        let mut output = Vec::new();
         let enabled_segments: Vec<_> = segments
             .into_iter()
             .filter(|(config, _)| config.enabled)
+            .map(Self::resolve_thresholds)
             .collect();
 
-        for (config, data) in enabled_segments.iter() {
-            let rendered = self.render_segment(config, data);
-            if !rendered.is_empty() {
-                output.push(rendered);
-            }
+        Self::split_lines(enabled_segments)
+            .iter()
+            .map(|line_segments| self.generate_line(line_segments))
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Apply threshold rules up front so both segment text and Powerline arrows
+    /// use the effective colors
+    fn resolve_thresholds(
+        (mut config, data): (SegmentConfig, SegmentData),
+    ) -> (SegmentConfig, SegmentData) {
+        config.apply_thresholds(&data.metadata);
+        (config, data)
+    }
+
+    /// Group segments by their configured line, keeping config order within each line
+    fn split_lines(
+        segments: Vec<(SegmentConfig, SegmentData)>,
+    ) -> Vec<Vec<(SegmentConfig, SegmentData)>> {
+        let mut lines: std::collections::BTreeMap<u8, Vec<_>> = std::collections::BTreeMap::new();
+        for (config, data) in segments {
+            lines.entry(config.line).or_default().push((config, data));
         }
+        lines.into_values().collect()
+    }
+
+    /// Render and join the segments of a single statusline row
+    fn generate_line(&self, segments: &[(SegmentConfig, SegmentData)]) -> String {
+        let (kept, output) = self.fit_line(segments);
 
         if output.is_empty() {
             return String::new();
@@ -58,13 +117,100 @@ impl StatusLineGenerator {
 
         // Handle Powerline arrow separators with color transition
         if self.config.style.separator == "\u{e0b0}" {
-            self.join_with_powerline_arrows(&output, &enabled_segments)
+            self.join_with_powerline_arrows(&output, &kept)
         } else {
             // For all other separators, use white color and simple join
             self.join_with_white_separators(&output)
         }
     }
 
+    /// Render a row within `max_width`, returning the kept segments and their rendered text.
+    ///
+    /// Lowest-priority segments are dropped until the remaining ones fit at their minimum
+    /// text width, then the text of shrinkable segments is ellipsized, lowest priority first.
+    fn fit_line(
+        &self,
+        segments: &[(SegmentConfig, SegmentData)],
+    ) -> (Vec<(SegmentConfig, SegmentData)>, Vec<String>) {
+        let natural: Vec<(String, usize)> = segments
+            .iter()
+            .map(|(config, data)| self.render_segment_with_limit(config, data, None))
+            .collect();
+
+        let mut kept: Vec<usize> = (0..segments.len())
+            .filter(|&i| !natural[i].0.is_empty())
+            .collect();
+
+        if let Some(max_width) = self.max_width {
+            let separator_width = visible_width(&self.config.style.separator);
+            let row_width =
+                |total: usize, count: usize| total + separator_width * count.saturating_sub(1);
+            let min_text = |i: usize| {
+                let text_width = natural[i].1;
+                segments[i]
+                    .0
+                    .min_width()
+                    .map_or(text_width, |min| min.min(text_width))
+            };
+            let overhead = |i: usize| visible_width(&natural[i].0) - natural[i].1;
+
+            // Phase 1: drop segments until the row fits with everything fully shrunk
+            while kept.len() > 1
+                && row_width(
+                    kept.iter().map(|&i| overhead(i) + min_text(i)).sum(),
+                    kept.len(),
+                ) > max_width
+            {
+                // Lowest priority goes first; on ties, the rightmost segment
+                let (pos, _) = kept
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(pos, &i)| (segments[i].0.priority(), std::cmp::Reverse(*pos)))
+                    .expect("kept is not empty");
+                kept.remove(pos);
+            }
+
+            // Phase 2: ellipsize text, lowest priority first, down to each minimum width
+            let mut overflow = row_width(
+                kept.iter().map(|&i| visible_width(&natural[i].0)).sum(),
+                kept.len(),
+            )
+            .saturating_sub(max_width);
+
+            let mut by_priority = kept.clone();
+            by_priority.sort_by_key(|&i| segments[i].0.priority());
+
+            let mut limits = std::collections::HashMap::new();
+            for i in by_priority {
+                if overflow == 0 {
+                    break;
+                }
+                let cut = overflow.min(natural[i].1 - min_text(i));
+                if cut > 0 {
+                    limits.insert(i, natural[i].1 - cut);
+                    overflow -= cut;
+                }
+            }
+
+            let output = kept
+                .iter()
+                .map(|&i| match limits.get(&i) {
+                    Some(&limit) => {
+                        let (config, data) = &segments[i];
+                        self.render_segment_with_limit(config, data, Some(limit)).0
+                    }
+                    None => natural[i].0.clone(),
+                })
+                .collect();
+            let kept = kept.iter().map(|&i| segments[i].clone()).collect();
+            return (kept, output);
+        }
+
+        let output = kept.iter().map(|&i| natural[i].0.clone()).collect();
+        let kept = kept.iter().map(|&i| segments[i].clone()).collect();
+        (kept, output)
+    }
+
     /// Generate statusline for TUI preview with proper width calculation
     /// This method handles ANSI escape sequences properly for ratatui rendering
     pub fn generate_for_tui(
@@ -99,17 +245,46 @@ impl StatusLineGenerator {
         let enabled_segments: Vec<_> = segments
             .into_iter()
             .filter(|(config, _)| config.enabled)
+            .map(Self::resolve_thresholds)
             .collect();
 
         if enabled_segments.is_empty() {
             return Text::from(vec![Line::default()]);
         }
 
+        // Explicit lines come first, then each one is wrapped to the available width
+        let lines: Vec<String> = Self::split_lines(enabled_segments)
+            .iter()
+            .flat_map(|line_segments| self.wrap_line(line_segments, max_width as usize))
+            .collect();
+
+        // Convert string lines to ratatui Text
+        let mut tui_lines = Vec::new();
+        for line in lines {
+            if let Ok(text) = line.into_text() {
+                for tui_line in text.lines {
+                    tui_lines.push(tui_line);
+                }
+            } else {
+                tui_lines.push(Line::from(vec![Span::raw(line)]));
+            }
+        }
+
+        // Ensure we have at least one line
+        if tui_lines.is_empty() {
+            tui_lines.push(Line::default());
+        }
+
+        Text::from(tui_lines)
+    }
+
+    /// Render one statusline row, breaking it between segments where it exceeds `max_w`
+    fn wrap_line(&self, segments: &[(SegmentConfig, SegmentData)], max_w: usize) -> Vec<String> {
         // Render each segment individually
         let mut rendered_segments = Vec::new();
         let mut segment_configs = Vec::new();
 
-        for (config, data) in &enabled_segments {
+        for (config, data) in segments {
             let rendered = self.render_segment(config, data);
             if !rendered.is_empty() {
                 rendered_segments.push(rendered);
@@ -118,7 +293,7 @@ impl StatusLineGenerator {
         }
 
         if rendered_segments.is_empty() {
-            return Text::from(vec![Line::default()]);
+            return Vec::new();
         }
 
         // Pre-calculate separators between segments
@@ -144,7 +319,6 @@ impl StatusLineGenerator {
         let mut lines: Vec<String> = Vec::new();
         let mut current_line = String::new();
         let mut current_width = 0usize;
-        let max_w = max_width as usize;
 
         for i in 0..rendered_segments.len() {
             let segment = &rendered_segments[i];
@@ -193,35 +367,44 @@ impl StatusLineGenerator {
             lines.push(current_line);
         }
 
-        // Convert string lines to ratatui Text
-        let mut tui_lines = Vec::new();
-        for line in lines {
-            if let Ok(text) = line.into_text() {
-                for tui_line in text.lines {
-                    tui_lines.push(tui_line);
-                }
-            } else {
-                tui_lines.push(Line::from(vec![Span::raw(line)]));
-            }
-        }
-
-        // Ensure we have at least one line
-        if tui_lines.is_empty() {
-            tui_lines.push(Line::default());
-        }
-
-        Text::from(tui_lines)
+        lines
     }
 
     fn render_segment(&self, config: &SegmentConfig, data: &SegmentData) -> String {
+        self.render_segment_with_limit(config, data, None).0
+    }
+
+    /// Render a segment with its primary text shortened to at most `limit` characters.
+    /// Returns the rendered segment and the visible width of its primary text.
+    fn render_segment_with_limit(
+        &self,
+        config: &SegmentConfig,
+        data: &SegmentData,
+        limit: Option<usize>,
+    ) -> (String, usize) {
         let icon = if let Some(dynamic_icon) = data.metadata.get("dynamic_icon") {
             dynamic_icon.clone()
         } else {
             self.get_icon(config)
         };
 
+        // A user-defined `format` template replaces the segment's built-in text
+        let (mut primary, secondary) = match self.format_with_template(config, data) {
+            Some(text) => (text, String::new()),
+            None => (data.primary.clone(), data.secondary.clone()),
+        };
+
+        let limit = match (limit, config.max_width()) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        if let Some(limit) = limit {
+            primary = ellipsize(&primary, limit);
+        }
+        let primary_width = visible_width(&primary);
+
         // Apply background color to the entire segment if set
-        if let Some(bg_color) = &config.colors.background {
+        let rendered = if let Some(bg_color) = &config.colors.background {
             let bg_code = self.apply_background_color(bg_color);
 
             // Build the entire segment content first
@@ -234,7 +417,7 @@ impl StatusLineGenerator {
 
             let text_styled = self
                 .apply_style(
-                    &data.primary,
+                    &primary,
                     config.colors.text.as_ref(),
                     config.styles.text_bold,
                 )
@@ -242,10 +425,10 @@ impl StatusLineGenerator {
 
             let mut segment_content = format!(" {} {} ", icon_colored, text_styled);
 
-            if !data.secondary.is_empty() {
+            if !secondary.is_empty() {
                 let secondary_styled = self
                     .apply_style(
-                        &data.secondary,
+                        &secondary,
                         config.colors.text.as_ref(),
                         config.styles.text_bold,
                     )
@@ -259,18 +442,18 @@ impl StatusLineGenerator {
             // No background color, use original logic
             let icon_colored = self.apply_color(&icon, config.colors.icon.as_ref());
             let text_styled = self.apply_style(
-                &data.primary,
+                &primary,
                 config.colors.text.as_ref(),
                 config.styles.text_bold,
             );
 
             let mut segment = format!("{} {}", icon_colored, text_styled);
 
-            if !data.secondary.is_empty() {
+            if !secondary.is_empty() {
                 segment.push_str(&format!(
                     " {}",
                     self.apply_style(
-                        &data.secondary,
+                        &secondary,
                         config.colors.text.as_ref(),
                         config.styles.text_bold
                     )
@@ -278,7 +461,21 @@ impl StatusLineGenerator {
             }
 
             segment
-        }
+        };
+
+        (rendered, primary_width)
+    }
+
+    /// Render the segment's `format` option against its metadata, if configured and valid
+    fn format_with_template(&self, config: &SegmentConfig, data: &SegmentData) -> Option<String> {
+        let source = config.options.get("format")?.as_str()?;
+        let template = Template::parse(source).ok()?;
+
+        Some(template.render(|key| match key {
+            "primary" => Some(data.primary.clone()),
+            "secondary" => Some(data.secondary.clone()),
+            _ => data.metadata.get(key).cloned(),
+        }))
     }
 
     fn get_icon(&self, config: &SegmentConfig) -> String {
@@ -453,68 +650,165 @@ impl StatusLineGenerator {
     }
 }
 
-pub fn collect_all_segments(
-    config: &Config,
-    input: &crate::config::InputData,
-) -> Vec<(SegmentConfig, SegmentData)> {
-    use crate::core::segments::*;
-
-    let mut results = Vec::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ColorConfig, IconConfig, SegmentId, TextStyleConfig};
+    use std::collections::HashMap;
+
+    fn segment(
+        id: SegmentId,
+        primary: &str,
+        options: &[(&str, serde_json::Value)],
+    ) -> (SegmentConfig, SegmentData) {
+        let config = SegmentConfig {
+            id,
+            enabled: true,
+            line: 1,
+            icon: IconConfig {
+                plain: "x".to_string(),
+                nerd_font: "x".to_string(),
+            },
+            colors: ColorConfig::default(),
+            styles: TextStyleConfig::default(),
+            options: options
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+            thresholds: Vec::new(),
+        };
+        let data = SegmentData {
+            primary: primary.to_string(),
+            secondary: String::new(),
+            metadata: HashMap::new(),
+        };
+        (config, data)
+    }
 
-    for segment_config in &config.segments {
-        // Skip disabled segments to avoid unnecessary API requests
-        if !segment_config.enabled {
-            continue;
+    /// Render one row in plain mode with ` | ` separators, without ANSI codes
+    fn render(max_width: u16, segments: Vec<(SegmentConfig, SegmentData)>) -> String {
+        let mut config = Config::default();
+        config.style.mode = StyleMode::Plain;
+        config.style.separator = " | ".to_string();
+        let line = StatusLineGenerator::new(config)
+            .with_max_width(Some(max_width))
+            .generate(segments);
+        let mut plain = String::new();
+        let mut in_escape = false;
+        for ch in line.chars() {
+            match ch {
+                '\x1b' => in_escape = true,
+                ch if in_escape => in_escape = !ch.is_ascii_alphabetic(),
+                ch => plain.push(ch),
+            }
         }
+        plain
+    }
 
-        let segment_data = match segment_config.id {
-            crate::config::SegmentId::Model => {
-                let segment = ModelSegment::new();
-                segment.collect(input)
-            }
-            crate::config::SegmentId::Directory => {
-                let segment = DirectorySegment::new();
-                segment.collect(input)
-            }
-            crate::config::SegmentId::Git => {
-                let show_sha = segment_config
-                    .options
-                    .get("show_sha")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false);
-                let segment = GitSegment::new().with_sha(show_sha);
-                segment.collect(input)
-            }
-            crate::config::SegmentId::ContextWindow => {
-                let segment = ContextWindowSegment::new();
-                segment.collect(input)
-            }
-            crate::config::SegmentId::Usage => {
-                let segment = UsageSegment::new();
-                segment.collect(input)
-            }
-            crate::config::SegmentId::Cost => {
-                let segment = CostSegment::new();
-                segment.collect(input)
-            }
-            crate::config::SegmentId::Session => {
-                let segment = SessionSegment::new();
-                segment.collect(input)
-            }
-            crate::config::SegmentId::OutputStyle => {
-                let segment = OutputStyleSegment::new();
-                segment.collect(input)
-            }
-            crate::config::SegmentId::Update => {
-                let segment = UpdateSegment::new();
-                segment.collect(input)
-            }
-        };
+    #[test]
+    fn keeps_everything_on_an_exact_fit() {
+        let segments = vec![
+            segment(SegmentId::Model, "abcd", &[]),
+            segment(SegmentId::Directory, "efgh", &[]),
+        ];
+        assert_eq!(render(15, segments), "x abcd | x efgh");
+    }
+
+    #[test]
+    fn drops_the_lowest_priority_segment_first() {
+        let segments = vec![
+            segment(SegmentId::Model, "aaaa", &[]),
+            segment(SegmentId::Custom, "cccc", &[]),
+            segment(SegmentId::Directory, "dddd", &[]),
+        ];
+        assert_eq!(render(20, segments.clone()), "x aaaa | x dddd");
+
+        // A higher `priority` option keeps it over the directory instead
+        let mut segments = segments;
+        segments[1] = segment(SegmentId::Custom, "cccc", &[("priority", 95.into())]);
+        assert_eq!(render(20, segments), "x aaaa | x cccc");
+    }
 
-        if let Some(data) = segment_data {
-            results.push((segment_config.clone(), data));
+    #[test]
+    fn ellipsizes_wide_text_by_display_width() {
+        let segments = vec![
+            segment(SegmentId::Model, "aaaa", &[]),
+            segment(SegmentId::Directory, "日本語のディレクトリ", &[]),
+        ];
+        let line = render(25, segments);
+        assert_eq!(line, "x aaaa | x 日本語のディ…");
+        assert!(visible_width(&line) <= 25);
+    }
+
+    #[test]
+    fn narrower_than_one_segment() {
+        // Shrinks to the minimum width, then overflows rather than vanishing
+        let segments = vec![segment(SegmentId::Directory, "abcdefghijkl", &[])];
+        assert_eq!(render(5, segments), "x abcdefg…");
+
+        // Of several, only the highest priority one is left
+        let segments = vec![
+            segment(SegmentId::Directory, "abcdefghijkl", &[]),
+            segment(SegmentId::Model, "model", &[]),
+        ];
+        assert_eq!(render(3, segments), "x model");
+    }
+
+    fn colored_rule(metric: &str, value: f64, c16: u8) -> crate::config::ThresholdRule {
+        crate::config::ThresholdRule {
+            metric: metric.to_string(),
+            operator: crate::config::ThresholdOperator::GreaterOrEqual,
+            value,
+            colors: ColorConfig {
+                text: Some(AnsiColor::Color16 { c16 }),
+                ..ColorConfig::default()
+            },
+            styles: None,
         }
     }
 
-    results
+    fn text_color(config: &SegmentConfig) -> Option<u8> {
+        match config.colors.text {
+            Some(AnsiColor::Color16 { c16 }) => Some(c16),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn first_matching_threshold_wins() {
+        let (mut config, mut data) = segment(SegmentId::Cost, "$12", &[]);
+        config.colors.text = Some(AnsiColor::Color16 { c16: 2 });
+        // Critical listed before warning, as the themes do
+        config.thresholds = vec![colored_rule("cost", 10.0, 1), colored_rule("cost", 5.0, 3)];
+
+        data.metadata.insert("cost".to_string(), "12".to_string());
+        let (resolved, _) = StatusLineGenerator::resolve_thresholds((config.clone(), data.clone()));
+        assert_eq!(text_color(&resolved), Some(1));
+
+        data.metadata.insert("cost".to_string(), "7".to_string());
+        let (resolved, _) = StatusLineGenerator::resolve_thresholds((config.clone(), data.clone()));
+        assert_eq!(text_color(&resolved), Some(3));
+
+        data.metadata.insert("cost".to_string(), "1".to_string());
+        let (resolved, _) = StatusLineGenerator::resolve_thresholds((config.clone(), data));
+        assert_eq!(text_color(&resolved), Some(2));
+    }
+
+    #[test]
+    fn missing_metric_keeps_base_colors() {
+        let (mut config, data) = segment(SegmentId::Cost, "$12", &[]);
+        config.colors.text = Some(AnsiColor::Color16 { c16: 2 });
+        config.thresholds = vec![colored_rule("cost", 0.0, 1)];
+        let (resolved, _) = StatusLineGenerator::resolve_thresholds((config, data));
+        assert_eq!(text_color(&resolved), Some(2));
+    }
+
+    #[test]
+    fn ellipsize_counts_columns() {
+        assert_eq!(ellipsize("abcdef", 6), "abcdef");
+        assert_eq!(ellipsize("abcdef", 4), "abc…");
+        assert_eq!(ellipsize("日本語", 4), "日…");
+        assert_eq!(ellipsize("abc", 0), "");
+        assert_eq!(visible_width("\x1b[1;31m日本\x1b[0m"), 4);
+    }
 }
@@ -0,0 +1,227 @@
+//! Segment text templates
+//!
+//! A template is plain text with `{field}` placeholders that are filled from a
+//! segment's `metadata`. Syntax:
+//!   - `{field}`       : metadata value, empty if missing
+//!   - `{field:.N}`    : numeric value rounded to N decimal places
+//!   - `[ ... ]`       : optional group, dropped entirely if any placeholder inside is empty
+//!   - `\{` `\[` etc.  : literal character
+//!
+//! Example: `"{percentage:.0}%[ ({tokens_k}/{limit_k})]"`
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Literal(String),
+    Field {
+        name: String,
+        precision: Option<usize>,
+    },
+    Group(Vec<Node>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    /// Parse a template string, returning a description of the first syntax error
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut chars = source.chars().peekable();
+        let nodes = Self::parse_nodes(&mut chars, false)?;
+        Ok(Self { nodes })
+    }
+
+    fn parse_nodes(
+        chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+        in_group: bool,
+    ) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        let mut literal = String::new();
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' => match chars.next() {
+                    Some(escaped) => literal.push(escaped),
+                    None => return Err("Trailing '\\' at end of template".to_string()),
+                },
+                '{' => {
+                    if !literal.is_empty() {
+                        nodes.push(Node::Literal(std::mem::take(&mut literal)));
+                    }
+                    nodes.push(Self::parse_field(chars)?);
+                }
+                '}' => return Err("Unmatched '}' in template".to_string()),
+                '[' => {
+                    if !literal.is_empty() {
+                        nodes.push(Node::Literal(std::mem::take(&mut literal)));
+                    }
+                    nodes.push(Node::Group(Self::parse_nodes(chars, true)?));
+                }
+                ']' if in_group => {
+                    if !literal.is_empty() {
+                        nodes.push(Node::Literal(literal));
+                    }
+                    return Ok(nodes);
+                }
+                ']' => return Err("Unmatched ']' in template".to_string()),
+                _ => literal.push(ch),
+            }
+        }
+
+        if in_group {
+            return Err("Unclosed '[' in template".to_string());
+        }
+
+        if !literal.is_empty() {
+            nodes.push(Node::Literal(literal));
+        }
+        Ok(nodes)
+    }
+
+    fn parse_field(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Result<Node, String> {
+        let mut spec = String::new();
+        loop {
+            match chars.next() {
+                Some('}') => break,
+                Some(c) => spec.push(c),
+                None => return Err("Unclosed '{' in template".to_string()),
+            }
+        }
+
+        let (name, format) = match spec.split_once(':') {
+            Some((name, format)) => (name.trim(), Some(format.trim())),
+            None => (spec.trim(), None),
+        };
+
+        if name.is_empty() {
+            return Err("Empty placeholder '{}' in template".to_string());
+        }
+
+        let precision = match format {
+            Some(f) => {
+                let digits = f
+                    .strip_prefix('.')
+                    .ok_or_else(|| format!("Unsupported format '{}' for '{}'", f, name))?;
+                Some(
+                    digits
+                        .parse::<usize>()
+                        .map_err(|_| format!("Invalid precision '{}' for '{}'", f, name))?,
+                )
+            }
+            None => None,
+        };
+
+        Ok(Node::Field {
+            name: name.to_string(),
+            precision,
+        })
+    }
+
+    /// Render the template, resolving placeholders through `lookup`
+    pub fn render<F>(&self, lookup: F) -> String
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut output = String::new();
+        Self::render_nodes(&self.nodes, &lookup, &mut output);
+        output
+    }
+
+    /// Render nodes into `output`, returning false if any placeholder was empty
+    fn render_nodes<F>(nodes: &[Node], lookup: &F, output: &mut String) -> bool
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut complete = true;
+
+        for node in nodes {
+            match node {
+                Node::Literal(text) => output.push_str(text),
+                Node::Field { name, precision } => {
+                    let value = lookup(name)
+                        .map(|v| Self::format_value(&v, *precision))
+                        .unwrap_or_default();
+                    if value.is_empty() {
+                        complete = false;
+                    }
+                    output.push_str(&value);
+                }
+                Node::Group(children) => {
+                    let mut group = String::new();
+                    if Self::render_nodes(children, lookup, &mut group) {
+                        output.push_str(&group);
+                    }
+                }
+            }
+        }
+
+        complete
+    }
+
+    fn format_value(value: &str, precision: Option<usize>) -> String {
+        match precision {
+            Some(p) => match value.parse::<f64>() {
+                Ok(n) => format!("{:.*}", p, n),
+                Err(_) => value.to_string(),
+            },
+            None => value.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn render(source: &str, fields: &[(&str, &str)]) -> String {
+        let fields: HashMap<&str, &str> = fields.iter().copied().collect();
+        Template::parse(source)
+            .unwrap()
+            .render(|name| fields.get(name).map(|v| v.to_string()))
+    }
+
+    #[test]
+    fn fills_placeholders() {
+        assert_eq!(render("{a} / {b}", &[("a", "1"), ("b", "2")]), "1 / 2");
+        assert_eq!(render("x{missing}y", &[]), "xy");
+    }
+
+    #[test]
+    fn rounds_to_precision() {
+        assert_eq!(render("{p:.0}%", &[("p", "26.65")]), "27%");
+        assert_eq!(render("{p:.2}", &[("p", "3")]), "3.00");
+        // Non-numeric values are left alone
+        assert_eq!(render("{p:.1}", &[("p", "n/a")]), "n/a");
+    }
+
+    #[test]
+    fn drops_groups_with_empty_placeholders() {
+        let source = "{p}%[ ({used}/{limit})]";
+        assert_eq!(
+            render(source, &[("p", "5"), ("used", "1k"), ("limit", "200k")]),
+            "5% (1k/200k)"
+        );
+        assert_eq!(render(source, &[("p", "5"), ("used", "1k")]), "5%");
+        assert_eq!(render("a[b[{x}]c]", &[]), "abc");
+        assert_eq!(render("a[b[{x}]{y}]", &[("x", "1")]), "a");
+    }
+
+    #[test]
+    fn escapes_are_literal() {
+        assert_eq!(render(r"\{a\} \[{a}\] \\", &[("a", "1")]), r"{a} [1] \");
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        assert!(Template::parse("{a").is_err());
+        assert!(Template::parse("a}").is_err());
+        assert!(Template::parse("[a").is_err());
+        assert!(Template::parse("a]").is_err());
+        assert!(Template::parse("{}").is_err());
+        assert!(Template::parse("{a:x}").is_err());
+        assert!(Template::parse("{a:.x}").is_err());
+        assert!(Template::parse("a\\").is_err());
+    }
+}
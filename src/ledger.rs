@@ -0,0 +1,316 @@
+use crate::config::{Config, InputData, SegmentId};
+use crate::utils::lock::{self, FileLock};
+use crate::utils::paths;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+/// Sessions not seen for this many days are dropped from the ledger
+const RETENTION_DAYS: i64 = 100;
+
+/// Spend of a single Claude Code session, keyed in the ledger by transcript path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub project: String,
+    /// Latest `cost.total_cost_usd` reported for the session
+    pub cost: f64,
+    pub last_seen: DateTime<Utc>,
+    /// Cost accrued per local day (`YYYY-MM-DD`), so sessions spanning
+    /// midnight are split between days
+    #[serde(default)]
+    pub daily: BTreeMap<NaiveDate, f64>,
+}
+
+/// Persistent record of spend across sessions, stored in `ledger.json` in the state directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Ledger {
+    #[serde(default)]
+    pub sessions: HashMap<String, SessionRecord>,
+}
+
+/// Spend since a given day, overall and by project
+#[derive(Debug, Default)]
+pub struct SpendSummary {
+    pub total: f64,
+    pub sessions: usize,
+    /// `(project, sessions, cost)`, most expensive first
+    pub projects: Vec<(String, usize, f64)>,
+}
+
+impl Ledger {
+    fn ledger_path() -> Option<PathBuf> {
+        Some(paths::state_dir().join("ledger.json"))
+    }
+
+    pub fn load() -> Self {
+        Self::ledger_path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether anything in the config reads the ledger: the spend or budget
+    /// segment, or daily/monthly budgets
+    pub fn is_used_by(config: &Config) -> bool {
+        config.budget.daily.is_some()
+            || config.budget.monthly.is_some()
+            || config.segments.iter().any(|segment| {
+                segment.enabled && matches!(segment.id, SegmentId::Spend | SegmentId::Budget)
+            })
+    }
+
+    /// Record the session's latest cost. Skipped if another process holds the
+    /// ledger lock for too long; the cost is cumulative, so the next refresh
+    /// catches up without losing anything.
+    pub fn record(input: &InputData) {
+        let Some(cost) = input.cost.as_ref().and_then(|c| c.total_cost_usd) else {
+            return;
+        };
+        if input.transcript_path.is_empty() {
+            return;
+        }
+        let Some(path) = Self::ledger_path() else {
+            return;
+        };
+
+        // Cheap unlocked check so unchanged refreshes don't contend for the lock
+        if Self::load()
+            .sessions
+            .get(&input.transcript_path)
+            .is_some_and(|session| session.cost == cost)
+        {
+            return;
+        }
+
+        let Some(_lock) = FileLock::acquire(
+            &path.with_extension("json.lock"),
+            std::time::Duration::from_millis(250),
+        ) else {
+            return;
+        };
+
+        let mut ledger = Self::load();
+        ledger.update_session(
+            &input.transcript_path,
+            &input.workspace.current_dir,
+            cost,
+            Self::today(),
+        );
+        ledger.prune();
+
+        if let Ok(json) = serde_json::to_string_pretty(&ledger) {
+            let _ = lock::write_atomic(&path, json.as_bytes());
+        }
+    }
+
+    fn update_session(&mut self, session_id: &str, project: &str, cost: f64, today: NaiveDate) {
+        let session = self
+            .sessions
+            .entry(session_id.to_string())
+            .or_insert_with(|| SessionRecord {
+                project: project.to_string(),
+                cost: 0.0,
+                last_seen: Utc::now(),
+                daily: BTreeMap::new(),
+            });
+
+        // A lower cost means the session restarted its counter; count it afresh
+        let delta = if cost >= session.cost {
+            cost - session.cost
+        } else {
+            cost
+        };
+
+        *session.daily.entry(today).or_insert(0.0) += delta;
+        session.cost = cost;
+        session.last_seen = Utc::now();
+    }
+
+    fn prune(&mut self) {
+        let cutoff = Utc::now() - Duration::days(RETENTION_DAYS);
+        self.sessions
+            .retain(|_, session| session.last_seen >= cutoff);
+    }
+
+    /// Spend accrued on `day`
+    pub fn total_on(&self, day: NaiveDate) -> f64 {
+        self.sessions
+            .values()
+            .filter_map(|session| session.daily.get(&day))
+            .fold(0.0, |total, cost| total + cost)
+    }
+
+    /// Spend accrued from `since` (inclusive) until now
+    pub fn summary_since(&self, since: NaiveDate) -> SpendSummary {
+        let mut summary = SpendSummary::default();
+        let mut projects: HashMap<&str, (usize, f64)> = HashMap::new();
+
+        for session in self.sessions.values() {
+            let cost = session
+                .daily
+                .range(since..)
+                .fold(0.0, |total, (_, cost)| total + cost);
+            if cost <= 0.0 {
+                continue;
+            }
+
+            summary.total += cost;
+            summary.sessions += 1;
+            let project = projects.entry(&session.project).or_default();
+            project.0 += 1;
+            project.1 += cost;
+        }
+
+        summary.projects = projects
+            .into_iter()
+            .map(|(project, (sessions, cost))| (project.to_string(), sessions, cost))
+            .collect();
+        summary
+            .projects
+            .sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        summary
+    }
+
+    /// Today in local time
+    pub fn today() -> NaiveDate {
+        Local::now().date_naive()
+    }
+
+    /// Monday of the current local week
+    pub fn week_start() -> NaiveDate {
+        let today = Self::today();
+        today - Duration::days(today.weekday().num_days_from_monday() as i64)
+    }
+
+    /// First day of the current local month
+    pub fn month_start() -> NaiveDate {
+        let today = Self::today();
+        today.with_day(1).unwrap_or(today)
+    }
+
+    /// Print daily totals for the last `days` days, plus week, month and per-project rollups
+    pub fn print_report(&self, days: u32) {
+        let today = Self::today();
+
+        println!("{:<14} {:>10}", "Day", "Cost");
+        for offset in (0..days.max(1) as i64).rev() {
+            let day = today - Duration::days(offset);
+            println!(
+                "{:<14} {:>10}",
+                day.format("%a %Y-%m-%d").to_string(),
+                format_usd(self.total_on(day))
+            );
+        }
+        println!();
+
+        let week = self.summary_since(Self::week_start());
+        let month = self.summary_since(Self::month_start());
+        println!("{:<14} {:>10}", "This week", format_usd(week.total));
+        println!("{:<14} {:>10}", "This month", format_usd(month.total));
+        println!();
+
+        if week.projects.is_empty() {
+            println!("No spend recorded this week.");
+            return;
+        }
+
+        let width = week
+            .projects
+            .iter()
+            .map(|(project, _, _)| project.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("Project (this week)".len());
+        println!(
+            "{:<width$} {:>8} {:>10}",
+            "Project (this week)",
+            "Sessions",
+            "Cost",
+            width = width
+        );
+        for (project, sessions, cost) in &week.projects {
+            println!(
+                "{:<width$} {:>8} {:>10}",
+                project,
+                sessions,
+                format_usd(*cost),
+                width = width
+            );
+        }
+    }
+}
+
+pub fn format_usd(cost: f64) -> String {
+    format!("${:.2}", cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+    }
+
+    fn input(transcript_path: &str, cost: f64) -> InputData {
+        serde_json::from_value(serde_json::json!({
+            "model": {"id": "claude-sonnet-4", "display_name": "Sonnet 4"},
+            "workspace": {"current_dir": "/work/project"},
+            "transcript_path": transcript_path,
+            "cost": {"total_cost_usd": cost},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn session_spend_is_split_by_day() {
+        let mut ledger = Ledger::default();
+        ledger.update_session("a", "/p", 1.0, day(1));
+        ledger.update_session("a", "/p", 1.5, day(1));
+        ledger.update_session("a", "/p", 4.0, day(2));
+        ledger.update_session("b", "/q", 2.0, day(2));
+
+        assert_eq!(ledger.total_on(day(1)), 1.5);
+        assert_eq!(ledger.total_on(day(2)), 4.5);
+        assert_eq!(ledger.sessions["a"].cost, 4.0);
+
+        let summary = ledger.summary_since(day(2));
+        assert_eq!(summary.total, 4.5);
+        assert_eq!(summary.sessions, 2);
+        assert_eq!(
+            summary.projects,
+            [("/p".to_string(), 1, 2.5), ("/q".to_string(), 1, 2.0)]
+        );
+
+        let summary = ledger.summary_since(day(3));
+        assert_eq!(summary.sessions, 0);
+        assert!(summary.projects.is_empty());
+    }
+
+    #[test]
+    fn a_restarted_session_counts_its_new_cost() {
+        let mut ledger = Ledger::default();
+        ledger.update_session("a", "/p", 3.0, day(1));
+        ledger.update_session("a", "/p", 0.5, day(1));
+        assert_eq!(ledger.total_on(day(1)), 3.5);
+    }
+
+    #[test]
+    fn recording_the_same_cost_again_is_idempotent() {
+        let state_dir = paths::use_test_dir();
+        let transcript = format!("{}/ledger-idempotent.jsonl", state_dir.display());
+
+        Ledger::record(&input(&transcript, 1.25));
+        Ledger::record(&input(&transcript, 1.25));
+        let ledger = Ledger::load();
+        assert_eq!(ledger.sessions[&transcript].cost, 1.25);
+        assert_eq!(ledger.sessions[&transcript].daily[&Ledger::today()], 1.25);
+
+        Ledger::record(&input(&transcript, 2.0));
+        Ledger::record(&input(&transcript, 2.0));
+        let ledger = Ledger::load();
+        assert_eq!(ledger.sessions[&transcript].project, "/work/project");
+        assert_eq!(ledger.sessions[&transcript].daily[&Ledger::today()], 2.0);
+    }
+}
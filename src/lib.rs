@@ -1,6 +1,9 @@
 pub mod cli;
+pub mod commands;
 pub mod config;
 pub mod core;
+pub mod ledger;
+pub mod refresh;
 pub mod ui;
 pub mod updater;
 pub mod utils;
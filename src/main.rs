@@ -1,15 +1,23 @@
-use ccometixline::cli::Cli;
-use ccometixline::config::{Config, InputData};
-use ccometixline::core::{collect_all_segments, StatusLineGenerator};
+use ccometixline::cli::{Cli, Command};
+use ccometixline::config::InputData;
 use ccometixline::ui::{MainMenu, MenuResult};
 use std::io::{self, IsTerminal};
+use std::process::ExitCode;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
     let cli = Cli::parse_args();
 
+    if let Some(dir) = cli.config_dir.clone() {
+        ccometixline::utils::paths::set_config_dir(dir);
+    }
+
+    if let Some(command) = &cli.command {
+        return ccometixline::commands::run(command, &cli);
+    }
+
     if cli.config {
         ccometixline::ui::run_configurator()?;
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
     }
 
     // Handle Claude Code patcher
@@ -35,15 +43,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("💡 To restore warnings, replace your cli.js with the backup file:");
         println!("   cp {} {}", backup_path, claude_path);
 
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
     }
 
-    // Load configuration
-    let mut config = Config::load().unwrap_or_else(|_| Config::default());
-
-    // Apply theme override if provided
-    if let Some(theme) = cli.theme {
-        config = ccometixline::ui::themes::ThemePresets::get_theme(&theme);
+    if cli.print_config {
+        return ccometixline::commands::run(&Command::Print, &cli);
     }
 
     // Check if stdin has data
@@ -57,21 +61,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 MenuResult::Exit => {}
             }
         }
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
     }
 
     // Read Claude Code data from stdin
     let stdin = io::stdin();
     let input: InputData = serde_json::from_reader(stdin.lock())?;
 
-    // Collect segment data
-    let segments_data = collect_all_segments(&config, &input);
-
-    // Render statusline
-    let generator = StatusLineGenerator::new(config);
-    let statusline = generator.generate(segments_data);
-
-    println!("{}", statusline);
-
-    Ok(())
+    ccometixline::commands::render(&cli, &input, true)?;
+    Ok(ExitCode::SUCCESS)
 }
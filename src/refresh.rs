@@ -0,0 +1,277 @@
+//! Background refresh of slow caches: network-backed ones, and segments that
+//! missed the render deadline.
+//!
+//! Segments render from whatever is cached and call [`spawn`] when it is
+//! stale. That starts a detached `ccline refresh <task>` process which
+//! updates the cache behind a lock file, so the statusline never waits on
+//! the network. What the refresher needs from the render that started it,
+//! like the effective segment options, is handed over with [`spawn_with`].
+//! Refreshes that only concern one directory or session are scoped with
+//! [`spawn_scoped`], so they don't hold back each other's retries.
+
+use crate::config::{Config, SegmentId};
+use crate::core::segments::UsageSegment;
+use crate::updater::UpdateState;
+use crate::utils::lock::{self, FileLock};
+use crate::utils::{dry_run, paths, process};
+use clap::ValueEnum;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+
+/// Don't start another refresh of the same task within this long, so a
+/// failing refresh isn't retried on every render
+const RETRY_AFTER: Duration = Duration::from_secs(30);
+/// Files of scoped refreshes that haven't run for this long are removed
+const SCOPE_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum RefreshTask {
+    /// Claude API usage limits
+    Usage,
+    /// ccline update check
+    Update,
+    /// Segments that missed the last render's deadline
+    Segments,
+}
+
+impl RefreshTask {
+    fn name(self) -> &'static str {
+        match self {
+            RefreshTask::Usage => "usage",
+            RefreshTask::Update => "update",
+            RefreshTask::Segments => "segments",
+        }
+    }
+
+    /// `.refresh_{task}` or `.refresh_{task}.{scope}`, followed by `suffix`
+    fn file_path(self, scope: Option<&str>, suffix: &str) -> Option<PathBuf> {
+        let stem = match scope {
+            Some(scope) => format!(".refresh_{}.{}", self.name(), scope),
+            None => format!(".refresh_{}", self.name()),
+        };
+        Some(refresh_dir()?.join(format!("{}{}", stem, suffix)))
+    }
+
+    fn lock_path(self, scope: Option<&str>) -> Option<PathBuf> {
+        self.file_path(scope, ".lock")
+    }
+
+    /// Touched whenever a refresh is started
+    fn marker_path(self, scope: Option<&str>) -> Option<PathBuf> {
+        self.file_path(scope, ".last")
+    }
+
+    /// Written by [`spawn_with`] for the refresher to read
+    fn input_path(self, scope: Option<&str>) -> Option<PathBuf> {
+        self.file_path(scope, "_input.json")
+    }
+}
+
+fn refresh_dir() -> Option<PathBuf> {
+    Some(paths::state_dir())
+}
+
+/// Start a background refresh unless one is already running or was started recently
+pub fn spawn(task: RefreshTask) {
+    start(task, None, None);
+}
+
+/// Like [`spawn`], handing `input` to the refresher, which gets it back from
+/// [`read_input`]
+pub fn spawn_with<T: Serialize>(task: RefreshTask, input: &T) {
+    if let Ok(json) = serde_json::to_vec(input) {
+        start(task, None, Some(json));
+    }
+}
+
+/// Like [`spawn_with`], for a refresh that only concerns `key`, e.g. one
+/// directory. Refreshes of different keys run and retry independently.
+pub fn spawn_scoped<T: Serialize>(task: RefreshTask, key: &str, input: &T) {
+    if let Ok(json) = serde_json::to_vec(input) {
+        start(task, Some(scope_of(key)), Some(json));
+    }
+}
+
+/// File name safe id of a scope key
+fn scope_of(key: &str) -> String {
+    let hash = format!("{:x}", Sha256::digest(key.as_bytes()));
+    hash[..16].to_string()
+}
+
+/// What the render that started this refresh passed to [`spawn_with`] or
+/// [`spawn_scoped`]
+pub fn read_input<T: DeserializeOwned>(task: RefreshTask, scope: Option<&str>) -> Option<T> {
+    let json = std::fs::read(task.input_path(scope)?).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+fn start(task: RefreshTask, scope: Option<String>, input: Option<Vec<u8>>) {
+    // Under test the current executable is the test harness, not ccline
+    if dry_run::is_enabled() || cfg!(test) {
+        return;
+    }
+    let scope = scope.as_deref();
+    let (Some(lock_path), Some(marker_path)) = (task.lock_path(scope), task.marker_path(scope))
+    else {
+        return;
+    };
+
+    let started_recently = std::fs::metadata(&marker_path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < RETRY_AFTER);
+    if started_recently {
+        return;
+    }
+
+    // The lock file holds the pid of the refresher that owns it
+    let holder = std::fs::read_to_string(&lock_path)
+        .ok()
+        .and_then(|pid| pid.trim().parse::<u32>().ok());
+    if holder.is_some_and(process::is_running) {
+        return;
+    }
+
+    let Ok(exe) = std::env::current_exe() else {
+        return;
+    };
+    if let Some(parent) = marker_path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if std::fs::write(&marker_path, b"").is_err() {
+        return;
+    }
+    if let (Some(json), Some(input_path)) = (input, task.input_path(scope)) {
+        if lock::write_atomic(&input_path, &json).is_err() {
+            return;
+        }
+    }
+
+    let mut cmd = Command::new(exe);
+    if let Some(dir) = paths::config_dir_override() {
+        cmd.arg("--config-dir").arg(dir);
+    }
+    cmd.arg("refresh").arg(task.name());
+    if let Some(scope) = scope {
+        cmd.arg("--scope").arg(scope);
+    }
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // Run outside Claude Code's process group so it survives the statusline exiting
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        cmd.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+    }
+
+    let _ = cmd.spawn();
+}
+
+/// Entry point of the refresher process; does nothing if another one holds the lock
+pub fn run(task: RefreshTask, scope: Option<&str>) {
+    // Scopes come from `scope_of`; anything else could point outside the state dir
+    if scope.is_some_and(|scope| !scope.chars().all(|c| c.is_ascii_hexdigit())) {
+        return;
+    }
+    let Some(lock_path) = task.lock_path(scope) else {
+        return;
+    };
+    let Some(_lock) = FileLock::acquire(&lock_path, Duration::ZERO) else {
+        return;
+    };
+
+    match task {
+        RefreshTask::Usage => {
+            // The options the statusline rendered with, including project
+            // files and overrides; the global config's when run by hand
+            let options = read_input(task, scope).unwrap_or_else(|| {
+                Config::load()
+                    .ok()
+                    .and_then(|config| {
+                        config
+                            .segments
+                            .into_iter()
+                            .find(|segment| segment.id == SegmentId::Usage)
+                    })
+                    .map(|segment| segment.options)
+                    .unwrap_or_default()
+            });
+            UsageSegment::new().with_options(options).refresh()
+        }
+        RefreshTask::Update => UpdateState::refresh(),
+        RefreshTask::Segments => crate::core::collect::refresh(scope),
+    }
+
+    if scope.is_some() {
+        remove_stale_scopes(task);
+    }
+}
+
+/// Remove the marker, lock and input files of scopes not refreshed for a while
+fn remove_stale_scopes(task: RefreshTask) {
+    let Some(dir) = refresh_dir() else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let prefix = format!(".refresh_{}.", task.name());
+
+    for entry in entries.flatten() {
+        if !entry.file_name().to_string_lossy().starts_with(&prefix) {
+            continue;
+        }
+        let stale = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > SCOPE_RETENTION);
+        if stale {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scoped_refreshes_get_their_own_files() {
+        let first = scope_of("/work/a\nGit");
+        let second = scope_of("/work/b\nGit");
+        assert_ne!(first, second);
+        assert_eq!(first, scope_of("/work/a\nGit"));
+        assert!(first.len() == 16 && first.chars().all(|c| c.is_ascii_hexdigit()));
+
+        let task = RefreshTask::Segments;
+        assert_ne!(
+            task.marker_path(Some(&first)),
+            task.marker_path(Some(&second))
+        );
+        assert_ne!(task.lock_path(Some(&first)), task.lock_path(None));
+        assert_eq!(
+            task.input_path(Some(&first))
+                .unwrap()
+                .file_name()
+                .unwrap()
+                .to_string_lossy(),
+            format!(".refresh_segments.{}_input.json", first)
+        );
+    }
+}
@@ -9,6 +9,7 @@ use crate::ui::components::{
     separator_editor::SeparatorEditorComponent,
     settings::SettingsComponent,
     theme_selector::ThemeSelectorComponent,
+    threshold_editor::{ThresholdColorTarget, ThresholdEditorComponent},
 };
 use crossterm::{
     event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
@@ -24,6 +25,9 @@ use ratatui::{
 };
 use std::io;
 
+/// Number of statusline rows a segment can be assigned to from the configurator
+const MAX_LINES: u8 = 3;
+
 pub struct App {
     config: Config,
     selected_segment: usize,
@@ -38,6 +42,7 @@ pub struct App {
     separator_editor: SeparatorEditorComponent,
     settings: SettingsComponent,
     theme_selector: ThemeSelectorComponent,
+    threshold_editor: ThresholdEditorComponent,
     help: HelpComponent,
     status_message: Option<String>,
 }
@@ -58,6 +63,7 @@ impl App {
             separator_editor: SeparatorEditorComponent::new(),
             settings: SettingsComponent::new(),
             theme_selector: ThemeSelectorComponent::new(),
+            threshold_editor: ThresholdEditorComponent::new(),
             help: HelpComponent::new(),
             status_message: None,
         };
@@ -155,6 +161,8 @@ impl App {
                         KeyCode::Backspace => app.color_picker.backspace(),
                         _ => {}
                     }
+                } else if app.threshold_editor.is_open {
+                    app.handle_threshold_key(key.code, key.modifiers);
                 } else if app.icon_selector.is_open {
                     match key.code {
                         KeyCode::Esc => app.icon_selector.close(),
@@ -441,6 +449,11 @@ impl App {
         );
 
         // Render popups on top
+        if self.threshold_editor.is_open {
+            if let Some(segment) = self.config.segments.get(self.selected_segment) {
+                self.threshold_editor.render(f, f.area(), segment);
+            }
+        }
         if self.color_picker.is_open {
             self.color_picker.render(f, f.area());
         }
@@ -465,25 +478,29 @@ impl App {
                 self.selected_segment = new_selection;
             }
             Panel::Settings => {
-                let field_count = 7; // Enabled, Icon, IconColor, TextColor, TextStyle, BackgroundColor, Options
+                let field_count = 9; // Enabled, Line, Icon, IconColor, TextColor, BackgroundColor, TextStyle, Thresholds, Options
                 let current_field = match self.selected_field {
                     FieldSelection::Enabled => 0i32,
-                    FieldSelection::Icon => 1,
-                    FieldSelection::IconColor => 2,
-                    FieldSelection::TextColor => 3,
-                    FieldSelection::BackgroundColor => 4,
-                    FieldSelection::TextStyle => 5,
-                    FieldSelection::Options => 6,
+                    FieldSelection::Line => 1,
+                    FieldSelection::Icon => 2,
+                    FieldSelection::IconColor => 3,
+                    FieldSelection::TextColor => 4,
+                    FieldSelection::BackgroundColor => 5,
+                    FieldSelection::TextStyle => 6,
+                    FieldSelection::Thresholds => 7,
+                    FieldSelection::Options => 8,
                 };
                 let new_field = (current_field + delta).clamp(0, field_count - 1) as usize;
                 self.selected_field = match new_field {
                     0 => FieldSelection::Enabled,
-                    1 => FieldSelection::Icon,
-                    2 => FieldSelection::IconColor,
-                    3 => FieldSelection::TextColor,
-                    4 => FieldSelection::BackgroundColor,
-                    5 => FieldSelection::TextStyle,
-                    6 => FieldSelection::Options,
+                    1 => FieldSelection::Line,
+                    2 => FieldSelection::Icon,
+                    3 => FieldSelection::IconColor,
+                    4 => FieldSelection::TextColor,
+                    5 => FieldSelection::BackgroundColor,
+                    6 => FieldSelection::TextStyle,
+                    7 => FieldSelection::Thresholds,
+                    8 => FieldSelection::Options,
                     _ => FieldSelection::Enabled,
                 };
             }
@@ -504,8 +521,12 @@ impl App {
                         SegmentId::Usage => "Usage",
                         SegmentId::Cost => "Cost",
                         SegmentId::Session => "Session",
+                        SegmentId::Tokens => "Tokens",
+                        SegmentId::Spend => "Spend",
+                        SegmentId::Budget => "Budget",
                         SegmentId::OutputStyle => "Output Style",
                         SegmentId::Update => "Update",
+                        SegmentId::Custom => "Custom",
                     };
                     let is_enabled = segment.enabled;
                     self.status_message = Some(format!(
@@ -531,8 +552,12 @@ impl App {
                                 SegmentId::Usage => "Usage",
                                 SegmentId::Cost => "Cost",
                                 SegmentId::Session => "Session",
+                                SegmentId::Tokens => "Tokens",
+                                SegmentId::Spend => "Spend",
+                                SegmentId::Budget => "Budget",
                                 SegmentId::OutputStyle => "Output Style",
                                 SegmentId::Update => "Update",
+                                SegmentId::Custom => "Custom",
                             };
                             let is_enabled = segment.enabled;
                             self.status_message = Some(format!(
@@ -543,6 +568,15 @@ impl App {
                             self.preview.update_preview(&self.config);
                         }
                     }
+                    FieldSelection::Line => {
+                        // Cycle the segment through the available statusline rows
+                        if let Some(segment) = self.config.segments.get_mut(self.selected_segment) {
+                            segment.line = segment.line % MAX_LINES + 1;
+                            self.status_message =
+                                Some(format!("Moved segment to line {}", segment.line));
+                            self.preview.update_preview(&self.config);
+                        }
+                    }
                     FieldSelection::Icon => self.open_icon_selector(),
                     FieldSelection::IconColor
                     | FieldSelection::TextColor
@@ -562,6 +596,7 @@ impl App {
                             self.preview.update_preview(&self.config);
                         }
                     }
+                    FieldSelection::Thresholds => self.threshold_editor.open(),
                     FieldSelection::Options => {
                         // TODO: Implement options editor
                         self.status_message =
@@ -596,6 +631,11 @@ impl App {
     }
 
     fn apply_selected_color(&mut self, color: crate::config::AnsiColor) {
+        if self.threshold_editor.is_open {
+            self.apply_threshold_color(color);
+            return;
+        }
+
         if let Some(segment) = self.config.segments.get_mut(self.selected_segment) {
             match self.selected_field {
                 FieldSelection::IconColor => segment.colors.icon = Some(color),
@@ -607,6 +647,117 @@ impl App {
         }
     }
 
+    fn apply_threshold_color(&mut self, color: crate::config::AnsiColor) {
+        let selected_rule = self.threshold_editor.selected_rule;
+        let target = self.threshold_editor.color_target.take();
+        if let Some(rule) = self
+            .config
+            .segments
+            .get_mut(self.selected_segment)
+            .and_then(|segment| segment.thresholds.get_mut(selected_rule))
+        {
+            match target {
+                Some(ThresholdColorTarget::Icon) => rule.colors.icon = Some(color),
+                Some(ThresholdColorTarget::Text) => rule.colors.text = Some(color),
+                Some(ThresholdColorTarget::Background) => rule.colors.background = Some(color),
+                None => {}
+            }
+            self.preview.update_preview(&self.config);
+        }
+    }
+
+    /// Handle a key press while the threshold rule editor is open
+    fn handle_threshold_key(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+        let Some(segment) = self.config.segments.get_mut(self.selected_segment) else {
+            return;
+        };
+        let segment_id = segment.id;
+        let rules = &mut segment.thresholds;
+        let selected = self.threshold_editor.selected_rule;
+
+        match code {
+            KeyCode::Esc => {
+                self.threshold_editor.close();
+                return;
+            }
+            KeyCode::Up if modifiers.contains(KeyModifiers::SHIFT) => {
+                if selected > 0 && selected < rules.len() {
+                    rules.swap(selected, selected - 1);
+                    self.threshold_editor.selected_rule -= 1;
+                }
+            }
+            KeyCode::Down if modifiers.contains(KeyModifiers::SHIFT) => {
+                if selected + 1 < rules.len() {
+                    rules.swap(selected, selected + 1);
+                    self.threshold_editor.selected_rule += 1;
+                }
+            }
+            KeyCode::Up => self.threshold_editor.move_selection(-1, rules.len()),
+            KeyCode::Down => self.threshold_editor.move_selection(1, rules.len()),
+            KeyCode::Char('a') | KeyCode::Char('A') => {
+                rules.push(ThresholdEditorComponent::new_rule(segment_id));
+                self.threshold_editor.selected_rule = rules.len() - 1;
+            }
+            KeyCode::Char('d') | KeyCode::Char('D') => {
+                if selected < rules.len() {
+                    rules.remove(selected);
+                    self.threshold_editor.move_selection(0, rules.len());
+                }
+            }
+            KeyCode::Char('g') | KeyCode::Char('G') if selected < rules.len() => {
+                self.threshold_editor.color_target = Some(ThresholdColorTarget::Background);
+                self.color_picker.open();
+                return;
+            }
+            KeyCode::Char('i') | KeyCode::Char('I') if selected < rules.len() => {
+                self.threshold_editor.color_target = Some(ThresholdColorTarget::Icon);
+                self.color_picker.open();
+                return;
+            }
+            KeyCode::Char('t') | KeyCode::Char('T') if selected < rules.len() => {
+                self.threshold_editor.color_target = Some(ThresholdColorTarget::Text);
+                self.color_picker.open();
+                return;
+            }
+            _ => {
+                let Some(rule) = rules.get_mut(selected) else {
+                    return;
+                };
+                match code {
+                    KeyCode::Left => {
+                        rule.value -= ThresholdEditorComponent::value_step(&rule.metric)
+                    }
+                    KeyCode::Right => {
+                        rule.value += ThresholdEditorComponent::value_step(&rule.metric)
+                    }
+                    KeyCode::Char('o') | KeyCode::Char('O') => {
+                        rule.operator = ThresholdEditorComponent::cycle_operator(rule.operator)
+                    }
+                    KeyCode::Char('m') | KeyCode::Char('M') => {
+                        rule.metric =
+                            ThresholdEditorComponent::cycle_metric(segment_id, &rule.metric)
+                    }
+                    KeyCode::Char('b') | KeyCode::Char('B') => {
+                        // Cycle bold override: inherit -> bold -> regular -> inherit
+                        rule.styles = match &rule.styles {
+                            None => Some(crate::config::TextStyleConfig { text_bold: true }),
+                            Some(styles) if styles.text_bold => {
+                                Some(crate::config::TextStyleConfig { text_bold: false })
+                            }
+                            Some(_) => None,
+                        }
+                    }
+                    KeyCode::Char('x') | KeyCode::Char('X') => {
+                        rule.colors = crate::config::ColorConfig::default()
+                    }
+                    _ => return,
+                }
+            }
+        }
+
+        self.preview.update_preview(&self.config);
+    }
+
     fn apply_selected_icon(&mut self, icon: String) {
         if let Some(segment) = self.config.segments.get_mut(self.selected_segment) {
             match self.config.style.mode {
@@ -629,7 +780,7 @@ impl App {
     }
 
     fn switch_to_theme(&mut self, theme_name: &str) {
-        self.config = crate::ui::themes::ThemePresets::get_theme(theme_name);
+        self.config.switch_theme(theme_name);
         self.selected_segment = 0;
         self.preview.update_preview(&self.config);
         self.status_message = Some(format!("Switched to {} theme", theme_name));
@@ -638,7 +789,7 @@ impl App {
     /// Reset current theme to its default configuration
     fn reset_to_theme_defaults(&mut self) {
         let current_theme = self.config.theme.clone();
-        self.config = crate::ui::themes::ThemePresets::get_theme(&current_theme);
+        self.config.switch_theme(&current_theme);
         self.selected_segment = 0;
         self.preview.update_preview(&self.config);
         self.status_message = Some(format!("Reset {} theme to defaults", current_theme));
@@ -120,8 +120,15 @@ impl PreviewComponent {
                         let mut map = HashMap::new();
                         map.insert("branch".to_string(), "master".to_string());
                         map.insert("status".to_string(), "Clean".to_string());
+                        map.insert("status_icon".to_string(), "✓".to_string());
                         map.insert("ahead".to_string(), "0".to_string());
                         map.insert("behind".to_string(), "0".to_string());
+                        map.insert("staged".to_string(), "0".to_string());
+                        map.insert("modified".to_string(), "0".to_string());
+                        map.insert("untracked".to_string(), "0".to_string());
+                        map.insert("conflicted".to_string(), "0".to_string());
+                        map.insert("stash".to_string(), "0".to_string());
+                        map.insert("detached".to_string(), "false".to_string());
                         map
                     },
                 },
@@ -131,15 +138,40 @@ impl PreviewComponent {
                     metadata: {
                         let mut map = HashMap::new();
                         map.insert("total_tokens".to_string(), "156400".to_string());
+                        map.insert("tokens".to_string(), "156400".to_string());
+                        map.insert("tokens_k".to_string(), "156.4k".to_string());
                         map.insert("percentage".to_string(), "78.2".to_string());
+                        map.insert("percentage_display".to_string(), "78.2%".to_string());
+                        map.insert("limit".to_string(), "200000".to_string());
+                        map.insert("limit_k".to_string(), "200k".to_string());
                         map.insert("session_tokens".to_string(), "48200".to_string());
                         map
                     },
                 },
                 SegmentId::Usage => SegmentData {
                     primary: "24%".to_string(),
-                    secondary: "· 10-7-2".to_string(),
-                    metadata: HashMap::new(),
+                    secondary: "· +12%/h · resets in 2h13m".to_string(),
+                    metadata: {
+                        let mut map = HashMap::new();
+                        map.insert("five_hour_utilization".to_string(), "24".to_string());
+                        map.insert("five_hour_percent".to_string(), "24".to_string());
+                        map.insert("seven_day_utilization".to_string(), "12".to_string());
+                        map.insert("utilization".to_string(), "24".to_string());
+                        map.insert("window".to_string(), "five_hour".to_string());
+                        map.insert("seven_day_percent".to_string(), "12".to_string());
+                        map.insert(
+                            "five_hour_reset_display".to_string(),
+                            "resets in 2h13m".to_string(),
+                        );
+                        map.insert(
+                            "seven_day_reset_display".to_string(),
+                            "resets in 3d4h".to_string(),
+                        );
+                        map.insert("reset_time".to_string(), "resets in 2h13m".to_string());
+                        map.insert("burn_rate".to_string(), "12".to_string());
+                        map.insert("burn_rate_display".to_string(), "+12%/h".to_string());
+                        map
+                    },
                 },
                 SegmentId::Cost => SegmentData {
                     primary: "$0.02".to_string(),
@@ -161,6 +193,53 @@ impl PreviewComponent {
                         map
                     },
                 },
+                SegmentId::Budget => SegmentData {
+                    primary: "$5.80 left today".to_string(),
+                    secondary: "(71%)".to_string(),
+                    metadata: {
+                        let mut map = HashMap::new();
+                        map.insert("scope".to_string(), "daily".to_string());
+                        map.insert("used_percent".to_string(), "71".to_string());
+                        map.insert("remaining".to_string(), "5.8".to_string());
+                        map.insert("remaining_display".to_string(), "$5.80".to_string());
+                        map.insert("limit".to_string(), "20".to_string());
+                        map.insert("spent".to_string(), "14.2".to_string());
+                        map
+                    },
+                },
+                SegmentId::Spend => SegmentData {
+                    primary: "$14.20 today".to_string(),
+                    secondary: "· $52.10 week".to_string(),
+                    metadata: {
+                        let mut map = HashMap::new();
+                        map.insert("today".to_string(), "14.2".to_string());
+                        map.insert("week".to_string(), "52.1".to_string());
+                        map.insert("month".to_string(), "163.4".to_string());
+                        map.insert("today_display".to_string(), "$14.20".to_string());
+                        map.insert("week_display".to_string(), "$52.10".to_string());
+                        map.insert("month_display".to_string(), "$163.40".to_string());
+                        map.insert("sessions_today".to_string(), "3".to_string());
+                        map
+                    },
+                },
+                SegmentId::Tokens => SegmentData {
+                    primary: "↑1.2M ↓84k".to_string(),
+                    secondary: "⚡92% cached".to_string(),
+                    metadata: {
+                        let mut map = HashMap::new();
+                        map.insert("input".to_string(), "16000".to_string());
+                        map.insert("output".to_string(), "84000".to_string());
+                        map.insert("cache_creation".to_string(), "80000".to_string());
+                        map.insert("cache_read".to_string(), "1104000".to_string());
+                        map.insert("total_input".to_string(), "1200000".to_string());
+                        map.insert("total_input_display".to_string(), "1.2M".to_string());
+                        map.insert("output_display".to_string(), "84k".to_string());
+                        map.insert("messages".to_string(), "42".to_string());
+                        map.insert("cache_hit_percent".to_string(), "92".to_string());
+                        map.insert("cache_hit_display".to_string(), "92%".to_string());
+                        map
+                    },
+                },
                 SegmentId::OutputStyle => SegmentData {
                     primary: "default".to_string(),
                     secondary: "".to_string(),
@@ -183,6 +262,19 @@ impl PreviewComponent {
                         map
                     },
                 },
+                SegmentId::Custom => {
+                    let name = segment_config.instance_name().unwrap_or("custom");
+                    SegmentData {
+                        primary: name.to_string(),
+                        secondary: "".to_string(),
+                        metadata: {
+                            let mut map = HashMap::new();
+                            map.insert("name".to_string(), name.to_string());
+                            map.insert("output".to_string(), name.to_string());
+                            map
+                        },
+                    }
+                }
             };
 
             segments_data.push((segment_config.clone(), mock_data));
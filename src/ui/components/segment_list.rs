@@ -16,11 +16,13 @@ pub enum Panel {
 #[derive(Debug, Clone, PartialEq)]
 pub enum FieldSelection {
     Enabled,
+    Line,
     Icon,
     IconColor,
     TextColor,
     BackgroundColor,
     TextStyle,
+    Thresholds,
     Options,
 }
 
@@ -40,6 +42,9 @@ impl SegmentListComponent {
         selected_segment: usize,
         selected_panel: &Panel,
     ) {
+        // Only show line numbers once the layout actually uses more than one line
+        let multi_line = config.segments.iter().any(|s| s.line != 1);
+
         let items: Vec<ListItem> = config
             .segments
             .iter()
@@ -55,9 +60,20 @@ impl SegmentListComponent {
                     SegmentId::Usage => "Usage",
                     SegmentId::Cost => "Cost",
                     SegmentId::Session => "Session",
+                    SegmentId::Tokens => "Tokens",
+                    SegmentId::Spend => "Spend",
+                    SegmentId::Budget => "Budget",
                     SegmentId::OutputStyle => "Output Style",
                     SegmentId::Update => "Update",
+                    SegmentId::Custom => "Custom",
+                };
+                let mut segment_name = match segment.instance_name() {
+                    Some(name) => format!("{} ({})", segment_name, name),
+                    None => segment_name.to_string(),
                 };
+                if multi_line {
+                    segment_name.push_str(&format!(" [L{}]", segment.line));
+                }
 
                 if is_selected {
                     // Selected item with colored cursor
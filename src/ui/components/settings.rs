@@ -34,8 +34,16 @@ impl SettingsComponent {
                 SegmentId::Usage => "Usage",
                 SegmentId::Cost => "Cost",
                 SegmentId::Session => "Session",
+                SegmentId::Tokens => "Tokens",
+                SegmentId::Spend => "Spend",
+                SegmentId::Budget => "Budget",
                 SegmentId::OutputStyle => "Output Style",
                 SegmentId::Update => "Update",
+                SegmentId::Custom => "Custom",
+            };
+            let segment_name = match segment.instance_name() {
+                Some(name) => format!("{} ({})", segment_name, name),
+                None => segment_name.to_string(),
             };
             let current_icon = match config.style.mode {
                 StyleMode::Plain => &segment.icon.plain,
@@ -217,6 +225,10 @@ impl SettingsComponent {
                         if segment.enabled { "✓" } else { "✗" }
                     ))],
                 ),
+                create_field_line(
+                    FieldSelection::Line,
+                    vec![Span::raw(format!("├─ Line: {}", segment.line))],
+                ),
                 create_field_line(
                     FieldSelection::Icon,
                     vec![
@@ -266,6 +278,13 @@ impl SettingsComponent {
                         }
                     ))],
                 ),
+                create_field_line(
+                    FieldSelection::Thresholds,
+                    vec![Span::raw(format!(
+                        "├─ Thresholds: {} rules",
+                        segment.thresholds.len()
+                    ))],
+                ),
                 create_field_line(
                     FieldSelection::Options,
                     vec![Span::raw(format!(
@@ -0,0 +1,244 @@
+use crate::config::{
+    AnsiColor, ColorConfig, SegmentConfig, SegmentId, ThresholdOperator, ThresholdRule,
+};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Which color of the selected rule the color picker will edit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThresholdColorTarget {
+    Icon,
+    Text,
+    Background,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ThresholdEditorComponent {
+    pub is_open: bool,
+    pub selected_rule: usize,
+    pub color_target: Option<ThresholdColorTarget>,
+}
+
+impl ThresholdEditorComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn open(&mut self) {
+        self.is_open = true;
+        self.selected_rule = 0;
+        self.color_target = None;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.color_target = None;
+    }
+
+    pub fn move_selection(&mut self, delta: i32, rule_count: usize) {
+        if rule_count == 0 {
+            self.selected_rule = 0;
+            return;
+        }
+        self.selected_rule =
+            (self.selected_rule as i32 + delta).clamp(0, rule_count as i32 - 1) as usize;
+    }
+
+    /// Metadata values that make sense as thresholds for a segment
+    pub fn metrics_for(id: SegmentId) -> &'static [&'static str] {
+        match id {
+            SegmentId::ContextWindow => &["percentage", "tokens"],
+            SegmentId::Usage => &[
+                "utilization",
+                "five_hour_utilization",
+                "seven_day_utilization",
+                "model_utilization",
+                "burn_rate",
+                "minutes_to_limit",
+            ],
+            SegmentId::Cost => &["cost"],
+            SegmentId::Spend => &["today", "week", "month"],
+            SegmentId::Budget => &[
+                "used_percent",
+                "session_used_percent",
+                "daily_used_percent",
+                "monthly_used_percent",
+            ],
+            SegmentId::Tokens => &["cache_hit_percent", "total_input", "output"],
+            _ => &["percentage", "five_hour_utilization", "cost"],
+        }
+    }
+
+    /// A new rule for the segment, using its first metric and a red text color
+    pub fn new_rule(id: SegmentId) -> ThresholdRule {
+        let metric = Self::metrics_for(id)[0];
+        ThresholdRule {
+            metric: metric.to_string(),
+            operator: ThresholdOperator::GreaterOrEqual,
+            value: Self::value_step(metric) * 16.0,
+            colors: ColorConfig {
+                icon: None,
+                text: Some(AnsiColor::Color16 { c16: 9 }),
+                background: None,
+            },
+            styles: None,
+        }
+    }
+
+    /// Amount the value changes per keypress
+    pub fn value_step(metric: &str) -> f64 {
+        match metric {
+            "cost" | "today" | "week" | "month" => 0.5,
+            "tokens" | "total_input" | "output" => 10_000.0,
+            _ => 5.0,
+        }
+    }
+
+    pub fn cycle_operator(operator: ThresholdOperator) -> ThresholdOperator {
+        match operator {
+            ThresholdOperator::GreaterOrEqual => ThresholdOperator::Greater,
+            ThresholdOperator::Greater => ThresholdOperator::LessOrEqual,
+            ThresholdOperator::LessOrEqual => ThresholdOperator::Less,
+            ThresholdOperator::Less => ThresholdOperator::GreaterOrEqual,
+        }
+    }
+
+    pub fn cycle_metric(id: SegmentId, metric: &str) -> String {
+        let metrics = Self::metrics_for(id);
+        let next = metrics
+            .iter()
+            .position(|m| *m == metric)
+            .map(|i| (i + 1) % metrics.len())
+            .unwrap_or(0);
+        metrics[next].to_string()
+    }
+
+    fn color_span(label: &str, color: &Option<AnsiColor>) -> Vec<Span<'static>> {
+        match color {
+            Some(c) => vec![
+                Span::raw(format!(" {}:", label)),
+                Span::styled("██".to_string(), Style::default().fg(to_ratatui_color(c))),
+            ],
+            None => vec![Span::styled(
+                format!(" {}:--", label),
+                Style::default().fg(Color::DarkGray),
+            )],
+        }
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, segment: &SegmentConfig) {
+        if !self.is_open {
+            return;
+        }
+
+        let popup_width = 70_u16.min(area.width.saturating_sub(4));
+        let popup_height = 18_u16.min(area.height.saturating_sub(2));
+        let popup_area = Rect {
+            x: (area.width.saturating_sub(popup_width)) / 2,
+            y: (area.height.saturating_sub(popup_height)) / 2,
+            width: popup_width,
+            height: popup_height,
+        };
+
+        f.render_widget(Clear, popup_area);
+
+        let popup_block = Block::default()
+            .borders(Borders::ALL)
+            .title("Threshold Rules");
+        let inner = popup_block.inner(popup_area);
+        f.render_widget(popup_block, popup_area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(5),    // Rules list
+                Constraint::Length(4), // Actions
+            ])
+            .split(inner);
+
+        let lines: Vec<Line> = if segment.thresholds.is_empty() {
+            vec![Line::from(Span::styled(
+                "No rules - press [A] to add one",
+                Style::default().fg(Color::DarkGray),
+            ))]
+        } else {
+            segment
+                .thresholds
+                .iter()
+                .enumerate()
+                .map(|(i, rule)| {
+                    let mut spans = vec![if i == self.selected_rule {
+                        Span::styled("▶ ", Style::default().fg(Color::Cyan))
+                    } else {
+                        Span::raw("  ")
+                    }];
+                    spans.push(Span::raw(format!(
+                        "{}. {} {} {}",
+                        i + 1,
+                        rule.metric,
+                        rule.operator.symbol(),
+                        rule.value
+                    )));
+                    spans.extend(Self::color_span("icon", &rule.colors.icon));
+                    spans.extend(Self::color_span("text", &rule.colors.text));
+                    spans.extend(Self::color_span("bg", &rule.colors.background));
+                    spans.push(Span::raw(match &rule.styles {
+                        Some(styles) if styles.text_bold => " bold",
+                        Some(_) => " regular",
+                        None => "",
+                    }));
+                    Line::from(spans)
+                })
+                .collect()
+        };
+
+        f.render_widget(
+            Paragraph::new(Text::from(lines)).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("First matching rule wins"),
+            ),
+            chunks[0],
+        );
+
+        f.render_widget(
+            Paragraph::new(
+                "[A] Add  [D] Delete  [←→] Value  [O] Operator  [M] Metric  [B] Bold\n\
+                 [I/T/G] Icon/Text/Background color  [X] Clear  [Shift+↑↓] Order  [Esc] Close",
+            )
+            .block(Block::default().borders(Borders::ALL)),
+            chunks[1],
+        );
+    }
+}
+
+fn to_ratatui_color(color: &AnsiColor) -> Color {
+    match color {
+        AnsiColor::Color16 { c16 } => match c16 {
+            0 => Color::Black,
+            1 => Color::Red,
+            2 => Color::Green,
+            3 => Color::Yellow,
+            4 => Color::Blue,
+            5 => Color::Magenta,
+            6 => Color::Cyan,
+            7 => Color::White,
+            8 => Color::DarkGray,
+            9 => Color::LightRed,
+            10 => Color::LightGreen,
+            11 => Color::LightYellow,
+            12 => Color::LightBlue,
+            13 => Color::LightMagenta,
+            14 => Color::LightCyan,
+            15 => Color::Gray,
+            _ => Color::White,
+        },
+        AnsiColor::Color256 { c256 } => Color::Indexed(*c256),
+        AnsiColor::Rgb { r, g, b } => Color::Rgb(*r, *g, *b),
+    }
+}
@@ -1,6 +1,9 @@
 // Theme presets for TUI configuration
 
-use crate::config::{Config, StyleConfig, StyleMode};
+use crate::config::{
+    schema::CONFIG_VERSION, BudgetConfig, ColorConfig, Config, StyleConfig, StyleMode,
+    TextStyleConfig, ThresholdOperator, ThresholdRule,
+};
 
 // Import all theme modules
 use super::{
@@ -17,7 +20,11 @@ impl ThemePresets {
             return config;
         }
 
-        // Fallback to built-in themes
+        Self::get_builtin_theme(theme_name)
+    }
+
+    /// Built-in theme, ignoring theme files; unknown names get the default theme
+    pub fn get_builtin_theme(theme_name: &str) -> Config {
         match theme_name {
             "cometix" => Self::get_cometix(),
             "default" => Self::get_default(),
@@ -41,8 +48,7 @@ impl ThemePresets {
             return Err(format!("Theme file not found: {}", theme_path.display()).into());
         }
 
-        let content = std::fs::read_to_string(&theme_path)?;
-        let mut config: Config = toml::from_str(&content)?;
+        let mut config = crate::config::schema::read_file(&theme_path)?.config;
 
         // Ensure the theme field matches the requested theme
         config.theme = theme_name.to_string();
@@ -50,13 +56,9 @@ impl ThemePresets {
         Ok(config)
     }
 
-    /// Get the themes directory path (~/.claude/ccline/themes/)
+    /// Get the themes directory path (~/.claude/ccline/themes/ by default)
     fn get_themes_path() -> std::path::PathBuf {
-        if let Some(home) = dirs::home_dir() {
-            home.join(".claude").join("ccline").join("themes")
-        } else {
-            std::path::PathBuf::from(".claude/ccline/themes")
-        }
+        crate::utils::paths::themes_dir()
     }
 
     /// Save current config as a new theme
@@ -122,11 +124,41 @@ impl ThemePresets {
         ]
     }
 
+    /// Warning and critical threshold rules shared by the built-in themes.
+    /// The critical rule comes first so it wins once both thresholds are crossed.
+    pub fn warning_critical_rules(
+        metric: &str,
+        warning_at: f64,
+        critical_at: f64,
+        warning: ColorConfig,
+        critical: ColorConfig,
+    ) -> Vec<ThresholdRule> {
+        vec![
+            ThresholdRule {
+                metric: metric.to_string(),
+                operator: ThresholdOperator::GreaterOrEqual,
+                value: critical_at,
+                colors: critical,
+                styles: Some(TextStyleConfig { text_bold: true }),
+            },
+            ThresholdRule {
+                metric: metric.to_string(),
+                operator: ThresholdOperator::GreaterOrEqual,
+                value: warning_at,
+                colors: warning,
+                styles: None,
+            },
+        ]
+    }
+
     pub fn get_cometix() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::NerdFont,
                 separator: " | ".to_string(),
+                max_width: None,
+                deadline_ms: None,
             },
             segments: vec![
                 theme_cometix::model_segment(),
@@ -135,18 +167,26 @@ impl ThemePresets {
                 theme_cometix::context_window_segment(),
                 theme_cometix::usage_segment(),
                 theme_cometix::cost_segment(),
+                theme_cometix::spend_segment(),
+                theme_cometix::budget_segment(),
                 theme_cometix::session_segment(),
+                theme_cometix::tokens_segment(),
                 theme_cometix::output_style_segment(),
             ],
             theme: "cometix".to_string(),
+            budget: BudgetConfig::default(),
+            trusted_projects: Vec::new(),
         }
     }
 
     pub fn get_default() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::Plain,
                 separator: " | ".to_string(),
+                max_width: None,
+                deadline_ms: None,
             },
             segments: vec![
                 theme_default::model_segment(),
@@ -155,18 +195,26 @@ impl ThemePresets {
                 theme_default::context_window_segment(),
                 theme_default::usage_segment(),
                 theme_default::cost_segment(),
+                theme_default::spend_segment(),
+                theme_default::budget_segment(),
                 theme_default::session_segment(),
+                theme_default::tokens_segment(),
                 theme_default::output_style_segment(),
             ],
             theme: "default".to_string(),
+            budget: BudgetConfig::default(),
+            trusted_projects: Vec::new(),
         }
     }
 
     pub fn get_minimal() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::Plain,
                 separator: " │ ".to_string(),
+                max_width: None,
+                deadline_ms: None,
             },
             segments: vec![
                 theme_minimal::model_segment(),
@@ -175,18 +223,26 @@ impl ThemePresets {
                 theme_minimal::context_window_segment(),
                 theme_minimal::usage_segment(),
                 theme_minimal::cost_segment(),
+                theme_minimal::spend_segment(),
+                theme_minimal::budget_segment(),
                 theme_minimal::session_segment(),
+                theme_minimal::tokens_segment(),
                 theme_minimal::output_style_segment(),
             ],
             theme: "minimal".to_string(),
+            budget: BudgetConfig::default(),
+            trusted_projects: Vec::new(),
         }
     }
 
     pub fn get_gruvbox() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::NerdFont,
                 separator: " | ".to_string(),
+                max_width: None,
+                deadline_ms: None,
             },
             segments: vec![
                 theme_gruvbox::model_segment(),
@@ -195,18 +251,26 @@ impl ThemePresets {
                 theme_gruvbox::context_window_segment(),
                 theme_gruvbox::usage_segment(),
                 theme_gruvbox::cost_segment(),
+                theme_gruvbox::spend_segment(),
+                theme_gruvbox::budget_segment(),
                 theme_gruvbox::session_segment(),
+                theme_gruvbox::tokens_segment(),
                 theme_gruvbox::output_style_segment(),
             ],
             theme: "gruvbox".to_string(),
+            budget: BudgetConfig::default(),
+            trusted_projects: Vec::new(),
         }
     }
 
     pub fn get_nord() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::NerdFont,
                 separator: "".to_string(),
+                max_width: None,
+                deadline_ms: None,
             },
             segments: vec![
                 theme_nord::model_segment(),
@@ -215,18 +279,26 @@ impl ThemePresets {
                 theme_nord::context_window_segment(),
                 theme_nord::usage_segment(),
                 theme_nord::cost_segment(),
+                theme_nord::spend_segment(),
+                theme_nord::budget_segment(),
                 theme_nord::session_segment(),
+                theme_nord::tokens_segment(),
                 theme_nord::output_style_segment(),
             ],
             theme: "nord".to_string(),
+            budget: BudgetConfig::default(),
+            trusted_projects: Vec::new(),
         }
     }
 
     pub fn get_powerline_dark() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::NerdFont,
                 separator: "".to_string(),
+                max_width: None,
+                deadline_ms: None,
             },
             segments: vec![
                 theme_powerline_dark::model_segment(),
@@ -235,18 +307,26 @@ impl ThemePresets {
                 theme_powerline_dark::context_window_segment(),
                 theme_powerline_dark::usage_segment(),
                 theme_powerline_dark::cost_segment(),
+                theme_powerline_dark::spend_segment(),
+                theme_powerline_dark::budget_segment(),
                 theme_powerline_dark::session_segment(),
+                theme_powerline_dark::tokens_segment(),
                 theme_powerline_dark::output_style_segment(),
             ],
             theme: "powerline-dark".to_string(),
+            budget: BudgetConfig::default(),
+            trusted_projects: Vec::new(),
         }
     }
 
     pub fn get_powerline_light() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::NerdFont,
                 separator: "".to_string(),
+                max_width: None,
+                deadline_ms: None,
             },
             segments: vec![
                 theme_powerline_light::model_segment(),
@@ -255,18 +335,26 @@ impl ThemePresets {
                 theme_powerline_light::context_window_segment(),
                 theme_powerline_light::usage_segment(),
                 theme_powerline_light::cost_segment(),
+                theme_powerline_light::spend_segment(),
+                theme_powerline_light::budget_segment(),
                 theme_powerline_light::session_segment(),
+                theme_powerline_light::tokens_segment(),
                 theme_powerline_light::output_style_segment(),
             ],
             theme: "powerline-light".to_string(),
+            budget: BudgetConfig::default(),
+            trusted_projects: Vec::new(),
         }
     }
 
     pub fn get_powerline_rose_pine() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::NerdFont,
                 separator: "".to_string(),
+                max_width: None,
+                deadline_ms: None,
             },
             segments: vec![
                 theme_powerline_rose_pine::model_segment(),
@@ -275,18 +363,26 @@ impl ThemePresets {
                 theme_powerline_rose_pine::context_window_segment(),
                 theme_powerline_rose_pine::usage_segment(),
                 theme_powerline_rose_pine::cost_segment(),
+                theme_powerline_rose_pine::spend_segment(),
+                theme_powerline_rose_pine::budget_segment(),
                 theme_powerline_rose_pine::session_segment(),
+                theme_powerline_rose_pine::tokens_segment(),
                 theme_powerline_rose_pine::output_style_segment(),
             ],
             theme: "powerline-rose-pine".to_string(),
+            budget: BudgetConfig::default(),
+            trusted_projects: Vec::new(),
         }
     }
 
     pub fn get_powerline_tokyo_night() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::NerdFont,
                 separator: "".to_string(),
+                max_width: None,
+                deadline_ms: None,
             },
             segments: vec![
                 theme_powerline_tokyo_night::model_segment(),
@@ -295,10 +391,15 @@ impl ThemePresets {
                 theme_powerline_tokyo_night::context_window_segment(),
                 theme_powerline_tokyo_night::usage_segment(),
                 theme_powerline_tokyo_night::cost_segment(),
+                theme_powerline_tokyo_night::spend_segment(),
+                theme_powerline_tokyo_night::budget_segment(),
                 theme_powerline_tokyo_night::session_segment(),
+                theme_powerline_tokyo_night::tokens_segment(),
                 theme_powerline_tokyo_night::output_style_segment(),
             ],
             theme: "powerline-tokyo-night".to_string(),
+            budget: BudgetConfig::default(),
+            trusted_projects: Vec::new(),
         }
     }
 }
@@ -1,5 +1,7 @@
+use super::ThemePresets;
 use crate::config::{
     AnsiColor, ColorConfig, IconConfig, SegmentConfig, SegmentId, TextStyleConfig,
+    ThresholdOperator, ThresholdRule,
 };
 use std::collections::HashMap;
 
@@ -7,6 +9,7 @@ pub fn model_segment() -> SegmentConfig {
     SegmentConfig {
         id: SegmentId::Model,
         enabled: true,
+        line: 1,
         icon: IconConfig {
             plain: "🤖".to_string(),
             nerd_font: "\u{e26d}".to_string(),
@@ -18,6 +21,7 @@ pub fn model_segment() -> SegmentConfig {
         },
         styles: TextStyleConfig::default(),
         options: HashMap::new(),
+        thresholds: Vec::new(),
     }
 }
 
@@ -25,6 +29,7 @@ pub fn directory_segment() -> SegmentConfig {
     SegmentConfig {
         id: SegmentId::Directory,
         enabled: true,
+        line: 1,
         icon: IconConfig {
             plain: "📁".to_string(),
             nerd_font: "\u{f024b}".to_string(),
@@ -36,6 +41,7 @@ pub fn directory_segment() -> SegmentConfig {
         },
         styles: TextStyleConfig::default(),
         options: HashMap::new(),
+        thresholds: Vec::new(),
     }
 }
 
@@ -43,6 +49,7 @@ pub fn git_segment() -> SegmentConfig {
     SegmentConfig {
         id: SegmentId::Git,
         enabled: true,
+        line: 1,
         icon: IconConfig {
             plain: "🌿".to_string(),
             nerd_font: "\u{f02a2}".to_string(),
@@ -58,6 +65,7 @@ pub fn git_segment() -> SegmentConfig {
             opts.insert("show_sha".to_string(), serde_json::Value::Bool(false));
             opts
         },
+        thresholds: Vec::new(),
     }
 }
 
@@ -65,6 +73,7 @@ pub fn context_window_segment() -> SegmentConfig {
     SegmentConfig {
         id: SegmentId::ContextWindow,
         enabled: true,
+        line: 1,
         icon: IconConfig {
             plain: "⚡️".to_string(),
             nerd_font: "\u{f49b}".to_string(),
@@ -76,6 +85,13 @@ pub fn context_window_segment() -> SegmentConfig {
         },
         styles: TextStyleConfig::default(),
         options: HashMap::new(),
+        thresholds: ThemePresets::warning_critical_rules(
+            "percentage",
+            60.0,
+            80.0,
+            warning_colors(),
+            critical_colors(),
+        ),
     }
 }
 
@@ -83,6 +99,7 @@ pub fn usage_segment() -> SegmentConfig {
     SegmentConfig {
         id: SegmentId::Usage,
         enabled: false,
+        line: 1,
         icon: IconConfig {
             plain: "📊".to_string(),
             nerd_font: "\u{f0a9e}".to_string(), // circle_slice_1
@@ -104,8 +121,23 @@ pub fn usage_segment() -> SegmentConfig {
                 serde_json::Value::Number(180.into()),
             );
             opts.insert("timeout".to_string(), serde_json::Value::Number(2.into()));
+            opts.insert(
+                "display".to_string(),
+                serde_json::Value::String("five_hour".to_string()),
+            );
+            opts.insert(
+                "reset_format".to_string(),
+                serde_json::Value::String("relative".to_string()),
+            );
             opts
         },
+        thresholds: ThemePresets::warning_critical_rules(
+            "utilization",
+            70.0,
+            90.0,
+            warning_colors(),
+            critical_colors(),
+        ),
     }
 }
 
@@ -113,6 +145,7 @@ pub fn cost_segment() -> SegmentConfig {
     SegmentConfig {
         id: SegmentId::Cost,
         enabled: false,
+        line: 1,
         icon: IconConfig {
             plain: "💰".to_string(),
             nerd_font: "\u{eec1}".to_string(),
@@ -124,6 +157,59 @@ pub fn cost_segment() -> SegmentConfig {
         },
         styles: TextStyleConfig::default(),
         options: HashMap::new(),
+        thresholds: ThemePresets::warning_critical_rules(
+            "cost",
+            5.0,
+            10.0,
+            warning_colors(),
+            critical_colors(),
+        ),
+    }
+}
+
+pub fn spend_segment() -> SegmentConfig {
+    SegmentConfig {
+        id: SegmentId::Spend,
+        enabled: false,
+        line: 1,
+        icon: IconConfig {
+            plain: "💸".to_string(),
+            nerd_font: "\u{f0d6}".to_string(),
+        },
+        colors: ColorConfig {
+            icon: Some(AnsiColor::Color16 { c16: 3 }), // Yellow
+            text: Some(AnsiColor::Color16 { c16: 3 }),
+            background: None,
+        },
+        styles: TextStyleConfig::default(),
+        options: HashMap::new(),
+        thresholds: Vec::new(),
+    }
+}
+
+pub fn budget_segment() -> SegmentConfig {
+    SegmentConfig {
+        id: SegmentId::Budget,
+        enabled: false,
+        line: 1,
+        icon: IconConfig {
+            plain: "💳".to_string(),
+            nerd_font: "\u{f09d}".to_string(),
+        },
+        colors: ColorConfig {
+            icon: Some(AnsiColor::Color16 { c16: 3 }), // Yellow
+            text: Some(AnsiColor::Color16 { c16: 3 }),
+            background: None,
+        },
+        styles: TextStyleConfig::default(),
+        options: HashMap::new(),
+        thresholds: ThemePresets::warning_critical_rules(
+            "used_percent",
+            75.0,
+            90.0,
+            warning_colors(),
+            critical_colors(),
+        ),
     }
 }
 
@@ -131,6 +217,7 @@ pub fn session_segment() -> SegmentConfig {
     SegmentConfig {
         id: SegmentId::Session,
         enabled: false,
+        line: 1,
         icon: IconConfig {
             plain: "⏱️".to_string(),
             nerd_font: "\u{f19bb}".to_string(),
@@ -142,6 +229,34 @@ pub fn session_segment() -> SegmentConfig {
         },
         styles: TextStyleConfig::default(),
         options: HashMap::new(),
+        thresholds: Vec::new(),
+    }
+}
+
+pub fn tokens_segment() -> SegmentConfig {
+    SegmentConfig {
+        id: SegmentId::Tokens,
+        enabled: false,
+        line: 1,
+        icon: IconConfig {
+            plain: "🪙".to_string(),
+            nerd_font: "\u{f01bc}".to_string(),
+        },
+        colors: ColorConfig {
+            icon: Some(AnsiColor::Color16 { c16: 2 }), // Green
+            text: Some(AnsiColor::Color16 { c16: 2 }),
+            background: None,
+        },
+        styles: TextStyleConfig::default(),
+        options: HashMap::new(),
+        // Flag sessions that keep missing the prompt cache
+        thresholds: vec![ThresholdRule {
+            metric: "cache_hit_percent".to_string(),
+            operator: ThresholdOperator::Less,
+            value: 50.0,
+            colors: warning_colors(),
+            styles: None,
+        }],
     }
 }
 
@@ -149,6 +264,7 @@ pub fn output_style_segment() -> SegmentConfig {
     SegmentConfig {
         id: SegmentId::OutputStyle,
         enabled: false,
+        line: 1,
         icon: IconConfig {
             plain: "🎯".to_string(),
             nerd_font: "\u{f12f5}".to_string(),
@@ -160,5 +276,22 @@ pub fn output_style_segment() -> SegmentConfig {
         },
         styles: TextStyleConfig::default(),
         options: HashMap::new(),
+        thresholds: Vec::new(),
+    }
+}
+
+fn warning_colors() -> ColorConfig {
+    ColorConfig {
+        icon: Some(AnsiColor::Color16 { c16: 11 }),
+        text: Some(AnsiColor::Color16 { c16: 11 }),
+        background: None,
+    }
+}
+
+fn critical_colors() -> ColorConfig {
+    ColorConfig {
+        icon: Some(AnsiColor::Color16 { c16: 9 }),
+        text: Some(AnsiColor::Color16 { c16: 9 }),
+        background: None,
     }
 }
@@ -1,3 +1,5 @@
+use crate::refresh::{self, RefreshTask};
+use crate::utils::{lock, paths};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -24,7 +26,6 @@ pub struct UpdateState {
     pub last_check: Option<DateTime<Utc>>,
     pub current_version: String,
     pub latest_version: Option<String>,
-    pub update_pid: Option<u32>,
 }
 
 impl UpdateState {
@@ -36,16 +37,21 @@ impl UpdateState {
         }
     }
 
-    /// Load update state from config directory and trigger auto-check if needed
+    /// Load update state from config directory, starting a background check if one is due
     pub fn load() -> Self {
-        let config_dir = dirs::home_dir()
-            .unwrap_or_default()
-            .join(".claude")
-            .join("ccline");
+        let state = Self::load_state();
+        if state.should_check_update() {
+            refresh::spawn(RefreshTask::Update);
+        }
+        state
+    }
+
+    fn load_state() -> Self {
+        let config_dir = paths::state_dir();
 
         let state_file = config_dir.join(".update_state.json");
 
-        let mut state = if let Ok(content) = std::fs::read_to_string(&state_file) {
+        if let Ok(content) = std::fs::read_to_string(&state_file) {
             serde_json::from_str::<UpdateState>(&content).unwrap_or_else(|_| UpdateState {
                 current_version: env!("CARGO_PKG_VERSION").to_string(),
                 ..Default::default()
@@ -55,84 +61,46 @@ impl UpdateState {
                 current_version: env!("CARGO_PKG_VERSION").to_string(),
                 ..Default::default()
             }
-        };
-
-        if state.should_check_update() {
-            let should_start_check = if let Some(pid) = state.update_pid {
-                !Self::is_process_running(pid)
-            } else {
-                true
-            };
-
-            if should_start_check {
-                state.update_pid = Some(std::process::id());
-                state.last_check = Some(Utc::now());
-                let _ = state.save();
-
-                match registry::check_for_updates() {
-                    Ok(Some(version)) => {
-                        state.status = UpdateStatus::Ready {
-                            version: version.clone(),
-                            found_at: Utc::now(),
-                        };
-                        state.latest_version = Some(version);
-                    }
-                    Ok(None) => {
-                        state.status = UpdateStatus::Idle;
-                    }
-                    Err(_) => {
-                        state.status = UpdateStatus::Idle;
-                    }
-                }
-
-                state.update_pid = None;
-                let _ = state.save();
-            }
         }
-
-        state
     }
 
-    fn is_process_running(pid: u32) -> bool {
-        #[cfg(unix)]
-        {
-            use std::process::Command;
-            if let Ok(output) = Command::new("ps").arg("-p").arg(pid.to_string()).output() {
-                output.status.success()
-            } else {
-                false
-            }
+    /// Query the npm registry and save the result; run by the background refresher
+    pub fn refresh() {
+        let mut state = Self::load_state();
+        if !state.should_check_update() {
+            return;
         }
 
-        #[cfg(windows)]
-        {
-            use std::process::Command;
-            if let Ok(output) = Command::new("tasklist")
-                .arg("/FI")
-                .arg(&format!("PID eq {}", pid))
-                .output()
-            {
-                String::from_utf8_lossy(&output.stdout).contains(&pid.to_string())
-            } else {
-                false
+        state.last_check = Some(Utc::now());
+        let _ = state.save();
+
+        match registry::check_for_updates() {
+            Ok(Some(version)) => {
+                state.status = UpdateStatus::Ready {
+                    version: version.clone(),
+                    found_at: Utc::now(),
+                };
+                state.latest_version = Some(version);
+            }
+            Ok(None) => {
+                state.status = UpdateStatus::Idle;
+            }
+            Err(_) => {
+                state.status = UpdateStatus::Idle;
             }
         }
 
-        #[cfg(not(any(unix, windows)))]
-        false
+        let _ = state.save();
     }
 
     pub fn save(&self) -> Result<(), std::io::Error> {
-        let config_dir = dirs::home_dir()
-            .unwrap_or_default()
-            .join(".claude")
-            .join("ccline");
+        let config_dir = paths::state_dir();
 
         std::fs::create_dir_all(&config_dir)?;
         let state_file = config_dir.join(".update_state.json");
 
         let content = serde_json::to_string_pretty(self)?;
-        std::fs::write(&state_file, content)?;
+        lock::write_atomic(&state_file, content.as_bytes())?;
 
         Ok(())
     }
@@ -157,7 +125,12 @@ mod registry {
     pub fn check_for_updates() -> Result<Option<String>, Box<dyn std::error::Error>> {
         let url = "https://registry.npmjs.org/@cometix/ccline/latest";
 
-        let response = ureq::get(url).header("Accept", "application/json").call()?;
+        let response = ureq::get(url)
+            .header("Accept", "application/json")
+            .config()
+            .timeout_global(Some(std::time::Duration::from_secs(10)))
+            .build()
+            .call()?;
 
         let data: serde_json::Value = response.into_body().read_json()?;
         let latest = data["version"].as_str().ok_or("Missing version field")?;
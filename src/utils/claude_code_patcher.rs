@@ -36,8 +36,12 @@ impl ClaudeCodePatcher {
     /// Get the version of Claude Code from the file header
     /// Format: // Version: X.Y.Z
     pub fn get_version(&self) -> Option<(u32, u32, u32)> {
-        // Look for "// Version: X.Y.Z" in the first 500 bytes
-        let header = &self.file_content[..std::cmp::min(500, self.file_content.len())];
+        Self::parse_version_header(&self.file_content)
+    }
+
+    /// Parse the `// Version: X.Y.Z` header from the first 500 bytes of cli.js
+    pub fn parse_version_header(content: &str) -> Option<(u32, u32, u32)> {
+        let header = content.get(..500.min(content.len())).unwrap_or(content);
 
         for line in header.lines() {
             if line.starts_with("// Version:") {
@@ -802,7 +806,7 @@ impl ClaudeCodePatcher {
         }
 
         // Sort patches by position descending (apply from end to start to avoid offset issues)
-        patches.sort_by(|a, b| b.location.start_index.cmp(&a.location.start_index));
+        patches.sort_by_key(|p| std::cmp::Reverse(p.location.start_index));
 
         // Apply all patches in one pass
         for patch in patches {
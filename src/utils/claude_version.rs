@@ -0,0 +1,162 @@
+use super::process::output_with_timeout;
+use super::ClaudeCodePatcher;
+use super::{lock, paths};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How long a detected version is trusted before looking again
+const CACHE_TTL_HOURS: i64 = 24;
+/// Retry sooner when no installation was found
+const MISS_TTL_HOURS: i64 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct VersionCache {
+    version: Option<String>,
+    checked_at: DateTime<Utc>,
+}
+
+/// Version of the locally installed Claude Code, e.g. `2.0.14`.
+///
+/// Looked up from the installation on disk, falling back to `claude --version`,
+/// and cached in `.claude_code_version.json` in the cache directory.
+pub fn installed_version() -> Option<String> {
+    let cache_path = cache_path();
+
+    if let Some(cache) = std::fs::read_to_string(&cache_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<VersionCache>(&content).ok())
+    {
+        let ttl = if cache.version.is_some() {
+            CACHE_TTL_HOURS
+        } else {
+            MISS_TTL_HOURS
+        };
+        if Utc::now().signed_duration_since(cache.checked_at) < Duration::hours(ttl) {
+            return cache.version;
+        }
+    }
+
+    let version = detect_version();
+    let cache = VersionCache {
+        version: version.clone(),
+        checked_at: Utc::now(),
+    };
+    if let Ok(json) = serde_json::to_string_pretty(&cache) {
+        let _ = lock::write_atomic(&cache_path, json.as_bytes());
+    }
+    version
+}
+
+fn cache_path() -> PathBuf {
+    paths::cache_dir().join(".claude_code_version.json")
+}
+
+fn detect_version() -> Option<String> {
+    find_executable()
+        .and_then(|path| version_from_install(&path))
+        .or_else(version_from_local_install)
+        .or_else(version_from_cli)
+}
+
+/// The `claude` executable on PATH, with symlinks resolved
+fn find_executable() -> Option<PathBuf> {
+    let names: &[&str] = if cfg!(windows) {
+        &["claude.cmd", "claude.exe", "claude"]
+    } else {
+        &["claude"]
+    };
+
+    let path_var = std::env::var_os("PATH")?;
+    std::env::split_paths(&path_var)
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|candidate| candidate.is_file())
+        .map(|candidate| std::fs::canonicalize(&candidate).unwrap_or(candidate))
+}
+
+/// Read the version from an npm installation (cli.js header or package.json)
+/// or from a native installation whose file name is the version
+fn version_from_install(executable: &Path) -> Option<String> {
+    if executable.extension().is_some_and(|ext| ext == "js") {
+        if let Some(version) = version_from_cli_js(executable) {
+            return Some(version);
+        }
+        if let Some(version) = executable
+            .parent()
+            .and_then(|dir| version_from_package_json(&dir.join("package.json")))
+        {
+            return Some(version);
+        }
+    }
+
+    // Windows npm shims sit next to the package's node_modules directory
+    if let Some(version) = executable.parent().and_then(|dir| {
+        version_from_package_json(
+            &dir.join("node_modules")
+                .join("@anthropic-ai")
+                .join("claude-code")
+                .join("package.json"),
+        )
+    }) {
+        return Some(version);
+    }
+
+    // Native installs keep each release under `versions/<version>`
+    let file_name = executable.file_name()?.to_str()?;
+    semver::Version::parse(file_name)
+        .ok()
+        .map(|version| version.to_string())
+}
+
+/// `claude migrate-installer` puts a local npm install under `~/.claude/local`
+fn version_from_local_install() -> Option<String> {
+    let package_json = paths::default_claude_dir()
+        .join("local")
+        .join("node_modules")
+        .join("@anthropic-ai")
+        .join("claude-code")
+        .join("package.json");
+    version_from_package_json(&package_json)
+}
+
+fn version_from_cli_js(path: &Path) -> Option<String> {
+    let mut header = Vec::with_capacity(500);
+    std::fs::File::open(path)
+        .ok()?
+        .take(500)
+        .read_to_end(&mut header)
+        .ok()?;
+
+    let (major, minor, patch) =
+        ClaudeCodePatcher::parse_version_header(&String::from_utf8_lossy(&header))?;
+    Some(format!("{}.{}.{}", major, minor, patch))
+}
+
+fn version_from_package_json(path: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let package: serde_json::Value = serde_json::from_str(&content).ok()?;
+    package
+        .get("version")?
+        .as_str()
+        .map(|version| version.to_string())
+}
+
+/// Ask the CLI itself; prints e.g. `2.0.14 (Claude Code)`
+fn version_from_cli() -> Option<String> {
+    let output = output_with_timeout(
+        Command::new("claude").arg("--version"),
+        None,
+        std::time::Duration::from_secs(2),
+    )?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = stdout.split_whitespace().next()?;
+    semver::Version::parse(version)
+        .ok()
+        .map(|version| version.to_string())
+}
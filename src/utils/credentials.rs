@@ -1,5 +1,16 @@
+use super::paths;
+use super::process::output_with_timeout;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+/// Environment variable holding a long-lived token, as created by `claude setup-token`
+const TOKEN_ENV_VAR: &str = "CLAUDE_CODE_OAUTH_TOKEN";
+/// Keychain / Secret Service entry Claude Code stores its credentials under
+const SECRET_SERVICE_NAME: &str = "Claude Code-credentials";
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
 
 #[derive(Debug, Deserialize, Serialize)]
 struct OAuthCredentials {
@@ -20,79 +31,280 @@ struct CredentialsFile {
     claude_ai_oauth: Option<OAuthCredentials>,
 }
 
-pub fn get_oauth_token() -> Option<String> {
-    if cfg!(target_os = "macos") {
-        get_oauth_token_macos()
-    } else {
-        get_oauth_token_file()
-    }
-}
-
-fn get_oauth_token_macos() -> Option<String> {
-    use std::process::Command;
-
-    let user = std::env::var("USER").unwrap_or_else(|_| "user".to_string());
-
-    let output = Command::new("security")
-        .args([
-            "find-generic-password",
-            "-a",
-            &user,
-            "-w",
-            "-s",
-            "Claude Code-credentials",
-        ])
-        .output();
-
-    match output {
-        Ok(output) if output.status.success() => {
-            let json_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if !json_str.is_empty() {
-                if let Ok(creds_file) = serde_json::from_str::<CredentialsFile>(&json_str) {
-                    return creds_file.claude_ai_oauth.map(|oauth| oauth.access_token);
+/// An OAuth access token and where it came from
+#[derive(Debug, Clone)]
+pub struct OAuthToken {
+    pub access_token: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Name of the provider that supplied the token
+    pub source: &'static str,
+}
+
+impl OAuthToken {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+
+    fn from_credentials(oauth: OAuthCredentials, source: &'static str) -> Self {
+        Self {
+            access_token: oauth.access_token,
+            // `expiresAt` is in milliseconds since the epoch
+            expires_at: oauth
+                .expires_at
+                .and_then(|ms| DateTime::from_timestamp_millis(ms as i64)),
+            source,
+        }
+    }
+
+    /// Parse the credentials JSON Claude Code stores, or a bare token
+    fn parse(content: &str, source: &'static str) -> Option<Self> {
+        let content = content.trim();
+        if content.is_empty() {
+            return None;
+        }
+
+        if content.starts_with('{') {
+            if let Ok(creds_file) = serde_json::from_str::<CredentialsFile>(content) {
+                if let Some(oauth) = creds_file.claude_ai_oauth {
+                    return Some(Self::from_credentials(oauth, source));
                 }
             }
-            None
+            // Also accept the inner object on its own
+            return serde_json::from_str::<OAuthCredentials>(content)
+                .ok()
+                .map(|oauth| Self::from_credentials(oauth, source));
         }
-        _ => {
-            // Fallback to file-based credentials
-            get_oauth_token_file()
+
+        let token = content.lines().next()?.trim();
+        Some(Self {
+            access_token: token.to_string(),
+            expires_at: None,
+            source,
+        })
+    }
+}
+
+/// A place OAuth credentials can be read from
+pub trait CredentialProvider {
+    fn name(&self) -> &'static str;
+    fn load(&self) -> Option<OAuthToken>;
+}
+
+/// Token from an environment variable
+struct EnvProvider;
+
+impl CredentialProvider for EnvProvider {
+    fn name(&self) -> &'static str {
+        "env"
+    }
+
+    fn load(&self) -> Option<OAuthToken> {
+        let token = std::env::var(TOKEN_ENV_VAR).ok()?;
+        OAuthToken::parse(&token, self.name())
+    }
+}
+
+/// User-configured command that prints a token or credentials JSON on stdout
+struct HelperProvider {
+    command: String,
+}
+
+impl CredentialProvider for HelperProvider {
+    fn name(&self) -> &'static str {
+        "helper"
+    }
+
+    fn load(&self) -> Option<OAuthToken> {
+        let mut cmd = if cfg!(windows) {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", &self.command]);
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", &self.command]);
+            cmd
+        };
+
+        let output = output_with_timeout(&mut cmd, None, COMMAND_TIMEOUT)?;
+        if !output.status.success() {
+            return None;
         }
+        OAuthToken::parse(&String::from_utf8_lossy(&output.stdout), self.name())
     }
 }
 
-fn get_oauth_token_file() -> Option<String> {
-    // Try CLAUDE_CONFIG_DIR first if set (respects explicit user configuration)
-    if let Ok(config_dir) = std::env::var("CLAUDE_CONFIG_DIR") {
-        let config_path = PathBuf::from(config_dir).join(".credentials.json");
-        if let Some(token) = read_token_from_path(&config_path) {
-            return Some(token);
+/// A `.credentials.json` file
+struct FileProvider {
+    name: &'static str,
+    path: Option<PathBuf>,
+}
+
+impl FileProvider {
+    /// `$CLAUDE_CONFIG_DIR/.credentials.json`
+    fn config_dir() -> Self {
+        Self {
+            name: "config_dir",
+            path: paths::claude_config_dir_env().map(|dir| dir.join(".credentials.json")),
         }
     }
 
-    // Fall back to default ~/.claude/.credentials.json
-    if let Some(default_path) = get_credentials_path() {
-        if let Some(token) = read_token_from_path(&default_path) {
-            return Some(token);
+    /// `~/.claude/.credentials.json`
+    fn default_path() -> Self {
+        Self {
+            name: "file",
+            path: Some(paths::default_claude_dir().join(".credentials.json")),
         }
     }
+}
 
-    None
+impl CredentialProvider for FileProvider {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn load(&self) -> Option<OAuthToken> {
+        let content = std::fs::read_to_string(self.path.as_ref()?).ok()?;
+        let creds_file: CredentialsFile = serde_json::from_str(&content).ok()?;
+        creds_file
+            .claude_ai_oauth
+            .map(|oauth| OAuthToken::from_credentials(oauth, self.name))
+    }
 }
 
-fn get_credentials_path() -> Option<PathBuf> {
-    let home = dirs::home_dir()?;
-    Some(home.join(".claude").join(".credentials.json"))
+/// macOS login keychain, via the `security` tool
+struct KeychainProvider;
+
+impl CredentialProvider for KeychainProvider {
+    fn name(&self) -> &'static str {
+        "keychain"
+    }
+
+    fn load(&self) -> Option<OAuthToken> {
+        let user = std::env::var("USER").unwrap_or_else(|_| "user".to_string());
+        let output = output_with_timeout(
+            Command::new("security").args([
+                "find-generic-password",
+                "-a",
+                &user,
+                "-w",
+                "-s",
+                SECRET_SERVICE_NAME,
+            ]),
+            None,
+            COMMAND_TIMEOUT,
+        )?;
+        if !output.status.success() {
+            return None;
+        }
+        OAuthToken::parse(&String::from_utf8_lossy(&output.stdout), self.name())
+    }
 }
 
-/// Read OAuth token from a credentials file path
-fn read_token_from_path(path: &PathBuf) -> Option<String> {
-    if !path.exists() {
-        return None;
+/// Linux Secret Service (GNOME Keyring, KWallet), queried over D-Bus through
+/// libsecret's `secret-tool` rather than linking a D-Bus client
+struct SecretServiceProvider;
+
+impl CredentialProvider for SecretServiceProvider {
+    fn name(&self) -> &'static str {
+        "secret_service"
     }
 
-    let content = std::fs::read_to_string(path).ok()?;
-    let creds_file: CredentialsFile = serde_json::from_str(&content).ok()?;
+    fn load(&self) -> Option<OAuthToken> {
+        let output = output_with_timeout(
+            Command::new("secret-tool").args(["lookup", "service", SECRET_SERVICE_NAME]),
+            None,
+            COMMAND_TIMEOUT,
+        )?;
+        if !output.status.success() {
+            return None;
+        }
+        OAuthToken::parse(&String::from_utf8_lossy(&output.stdout), self.name())
+    }
+}
+
+/// Providers in lookup order. Explicit sources come first, then the platform
+/// store Claude Code itself uses, then the other fallbacks.
+fn providers(helper: Option<&str>) -> Vec<Box<dyn CredentialProvider>> {
+    let mut providers: Vec<Box<dyn CredentialProvider>> = vec![Box::new(EnvProvider)];
+    // A dry run doesn't run commands; the other providers only read
+    let helper = helper.filter(|_| !super::dry_run::is_enabled());
+    if let Some(command) = helper.filter(|command| !command.trim().is_empty()) {
+        providers.push(Box::new(HelperProvider {
+            command: command.to_string(),
+        }));
+    }
 
-    creds_file.claude_ai_oauth.map(|oauth| oauth.access_token)
+    if cfg!(target_os = "macos") {
+        providers.push(Box::new(KeychainProvider));
+    }
+    providers.push(Box::new(FileProvider::config_dir()));
+    providers.push(Box::new(FileProvider::default_path()));
+    if cfg!(target_os = "linux") {
+        providers.push(Box::new(SecretServiceProvider));
+    }
+    providers
+}
+
+/// First unexpired token from the providers, or the first expired one if
+/// that's all there is, so callers can tell "expired" from "not logged in".
+///
+/// `helper` is an optional shell command printing a token or credentials JSON.
+pub fn load_token(helper: Option<&str>) -> Option<OAuthToken> {
+    let mut expired = None;
+    for provider in providers(helper) {
+        if let Some(token) = provider.load() {
+            if !token.is_expired() {
+                return Some(token);
+            }
+            expired.get_or_insert(token);
+        }
+    }
+    expired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_credentials_file_format() {
+        let token = OAuthToken::parse(
+            r#"{"claudeAiOauth": {"accessToken": "sk-ant-oat01-abc", "refreshToken": "r",
+                "expiresAt": 1767225600000, "scopes": ["user:inference"]}}"#,
+            "test",
+        )
+        .unwrap();
+        assert_eq!(token.access_token, "sk-ant-oat01-abc");
+        assert_eq!(token.source, "test");
+        // `expiresAt` is in milliseconds
+        assert_eq!(
+            token.expires_at.map(|expires_at| expires_at.to_rfc3339()),
+            Some("2026-01-01T00:00:00+00:00".to_string())
+        );
+        assert!(token.is_expired());
+    }
+
+    #[test]
+    fn parses_the_inner_object_on_its_own() {
+        let token = OAuthToken::parse(r#"  {"accessToken": "abc"}  "#, "test").unwrap();
+        assert_eq!(token.access_token, "abc");
+        assert_eq!(token.expires_at, None);
+        assert!(!token.is_expired());
+    }
+
+    #[test]
+    fn parses_a_bare_token() {
+        let token = OAuthToken::parse("sk-ant-oat01-abc\nignored\n", "test").unwrap();
+        assert_eq!(token.access_token, "sk-ant-oat01-abc");
+        assert_eq!(token.expires_at, None);
+    }
+
+    #[test]
+    fn rejects_empty_or_unrecognized_input() {
+        assert!(OAuthToken::parse("", "test").is_none());
+        assert!(OAuthToken::parse(" \n\t", "test").is_none());
+        assert!(OAuthToken::parse(r#"{"claudeAiOauth": null}"#, "test").is_none());
+        assert!(OAuthToken::parse(r#"{"token": "abc"}"#, "test").is_none());
+    }
 }
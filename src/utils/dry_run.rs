@@ -0,0 +1,15 @@
+//! `ccline render --dry-run`: render from the fixture and whatever is already
+//! cached, without running commands, writing caches or starting refreshes.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Skip side effects for the rest of the process
+pub fn enable() {
+    DRY_RUN.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
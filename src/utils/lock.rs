@@ -0,0 +1,164 @@
+use crate::utils::process;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Locks whose holder has exited are stale. So are locks older than this, in
+/// case the holder's pid was reused, and pid-less locks older than
+/// [`UNWRITTEN_AFTER`].
+const STALE_AFTER: Duration = Duration::from_secs(600);
+
+/// A lock file is created before the pid is written into it; give the
+/// holder this long to write it
+const UNWRITTEN_AFTER: Duration = Duration::from_secs(10);
+
+/// Advisory lock held by creating a file exclusively; released on drop.
+///
+/// Used to serialize read-modify-write updates to shared state files when
+/// several statusline processes refresh at the same time.
+pub struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Try to take the lock, retrying until `timeout`. Returns `None` if another
+    /// process still holds it.
+    pub fn acquire(path: &Path, timeout: Duration) -> Option<Self> {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+
+        let deadline = Instant::now() + timeout;
+        // Holder already found alive, so `ps` isn't run on every retry
+        let mut live_holder = None;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(mut file) => {
+                    let _ = write!(file, "{}", std::process::id());
+                    return Some(Self {
+                        path: path.to_path_buf(),
+                    });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let holder = Self::holder(path);
+                    if holder.is_none() || holder != live_holder {
+                        if Self::is_stale(path, holder) {
+                            Self::break_lock(path, holder);
+                            continue;
+                        }
+                        live_holder = holder;
+                    }
+                }
+                Err(_) => return None,
+            }
+
+            if Instant::now() >= deadline {
+                return None;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn holder(path: &Path) -> Option<u32> {
+        std::fs::read_to_string(path).ok()?.trim().parse().ok()
+    }
+
+    fn is_stale(path: &Path, holder: Option<u32>) -> bool {
+        let age = std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .unwrap_or_default();
+        match holder {
+            Some(pid) => age > STALE_AFTER || !process::is_running(pid),
+            None => age > UNWRITTEN_AFTER,
+        }
+    }
+
+    /// Move a stale lock aside rather than unlinking the shared path, so a
+    /// waiter that raced us here can't delete the lock we create next. If the
+    /// file moved turns out to be a fresh lock, it's put back.
+    fn break_lock(path: &Path, stale_holder: Option<u32>) {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let aside = path.with_file_name(format!(
+            ".{}.{}.{}.stale",
+            file_name,
+            std::process::id(),
+            nanos
+        ));
+
+        if std::fs::rename(path, &aside).is_err() {
+            return;
+        }
+        if Self::holder(&aside) != stale_holder {
+            // `hard_link` fails rather than replacing a lock taken meanwhile
+            let _ = std::fs::hard_link(&aside, path);
+        }
+        let _ = std::fs::remove_file(&aside);
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Write a file via a temporary file and rename, so readers never see it half written
+pub fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // Unique per call, so threads writing the same file don't share a temp file
+    static NEXT_TMP: AtomicU64 = AtomicU64::new(0);
+    let tmp_path = path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        file_name,
+        std::process::id(),
+        NEXT_TMP.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    std::fs::write(&tmp_path, content)?;
+    std::fs::rename(&tmp_path, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp_path);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threads_writing_one_file_do_not_collide() {
+        let path = std::env::temp_dir().join(format!("ccline-atomic-{}.json", std::process::id()));
+        let writers: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    (0..20).all(|_| write_atomic(&path, format!("writer {}", i).as_bytes()).is_ok())
+                })
+            })
+            .collect();
+        for writer in writers {
+            assert!(writer.join().unwrap());
+        }
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("writer "), "{}", content);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,4 +1,10 @@
 pub mod claude_code_patcher;
+pub mod claude_version;
 pub mod credentials;
+pub mod dry_run;
+pub mod lock;
+pub mod paths;
+pub mod process;
+pub mod transcript;
 
 pub use claude_code_patcher::{ClaudeCodePatcher, LocationResult};
@@ -0,0 +1,263 @@
+//! Where ccline and Claude Code keep their files.
+//!
+//! ccline's own files live in one directory per kind:
+//!
+//! - config (`config.toml`, `models.toml`, themes)
+//! - cache (API and transcript caches, safe to delete)
+//! - state (spend ledger, update state, refresh locks)
+//!
+//! Each is resolved in this order:
+//!
+//! 1. `--config-dir <dir>`, for all three kinds
+//! 2. `$CLAUDE_CONFIG_DIR/ccline`, so every Claude Code profile gets its own files
+//! 3. the XDG directory: `$XDG_CONFIG_HOME/ccline` or `$XDG_STATE_HOME/ccline`
+//!    if it exists, and `$XDG_CACHE_HOME/ccline` whenever `XDG_CACHE_HOME` is set.
+//!    The XDG defaults (`~/.config`, `~/.local/state`) apply on every platform,
+//!    macOS included.
+//! 4. `~/.claude/ccline`
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+static CONFIG_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+const APP_DIR: &str = "ccline";
+
+/// Use `dir` for config, cache and state, as given by `--config-dir`.
+/// Only the first call has any effect.
+pub fn set_config_dir(dir: PathBuf) {
+    let dir = std::path::absolute(&dir).unwrap_or(dir);
+    let _ = CONFIG_DIR_OVERRIDE.set(dir);
+}
+
+/// Point config, cache and state at one temp directory shared by every test
+/// in the process, since the override can only be set once
+#[cfg(test)]
+pub fn use_test_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ccline-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    set_config_dir(dir);
+    config_dir_override().unwrap().clone()
+}
+
+/// The `--config-dir` override, if one was given
+pub fn config_dir_override() -> Option<&'static PathBuf> {
+    CONFIG_DIR_OVERRIDE.get()
+}
+
+/// `~/.claude`, regardless of `CLAUDE_CONFIG_DIR`
+pub fn default_claude_dir() -> PathBuf {
+    match dirs::home_dir() {
+        Some(home) => home.join(".claude"),
+        None => PathBuf::from(".claude"),
+    }
+}
+
+/// `$CLAUDE_CONFIG_DIR`, if set to something
+pub fn claude_config_dir_env() -> Option<PathBuf> {
+    std::env::var_os("CLAUDE_CONFIG_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Claude Code's config directory: `$CLAUDE_CONFIG_DIR`, else `~/.claude`
+pub fn claude_dir() -> PathBuf {
+    claude_config_dir_env().unwrap_or_else(default_claude_dir)
+}
+
+/// Claude Code's `settings.json`
+pub fn claude_settings_file() -> PathBuf {
+    claude_dir().join("settings.json")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Config,
+    Cache,
+    State,
+}
+
+/// Directory of `kind` given the `--config-dir` override, an environment
+/// lookup returning non-empty values only, and the home directory
+fn resolve(
+    kind: Kind,
+    config_dir_override: Option<&Path>,
+    var: &dyn Fn(&str) -> Option<PathBuf>,
+    home: Option<&Path>,
+) -> PathBuf {
+    if let Some(dir) = config_dir_override {
+        return dir.to_path_buf();
+    }
+    if let Some(dir) = var("CLAUDE_CONFIG_DIR") {
+        return dir.join(APP_DIR);
+    }
+
+    // Relative XDG paths are invalid per the spec and ignored
+    let xdg = |name: &str| var(name).filter(|dir| dir.is_absolute());
+    let xdg_or_default = |name: &str, default: &str| {
+        xdg(name)
+            .or_else(|| home.map(|home| home.join(default)))
+            .map(|dir| dir.join(APP_DIR))
+    };
+
+    match kind {
+        Kind::Config => xdg_or_default("XDG_CONFIG_HOME", ".config")
+            .filter(|dir| dir.is_dir())
+            .unwrap_or_else(|| home.unwrap_or(Path::new("")).join(".claude").join(APP_DIR)),
+        Kind::Cache => xdg("XDG_CACHE_HOME")
+            .map(|dir| dir.join(APP_DIR))
+            .unwrap_or_else(|| resolve(Kind::Config, None, var, home)),
+        Kind::State => xdg_or_default("XDG_STATE_HOME", ".local/state")
+            .filter(|dir| dir.is_dir())
+            .unwrap_or_else(|| resolve(Kind::Config, None, var, home)),
+    }
+}
+
+fn resolve_from_env(kind: Kind) -> PathBuf {
+    let var = |name: &str| {
+        std::env::var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+    resolve(
+        kind,
+        CONFIG_DIR_OVERRIDE.get().map(PathBuf::as_path),
+        &var,
+        dirs::home_dir().as_deref(),
+    )
+}
+
+/// Directory for `config.toml`, `models.toml` and themes
+pub fn config_dir() -> PathBuf {
+    resolve_from_env(Kind::Config)
+}
+
+/// Directory for disposable caches
+pub fn cache_dir() -> PathBuf {
+    resolve_from_env(Kind::Cache)
+}
+
+/// Directory for state that should survive cache clears, like the spend ledger
+pub fn state_dir() -> PathBuf {
+    resolve_from_env(Kind::State)
+}
+
+pub fn config_file() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+pub fn models_file() -> PathBuf {
+    config_dir().join("models.toml")
+}
+
+pub fn themes_dir() -> PathBuf {
+    config_dir().join("themes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// A home directory with `dirs` created under it
+    fn temp_home(name: &str, dirs: &[&str]) -> PathBuf {
+        let home =
+            std::env::temp_dir().join(format!("ccline-paths-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&home);
+        std::fs::create_dir_all(&home).unwrap();
+        for dir in dirs {
+            std::fs::create_dir_all(home.join(dir)).unwrap();
+        }
+        home
+    }
+
+    fn resolve_with(
+        kind: Kind,
+        over: Option<&Path>,
+        env: &[(&str, PathBuf)],
+        home: &Path,
+    ) -> PathBuf {
+        let env: HashMap<&str, PathBuf> = env.iter().cloned().collect();
+        resolve(kind, over, &|name| env.get(name).cloned(), Some(home))
+    }
+
+    #[test]
+    fn config_dir_override_wins_over_everything() {
+        let home = temp_home("override", &[".config/ccline", ".local/state/ccline"]);
+        let over = home.join("custom");
+        let env = [
+            ("CLAUDE_CONFIG_DIR", home.join("profile")),
+            ("XDG_CACHE_HOME", home.join("cache")),
+        ];
+        for kind in [Kind::Config, Kind::Cache, Kind::State] {
+            assert_eq!(resolve_with(kind, Some(&over), &env, &home), over);
+        }
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn claude_config_dir_comes_before_xdg() {
+        let home = temp_home("profile", &[".config/ccline"]);
+        let env = [
+            ("CLAUDE_CONFIG_DIR", home.join("profile")),
+            ("XDG_CACHE_HOME", home.join("cache")),
+        ];
+        for kind in [Kind::Config, Kind::Cache, Kind::State] {
+            assert_eq!(
+                resolve_with(kind, None, &env, &home),
+                home.join("profile/ccline")
+            );
+        }
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn xdg_directories_are_used_once_they_exist() {
+        let home = temp_home("xdg", &[".config/ccline", "state/ccline"]);
+        let env = [
+            ("XDG_STATE_HOME", home.join("state")),
+            ("XDG_CACHE_HOME", home.join("cache")),
+        ];
+        assert_eq!(
+            resolve_with(Kind::Config, None, &env, &home),
+            home.join(".config/ccline")
+        );
+        assert_eq!(
+            resolve_with(Kind::State, None, &env, &home),
+            home.join("state/ccline")
+        );
+        // The cache dir is used whenever XDG_CACHE_HOME is set, since it's disposable
+        assert_eq!(
+            resolve_with(Kind::Cache, None, &env, &home),
+            home.join("cache/ccline")
+        );
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn falls_back_to_the_claude_dir() {
+        let home = temp_home("default", &[]);
+        // Relative XDG paths are ignored
+        let env = [
+            ("XDG_CONFIG_HOME", home.join("config")),
+            ("XDG_CACHE_HOME", PathBuf::from("relative/cache")),
+        ];
+        for kind in [Kind::Config, Kind::Cache, Kind::State] {
+            assert_eq!(
+                resolve_with(kind, None, &env, &home),
+                home.join(".claude/ccline")
+            );
+        }
+
+        // Without XDG_STATE_HOME, cache and state follow the config dir
+        let home_with_config = temp_home("config-only", &[".config/ccline"]);
+        for kind in [Kind::Cache, Kind::State] {
+            assert_eq!(
+                resolve_with(kind, None, &[], &home_with_config),
+                home_with_config.join(".config/ccline")
+            );
+        }
+        std::fs::remove_dir_all(home).unwrap();
+        std::fs::remove_dir_all(home_with_config).unwrap();
+    }
+}
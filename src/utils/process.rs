@@ -0,0 +1,156 @@
+use std::io::{Read, Write};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Run a command, killing it if it hasn't exited within `timeout`.
+///
+/// `stdin` is written to the child before waiting. Stdout is drained on a
+/// separate thread so a chatty command can't block on a full pipe; stderr is
+/// discarded. Returns `None` if the command can't be spawned or times out.
+///
+/// On unix the command runs in its own process group, and a timeout kills
+/// the whole group, including anything it started in the background.
+pub fn output_with_timeout(
+    cmd: &mut Command,
+    stdin: Option<&[u8]>,
+    timeout: Duration,
+) -> Option<Output> {
+    let deadline = Instant::now() + timeout;
+    cmd.stdin(if stdin.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    })
+    .stdout(Stdio::piped())
+    .stderr(Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    let mut child = cmd.spawn().ok()?;
+
+    // Write input and close stdin so the command sees EOF
+    if let (Some(input), Some(mut child_stdin)) = (stdin, child.stdin.take()) {
+        let _ = child_stdin.write_all(input);
+    }
+
+    let mut stdout = child.stdout.take()?;
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stdout.read_to_end(&mut buf);
+        let _ = sender.send(buf);
+    });
+
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(2));
+            }
+            _ => {
+                kill(&mut child);
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+
+    // Something the command left running in the background can keep stdout
+    // open after the command itself has exited
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    let Ok(stdout) = receiver.recv_timeout(remaining) else {
+        kill(&mut child);
+        return None;
+    };
+
+    Some(Output {
+        status,
+        stdout,
+        stderr: Vec::new(),
+    })
+}
+
+/// Kill the child's process group, or just the child where there are none
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        // The group id is the child's pid, see `process_group(0)` above
+        if let Ok(pgid) = libc::pid_t::try_from(child.id()) {
+            // SAFETY: kill has no memory-safety preconditions
+            unsafe {
+                libc::kill(-pgid, libc::SIGKILL);
+            }
+        }
+    }
+    let _ = child.kill();
+}
+
+/// Whether a process with this pid is still alive
+pub fn is_running(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        if let Ok(output) = Command::new("ps").arg("-p").arg(pid.to_string()).output() {
+            output.status.success()
+        } else {
+            false
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        if let Ok(output) = Command::new("tasklist")
+            .arg("/FI")
+            .arg(format!("PID eq {}", pid))
+            .output()
+        {
+            String::from_utf8_lossy(&output.stdout).contains(&pid.to_string())
+        } else {
+            false
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    false
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", script]);
+        cmd
+    }
+
+    #[test]
+    fn returns_stdout_and_stdin_round_trip() {
+        let output =
+            output_with_timeout(&mut sh("cat"), Some(b"hello"), Duration::from_secs(5)).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"hello");
+    }
+
+    #[test]
+    fn times_out_slow_commands() {
+        let started = Instant::now();
+        let output = output_with_timeout(&mut sh("sleep 5"), None, Duration::from_millis(100));
+        assert!(output.is_none());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn background_process_holding_stdout_does_not_block() {
+        let started = Instant::now();
+        let output = output_with_timeout(
+            &mut sh("sleep 5 & echo started"),
+            None,
+            Duration::from_millis(200),
+        );
+        assert!(output.is_none());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,610 @@
+use crate::config::{is_long_context, NormalizedUsage, TranscriptEntry};
+use crate::utils::lock::{self, FileLock};
+use crate::utils::paths;
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs::{File, Metadata};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+const MAX_CACHE_ENTRIES: usize = 32;
+/// Message ids remembered across parses, enough to span interleaved streaming chunks
+const RECENT_MESSAGE_IDS: usize = 128;
+/// Cache file used before the context and usage caches were split
+const LEGACY_CACHE_FILE: &str = ".transcript_cache.json";
+/// How long to wait for another process saving the same cache
+const SAVE_LOCK_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Reads the lines of a file from the end backwards, stopping at `floor`.
+///
+/// Only the chunks needed to reach the requested lines are read, so finding
+/// the most recent entry of a large JSONL transcript costs about the size of
+/// that entry rather than the size of the file.
+pub struct ReverseLines {
+    file: File,
+    floor: u64,
+    pos: u64,
+    /// Bytes read but not yet yielded, ending where the last yielded line began
+    pending: Vec<u8>,
+}
+
+impl ReverseLines {
+    /// Iterate the lines in `floor..end`, newest first. `floor` must be at a line start.
+    pub fn new(file: File, floor: u64, end: u64) -> Self {
+        Self {
+            file,
+            floor,
+            pos: end.max(floor),
+            pending: Vec::new(),
+        }
+    }
+
+    fn read_chunk(&mut self) -> bool {
+        if self.pos <= self.floor {
+            return false;
+        }
+
+        let len = (self.pos - self.floor).min(CHUNK_SIZE as u64) as usize;
+        let start = self.pos - len as u64;
+        let mut chunk = vec![0; len];
+        if self.file.seek(SeekFrom::Start(start)).is_err()
+            || self.file.read_exact(&mut chunk).is_err()
+        {
+            self.pos = self.floor;
+            return false;
+        }
+
+        chunk.extend_from_slice(&self.pending);
+        self.pending = chunk;
+        self.pos = start;
+        true
+    }
+}
+
+impl Iterator for ReverseLines {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if self.pending.is_empty() && !self.read_chunk() {
+            return None;
+        }
+
+        // Drop the newline that terminates this line
+        if self.pending.last() == Some(&b'\n') {
+            self.pending.pop();
+        }
+
+        loop {
+            if let Some(idx) = self.pending.iter().rposition(|&b| b == b'\n') {
+                let line = self.pending.split_off(idx + 1);
+                return Some(String::from_utf8_lossy(&line).into_owned());
+            }
+
+            if !self.read_chunk() {
+                let line = std::mem::take(&mut self.pending);
+                return Some(String::from_utf8_lossy(&line).into_owned());
+            }
+        }
+    }
+}
+
+/// What was learned from a transcript the last time it was parsed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TranscriptCacheEntry<T> {
+    pub inode: u64,
+    /// Byte offset just past the last complete line that was parsed
+    pub offset: u64,
+    pub value: T,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+/// On-disk cache of values parsed from transcripts, keyed by transcript path.
+///
+/// An entry is only reused while the file keeps the same inode and has not
+/// shrunk, so a rewritten transcript is parsed again from scratch. Each kind
+/// of value lives in its own file so segments never overwrite each other.
+#[derive(Debug)]
+pub struct TranscriptCache<T> {
+    file_name: &'static str,
+    entries: HashMap<String, TranscriptCacheEntry<T>>,
+    /// Keys inserted since loading, the only ones [`TranscriptCache::save`] writes
+    inserted: HashSet<String>,
+}
+
+impl<T: Serialize + DeserializeOwned> TranscriptCache<T> {
+    fn cache_path(file_name: &str) -> Option<PathBuf> {
+        Some(paths::cache_dir().join(file_name))
+    }
+
+    fn read_entries(file_name: &str) -> HashMap<String, TranscriptCacheEntry<T>> {
+        Self::cache_path(file_name)
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn load(file_name: &'static str) -> Self {
+        Self {
+            file_name,
+            entries: Self::read_entries(file_name),
+            inserted: HashSet::new(),
+        }
+    }
+
+    /// The entry for `path` if it is still valid for the file's current state
+    pub fn get(&self, path: &Path, metadata: &Metadata) -> Option<&TranscriptCacheEntry<T>> {
+        self.entries
+            .get(path.to_string_lossy().as_ref())
+            .filter(|entry| entry.inode == file_inode(metadata) && entry.offset <= metadata.len())
+    }
+
+    pub fn insert(&mut self, path: &Path, mut entry: TranscriptCacheEntry<T>) {
+        entry.updated_at = Utc::now().to_rfc3339();
+        let key = path.to_string_lossy().into_owned();
+        self.inserted.insert(key.clone());
+        self.entries.insert(key, entry);
+    }
+
+    /// Write the inserted entries over what's on disk now, keeping only the
+    /// most recently updated ones. Several statuslines save the same cache,
+    /// so this is done under a lock; if it can't be taken the entries are
+    /// dropped and simply parsed again next time.
+    pub fn save(&mut self) {
+        if super::dry_run::is_enabled() {
+            return;
+        }
+        let Some(cache_path) = Self::cache_path(self.file_name) else {
+            return;
+        };
+        let Some(_lock) =
+            FileLock::acquire(&cache_path.with_extension("json.lock"), SAVE_LOCK_TIMEOUT)
+        else {
+            return;
+        };
+
+        let mut entries = Self::read_entries(self.file_name);
+        for key in self.inserted.drain() {
+            if let Some(entry) = self.entries.remove(&key) {
+                entries.insert(key, entry);
+            }
+        }
+        self.entries = entries;
+
+        if self.entries.len() > MAX_CACHE_ENTRIES {
+            let mut by_age: Vec<(String, String)> = self
+                .entries
+                .iter()
+                .map(|(path, entry)| (entry.updated_at.clone(), path.clone()))
+                .collect();
+            by_age.sort();
+            for (_, path) in by_age.iter().take(self.entries.len() - MAX_CACHE_ENTRIES) {
+                self.entries.remove(path);
+            }
+        }
+
+        if let Ok(json) = serde_json::to_string(&self.entries) {
+            let _ = lock::write_atomic(&cache_path, json.as_bytes());
+        }
+
+        if let Some(legacy_path) = Self::cache_path(LEGACY_CACHE_FILE) {
+            let _ = std::fs::remove_file(legacy_path);
+        }
+    }
+}
+
+/// Token counts summed over a set of assistant messages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenTotals {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+    pub messages: u64,
+}
+
+impl TokenTotals {
+    pub fn add(&mut self, usage: &NormalizedUsage) {
+        self.input_tokens += usage.input_tokens as u64;
+        self.output_tokens += usage.output_tokens as u64;
+        self.cache_creation_input_tokens += usage.cache_creation_input_tokens as u64;
+        self.cache_read_input_tokens += usage.cache_read_input_tokens as u64;
+        self.messages += 1;
+    }
+
+    pub fn merge(&mut self, other: &TokenTotals) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+        self.messages += other.messages;
+    }
+
+    /// These totals less a subset of them
+    pub fn minus(&self, subset: &TokenTotals) -> TokenTotals {
+        TokenTotals {
+            input_tokens: self.input_tokens.saturating_sub(subset.input_tokens),
+            output_tokens: self.output_tokens.saturating_sub(subset.output_tokens),
+            cache_creation_input_tokens: self
+                .cache_creation_input_tokens
+                .saturating_sub(subset.cache_creation_input_tokens),
+            cache_read_input_tokens: self
+                .cache_read_input_tokens
+                .saturating_sub(subset.cache_read_input_tokens),
+            messages: self.messages.saturating_sub(subset.messages),
+        }
+    }
+
+    /// All prompt tokens, whether uncached, written to cache or read from it
+    pub fn total_input_tokens(&self) -> u64 {
+        self.input_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens
+    }
+
+    /// Share of prompt tokens served from cache, as a percentage
+    pub fn cache_hit_percent(&self) -> Option<f64> {
+        let total = self.total_input_tokens();
+        if total == 0 {
+            None
+        } else {
+            Some(self.cache_read_input_tokens as f64 / total as f64 * 100.0)
+        }
+    }
+}
+
+/// Token usage of a whole transcript, split by the model that produced each message
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TranscriptUsage {
+    pub by_model: BTreeMap<String, TokenTotals>,
+    /// The part of `by_model` from messages with a long context prompt (see
+    /// [`is_long_context`]), which some models bill at a higher rate
+    #[serde(default)]
+    pub long_context_by_model: BTreeMap<String, TokenTotals>,
+    /// Claude Code writes one line per content block, each repeating the
+    /// message's usage, and lines of parallel messages can interleave. Only
+    /// the first line of a message is counted.
+    #[serde(default)]
+    recent_message_ids: VecDeque<String>,
+}
+
+impl TranscriptUsage {
+    pub fn totals(&self) -> TokenTotals {
+        let mut totals = TokenTotals::default();
+        for model_totals in self.by_model.values() {
+            totals.merge(model_totals);
+        }
+        totals
+    }
+
+    fn add_line(&mut self, line: &str) {
+        let Ok(entry) = serde_json::from_str::<TranscriptEntry>(line) else {
+            return;
+        };
+        if entry.r#type.as_deref() != Some("assistant") {
+            return;
+        }
+        let Some(message) = entry.message else {
+            return;
+        };
+        let Some(usage) = message.usage else {
+            return;
+        };
+
+        if let Some(id) = message.id {
+            if self.recent_message_ids.contains(&id) {
+                return;
+            }
+            if self.recent_message_ids.len() == RECENT_MESSAGE_IDS {
+                self.recent_message_ids.pop_front();
+            }
+            self.recent_message_ids.push_back(id);
+        }
+
+        let model = message.model.unwrap_or_else(|| "unknown".to_string());
+        let usage = usage.normalize();
+        let prompt_tokens = usage.input_tokens as u64
+            + usage.cache_creation_input_tokens as u64
+            + usage.cache_read_input_tokens as u64;
+        if is_long_context(prompt_tokens) {
+            self.long_context_by_model
+                .entry(model.clone())
+                .or_default()
+                .add(&usage);
+        }
+        self.by_model.entry(model).or_default().add(&usage);
+    }
+}
+
+/// Sum token usage over every assistant message in the transcript.
+///
+/// Progress is cached, so each call only reads lines appended since the last.
+pub fn transcript_usage(path: &Path) -> Option<TranscriptUsage> {
+    let file = File::open(path).ok()?;
+    let metadata = file.metadata().ok()?;
+
+    let mut cache = TranscriptCache::<TranscriptUsage>::load(".transcript_usage_cache.json");
+    let (floor, mut usage) = cache
+        .get(path, &metadata)
+        .map(|entry| (entry.offset, entry.value.clone()))
+        .unwrap_or_default();
+
+    let end = complete_len(&file, metadata.len());
+    if end == floor {
+        return Some(usage);
+    }
+
+    let mut reader = BufReader::new(file);
+    reader.seek(SeekFrom::Start(floor)).ok()?;
+    for line in reader.take(end - floor).lines() {
+        match line {
+            Ok(line) => usage.add_line(line.trim()),
+            Err(_) => return Some(usage),
+        }
+    }
+
+    cache.insert(
+        path,
+        TranscriptCacheEntry {
+            inode: file_inode(&metadata),
+            offset: end,
+            value: usage.clone(),
+            updated_at: String::new(),
+        },
+    );
+    cache.save();
+
+    Some(usage)
+}
+
+#[cfg(unix)]
+pub fn file_inode(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+pub fn file_inode(_metadata: &Metadata) -> u64 {
+    0
+}
+
+/// Offset just past the last newline in the file, i.e. the end of the last
+/// complete line. A line still being written is left for the next parse.
+pub fn complete_len(mut file: &File, len: u64) -> u64 {
+    let mut end = len;
+    let mut buf = vec![0; CHUNK_SIZE];
+
+    while end > 0 {
+        let n = end.min(CHUNK_SIZE as u64) as usize;
+        let start = end - n as u64;
+        if file.seek(SeekFrom::Start(start)).is_err() || file.read_exact(&mut buf[..n]).is_err() {
+            return 0;
+        }
+        if let Some(idx) = buf[..n].iter().rposition(|&b| b == b'\n') {
+            return start + idx as u64 + 1;
+        }
+        end = start;
+    }
+
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write `content` to a fresh temp file and return its path
+    fn temp_file(name: &str, content: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "ccline-transcript-{}-{}.jsonl",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn reverse_lines(path: &Path, floor: u64) -> Vec<String> {
+        let file = File::open(path).unwrap();
+        let end = file.metadata().unwrap().len();
+        ReverseLines::new(file, floor, end).collect()
+    }
+
+    #[test]
+    fn reverse_lines_yields_newest_first() {
+        let path = temp_file("reverse", b"one\ntwo\nthree\n");
+        assert_eq!(reverse_lines(&path, 0), ["three", "two", "one"]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reverse_lines_without_trailing_newline() {
+        let path = temp_file("no-newline", b"one\ntwo");
+        assert_eq!(reverse_lines(&path, 0), ["two", "one"]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reverse_lines_stops_at_floor() {
+        let path = temp_file("floor", b"one\ntwo\nthree\n");
+        assert_eq!(reverse_lines(&path, 4), ["three", "two"]);
+        assert_eq!(reverse_lines(&path, 14), Vec::<String>::new());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reverse_lines_spans_chunk_boundaries() {
+        let long = "x".repeat(CHUNK_SIZE * 2 + 10);
+        // Put a newline exactly on the boundary of the last chunk as well
+        let tail = "y".repeat(CHUNK_SIZE - 1);
+        let path = temp_file("long", format!("first\n{}\n{}\n", long, tail).as_bytes());
+        assert_eq!(reverse_lines(&path, 0), [tail, long, "first".to_string()]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn complete_len_ignores_a_partial_last_line() {
+        let path = temp_file("complete", b"one\ntwo\npart");
+        let file = File::open(&path).unwrap();
+        assert_eq!(complete_len(&file, 12), 8);
+        assert_eq!(complete_len(&file, 8), 8);
+        assert_eq!(complete_len(&file, 3), 0);
+        std::fs::remove_file(path).unwrap();
+
+        let long = format!("{}\n{}", "a".repeat(10), "b".repeat(CHUNK_SIZE * 2));
+        let path = temp_file("complete-long", long.as_bytes());
+        let file = File::open(&path).unwrap();
+        assert_eq!(complete_len(&file, long.len() as u64), 11);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn cache_entries_are_invalidated_by_a_new_inode_or_shrinking() {
+        let path = temp_file("cache", b"one\ntwo\n");
+        let metadata = std::fs::metadata(&path).unwrap();
+        let mut cache = TranscriptCache::<u32> {
+            file_name: "unused",
+            entries: HashMap::new(),
+            inserted: HashSet::new(),
+        };
+        cache.insert(
+            &path,
+            TranscriptCacheEntry {
+                inode: file_inode(&metadata),
+                offset: 8,
+                value: 7,
+                updated_at: String::new(),
+            },
+        );
+        assert_eq!(
+            cache.get(&path, &metadata).map(|entry| entry.value),
+            Some(7)
+        );
+
+        // Appending keeps the entry
+        std::fs::write(&path, b"one\ntwo\nthree\n").unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert!(cache.get(&path, &metadata).is_some());
+
+        // Shrinking below the cached offset drops it
+        std::fs::write(&path, b"one\n").unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert!(cache.get(&path, &metadata).is_none());
+
+        // So does replacing the file, even if it is longer
+        let replacement = temp_file("cache-new", b"one\ntwo\nthree\n");
+        std::fs::rename(&replacement, &path).unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+        if cfg!(unix) {
+            assert!(cache.get(&path, &metadata).is_none());
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    fn assistant_line(id: &str, model: &str, output_tokens: u32) -> String {
+        serde_json::json!({
+            "type": "assistant",
+            "message": {
+                "id": id,
+                "model": model,
+                "usage": {"input_tokens": 10, "output_tokens": output_tokens},
+            },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn interleaved_content_blocks_are_counted_once() {
+        let mut usage = TranscriptUsage::default();
+        for line in [
+            assistant_line("msg_a", "claude-sonnet-4", 5),
+            assistant_line("msg_b", "claude-opus-4", 7),
+            assistant_line("msg_a", "claude-sonnet-4", 5),
+            r#"{"type":"user","message":{"id":"msg_c"}}"#.to_string(),
+            assistant_line("msg_b", "claude-opus-4", 7),
+            assistant_line("msg_a", "claude-sonnet-4", 5),
+        ] {
+            usage.add_line(&line);
+        }
+
+        let totals = usage.totals();
+        assert_eq!(totals.messages, 2);
+        assert_eq!(totals.input_tokens, 20);
+        assert_eq!(totals.output_tokens, 12);
+        assert_eq!(usage.by_model["claude-sonnet-4"].output_tokens, 5);
+        assert_eq!(usage.by_model["claude-opus-4"].output_tokens, 7);
+    }
+
+    #[test]
+    fn ids_before_a_cached_offset_still_dedup_after_resume() {
+        let mut usage = TranscriptUsage::default();
+        usage.add_line(&assistant_line("msg_a", "claude-sonnet-4", 5));
+
+        // Resume from the cached value, as the next render would
+        let json = serde_json::to_string(&usage).unwrap();
+        let mut resumed: TranscriptUsage = serde_json::from_str(&json).unwrap();
+        resumed.add_line(&assistant_line("msg_a", "claude-sonnet-4", 5));
+        resumed.add_line(&assistant_line("msg_b", "claude-sonnet-4", 3));
+
+        let totals = resumed.totals();
+        assert_eq!(totals.messages, 2);
+        assert_eq!(totals.output_tokens, 8);
+    }
+
+    #[test]
+    fn long_context_messages_are_tallied_separately() {
+        let mut usage = TranscriptUsage::default();
+        usage.add_line(&assistant_line("msg_a", "claude-sonnet-4", 5));
+        usage.add_line(
+            &serde_json::json!({
+                "type": "assistant",
+                "message": {
+                    "id": "msg_b",
+                    "model": "claude-sonnet-4",
+                    "usage": {
+                        "input_tokens": 1,
+                        "cache_read_input_tokens": 200_000,
+                        "output_tokens": 9,
+                    },
+                },
+            })
+            .to_string(),
+        );
+
+        assert_eq!(usage.by_model["claude-sonnet-4"].messages, 2);
+        let long_context = usage.long_context_by_model["claude-sonnet-4"];
+        assert_eq!(long_context.messages, 1);
+        assert_eq!(long_context.output_tokens, 9);
+        assert_eq!(
+            usage.by_model["claude-sonnet-4"]
+                .minus(&long_context)
+                .output_tokens,
+            5
+        );
+    }
+
+    #[test]
+    fn concurrent_saves_keep_each_others_entries() {
+        paths::use_test_dir();
+        let file_name = "transcript-cache-test.json";
+        let entry = |value: u32| TranscriptCacheEntry {
+            inode: 1,
+            offset: 0,
+            value,
+            updated_at: String::new(),
+        };
+
+        // Both load before either saves, like two statuslines rendering at once
+        let mut first = TranscriptCache::<u32>::load(file_name);
+        let mut second = TranscriptCache::<u32>::load(file_name);
+        first.insert(Path::new("/a.jsonl"), entry(1));
+        second.insert(Path::new("/b.jsonl"), entry(2));
+        first.save();
+        second.save();
+
+        let reloaded = TranscriptCache::<u32>::load(file_name);
+        assert_eq!(reloaded.entries["/a.jsonl"].value, 1);
+        assert_eq!(reloaded.entries["/b.jsonl"].value, 2);
+    }
+}